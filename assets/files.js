@@ -0,0 +1,38 @@
+// The browser side of src/app/downloader.rs: saving exports, and picking files to upload.
+
+function make_download(name, bytes, mime) {
+    const url = URL.createObjectURL(new Blob([bytes], { type: mime }));
+    const link = document.createElement("a");
+    link.href = url;
+    link.download = name;
+    link.click();
+    setTimeout(() => URL.revokeObjectURL(url), 0);
+}
+
+// Files that have been read, waiting for the app to take them.
+const uploads = [];
+let waiting = false;
+
+function choose_upload(accept) {
+    const input = document.createElement("input");
+    input.type = "file";
+    input.accept = accept;
+    input.multiple = true;
+    input.addEventListener("change", async () => {
+        for (const file of input.files) {
+            uploads.push({ name: file.name, bytes: new Uint8Array(await file.arrayBuffer()) });
+        }
+        waiting = false;
+    });
+    input.addEventListener("cancel", () => { waiting = false; });
+    waiting = true;
+    input.click();
+}
+
+function upload_waiting() {
+    return waiting;
+}
+
+function take_upload() {
+    return uploads.shift();
+}
@@ -0,0 +1,24 @@
+#!/bin/bash
+set -eu
+
+# Builds the counting library alone (no egui) for use from other pages:
+#
+#   <script src="api/burnside.js"></script>
+#   <script>
+#     burnside("api/burnside_bg.wasm").then(() => console.log(burnside.countTiles({width: 2, height: 2, colours: 3})));
+#   </script>
+
+FOLDER_NAME=${PWD##*/}
+CRATE_NAME=$FOLDER_NAME # assume crate name is the same as the folder name
+CRATE_NAME_SNAKE_CASE="${CRATE_NAME//-/_}" # for those who name crates with-kebab-case
+
+echo "Building rust…"
+BUILD=release
+cargo build --release -p ${CRATE_NAME} --lib --no-default-features --target wasm32-unknown-unknown
+
+echo "Generating JS bindings for wasm…"
+TARGET_NAME="${CRATE_NAME_SNAKE_CASE}.wasm"
+wasm-bindgen "target/wasm32-unknown-unknown/${BUILD}/${TARGET_NAME}" \
+  --out-dir docs/api --no-modules --no-modules-global burnside --no-typescript
+
+echo "Finished: docs/api/${CRATE_NAME_SNAKE_CASE}.js"
@@ -3,6 +3,7 @@
 set -eu
 
 cargo check --workspace --all-targets
+cargo check --workspace --lib --no-default-features
 cargo check --workspace --all-features --lib --target wasm32-unknown-unknown
 cargo fmt --all -- --check
 CARGO_INCREMENTAL=0 cargo clippy --workspace --all-targets --all-features --  -D warnings -W clippy::all
@@ -1,18 +1,69 @@
 use eframe::egui;
 
 mod about;
-mod bag_draw;
+mod allowed;
+mod check;
+mod csv;
+mod export;
+mod files;
+mod import;
 mod palettes;
+mod presets;
+mod print;
+mod quantise;
 mod ring;
+mod share;
+mod sprites;
+mod symbols;
+mod tabletop;
 mod tile;
+mod vision;
 use self::palettes::PaletteChooser;
 use about::About;
+use check::{DesignCheck, Upload};
+#[cfg(not(target_arch = "wasm32"))]
+use files::{ExportFormat, Exportable};
+use import::Import;
+use presets::Presets;
+use print::PrintLayout;
 use ring::Ring;
+use share::{Shared, SharedSpec};
+use sprites::SheetLayout;
+use tabletop::DeckSettings;
 use tile::Tile;
 
 mod downloader;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Layout options for the image and print exports.
+#[derive(Debug, Default, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct ExportSettings {
+    sheet: SheetLayout,
+    print: PrintLayout,
+    deck: DeckSettings,
+}
+
+impl ExportSettings {
+    pub fn settings(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Sprite sheet layout", |ui| self.sheet.settings(ui));
+        ui.collapsing("Print layout", |ui| self.print.settings(ui));
+        ui.collapsing("Tabletop Simulator deck", |ui| self.deck.settings(ui));
+    }
+}
+
+/// The images we offer to open, for palettes and pictures.
+#[cfg(not(target_arch = "wasm32"))]
+const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "gif", "bmp", "webp"];
+
+/// Something that needs a file dialog, done once the menus have closed.
+#[cfg(not(target_arch = "wasm32"))]
+enum FileAction {
+    Import,
+    ImportImage,
+    Export(ExportFormat),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 enum SelectedMode {
     Tile,
     Ring,
@@ -20,12 +71,29 @@ enum SelectedMode {
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "persistence", serde(default))]
 pub struct BurnsideApp {
     mode: SelectedMode,
     tile: Tile,
     ring: Ring,
     palette: PaletteChooser,
+    presets: Presets,
+    exports: ExportSettings,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    import: Import,
     about: About,
+    /// The URL fragment describing the settings, as of the last frame.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    fragment: String,
+    /// Designs uploaded to check against the current settings.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    check: Option<DesignCheck>,
+    /// The outcome of the last import or export, if there's anything to say.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    status: String,
+    /// The marking the next image opened is a picture for; otherwise images become palettes.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    picture_for: Option<usize>,
 }
 
 impl BurnsideApp {
@@ -33,7 +101,140 @@ impl BurnsideApp {
         // This is also where you can customized the look at feel of egui using
         // `cc.egui_ctx.set_visuals` and `cc.egui_ctx.set_fonts`.
 
-        Default::default()
+        // Load old app state (if any). Window positions are restored by eframe itself.
+        #[allow(unused_mut)]
+        let mut app: Self = Default::default();
+        #[cfg(feature = "persistence")]
+        if let Some(storage) = _cc.storage {
+            app = eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+        }
+
+        // A shared link takes priority over whatever was open last time.
+        #[cfg(target_arch = "wasm32")]
+        if let Some(shared) = Shared::decode(&_cc.integration_info.web_info.location.hash) {
+            app.set_shared(shared);
+        }
+
+        app
+    }
+
+    fn shared(&self) -> Shared {
+        let spec = match self.mode {
+            SelectedMode::Tile => SharedSpec::Tile(self.tile.spec()),
+            SelectedMode::Ring => SharedSpec::Ring(self.ring.spec()),
+        };
+        Shared {
+            spec,
+            palette: self.palette.key(),
+        }
+    }
+
+    fn set_shared(&mut self, shared: Shared) {
+        match shared.spec {
+            SharedSpec::Tile(spec) => {
+                self.mode = SelectedMode::Tile;
+                self.tile.set_spec(spec);
+            }
+            SharedSpec::Ring(spec) => {
+                self.mode = SelectedMode::Ring;
+                self.ring.set_spec(spec);
+            }
+        }
+        self.palette.select(&shared.palette);
+    }
+
+    /// Switches to the settings in an uploaded or dropped file, and checks any designs in it
+    /// against them; images become palettes or pictures instead. Gives a message saying how it
+    /// went.
+    fn import_file(&mut self, name: &str, bytes: &[u8]) -> Result<String, String> {
+        if quantise::is_image(name) {
+            return match self.picture_for.take() {
+                Some(index) => self.palette.symbols.set_picture(index, name, bytes),
+                None => self.palette.add_from_image(name, bytes),
+            };
+        }
+        let upload = Upload::parse(name, bytes, self.shared().states())?;
+        match upload.settings {
+            // Keep the current palette, so the designs look the way we're used to seeing them
+            Some(settings) => self.set_shared(Shared {
+                palette: self.palette.key(),
+                ..settings
+            }),
+            None => match self.mode {
+                SelectedMode::Tile => self.tile.fit(&upload.designs)?,
+                SelectedMode::Ring => self.ring.fit(&upload.designs)?,
+            },
+        }
+        match self.mode {
+            SelectedMode::Tile => self.tile.recompute_perms(),
+            SelectedMode::Ring => self.ring.recompute_perms(),
+        }
+        if upload.designs.is_empty() {
+            return Ok(format!("Imported {}", self.shared().describe()));
+        }
+        let check = match self.mode {
+            SelectedMode::Tile => DesignCheck::new(name.to_string(), upload.designs, &self.tile),
+            SelectedMode::Ring => DesignCheck::new(name.to_string(), upload.designs, &self.ring),
+        };
+        let summary = check.summary();
+        self.check = Some(check);
+        Ok(summary)
+    }
+
+    /// Imports the files dropped on the window, and any picked with the web build's Upload button.
+    fn receive_files(&mut self, ctx: &egui::Context) {
+        let mut files = vec![];
+        for file in ctx.input().raw.dropped_files.clone() {
+            match (file.bytes, file.path) {
+                (Some(bytes), _) => files.push(Ok((file.name, bytes.to_vec()))),
+                #[cfg(not(target_arch = "wasm32"))]
+                (None, Some(path)) => files.push(files::read(&path)),
+                _ => {}
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            if downloader::upload_waiting() {
+                ctx.request_repaint();
+            }
+            while let Some(file) = downloader::take_upload() {
+                files.push(Ok((file.name(), file.bytes())));
+            }
+        }
+        for file in files {
+            let result = file.and_then(|(name, bytes)| self.import_file(&name, &bytes));
+            let (Ok(message) | Err(message)) = result;
+            self.status = message;
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn run_file_action(&mut self, action: FileAction) {
+        let result = match action {
+            FileAction::Export(format) => {
+                let files = match self.mode {
+                    SelectedMode::Tile => self.tile.export(format, &self.palette, &self.exports),
+                    SelectedMode::Ring => self.ring.export(format, &self.palette, &self.exports),
+                };
+                files::save(files)
+            }
+            FileAction::Import => files::open("JSON or CSV", &["json", "csv"]).map(|file| {
+                let (name, bytes) = file?;
+                self.import_file(&name, &bytes)
+            }),
+            FileAction::ImportImage => {
+                let result = files::open("Image", &IMAGE_EXTENSIONS).map(|file| {
+                    let (name, bytes) = file?;
+                    self.import_file(&name, &bytes)
+                });
+                // Used or cancelled, later images go back to being palettes
+                self.picture_for = None;
+                result
+            }
+        };
+        if let Some(Ok(message) | Err(message)) = result {
+            self.status = message;
+        }
     }
 }
 
@@ -44,41 +245,93 @@ impl Default for BurnsideApp {
             tile: Tile::new(),
             ring: Ring::new(),
             palette: PaletteChooser::new(),
+            presets: Presets::new(),
+            exports: ExportSettings::default(),
+            import: Import::new(),
             about: About::new(),
+            fragment: String::new(),
+            check: None,
+            status: String::new(),
+            picture_for: None,
         }
     }
 }
 
 impl eframe::App for BurnsideApp {
-    /// Called by the framework to load old app state (if any).
-    #[cfg(feature = "persistence")]
-    fn load(&mut self, storage: &dyn epi::Storage) {
-        *self = epi::get_value(storage, epi::APP_KEY).unwrap_or_default()
-    }
-
-    /// Called by the frame work to save state before shutdown.
+    /// Called by the frame work to save state before shutdown, and every now and then.
     #[cfg(feature = "persistence")]
-    fn save(&mut self, storage: &mut dyn epi::Storage) {
-        epi::set_value(storage, epi::APP_KEY, self);
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, self);
     }
 
     /// Called each time the UI needs repainting, which may be many times per second.
     /// Put your widgets into a `SidePanel`, `TopPanel`, `CentralPanel`, `Window` or `Area`.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let shared = self.shared();
+        if let Some(preset) = self.presets.show(ctx, shared) {
+            self.set_shared(preset);
+        }
+        if let Some(imported) = self.import.show(ctx) {
+            self.set_shared(imported.settings);
+            self.palette
+                .restore(&imported.palette, &imported.palette_names);
+        }
+        self.receive_files(ctx);
+        self.palette.symbols.prepare(ctx);
+        if let Some(check) = &self.check {
+            if !check.show(ctx, &self.palette.preview()) {
+                self.check = None;
+            }
+        }
+
+        let fragment = self.shared().encode();
+        if fragment != self.fragment {
+            #[cfg(target_arch = "wasm32")]
+            share::show_in_address_bar(&fragment);
+            self.fragment = fragment;
+        }
+
         let BurnsideApp {
             mode,
             tile,
             ring,
             palette,
+            presets,
+            exports,
+            import,
             about,
+            fragment,
+            check,
+            status,
+            picture_for,
         } = self;
 
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut file_action = None;
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             // The top panel is often a good place for a menu bar:
             egui::menu::bar(ui, |ui| {
                 egui::menu::bar(ui, |ui| {
                     #[cfg(not(target_arch = "wasm32"))]
                     ui.menu_button("File", |ui| {
+                        if ui.button("Import…").clicked() {
+                            ui.close_menu();
+                            file_action = Some(FileAction::Import);
+                        }
+                        if ui.button("Paste settings…").clicked() {
+                            ui.close_menu();
+                            import.open();
+                        }
+                        ui.menu_button("Export", |ui| {
+                            for format in ExportFormat::ALL {
+                                if ui.button(format!("{}…", format.label())).clicked() {
+                                    ui.close_menu();
+                                    file_action = Some(FileAction::Export(format));
+                                }
+                            }
+                        });
+                        ui.separator();
                         if ui.button("Quit").clicked() {
                             _frame.close();
                         }
@@ -86,23 +339,82 @@ impl eframe::App for BurnsideApp {
                     if ui.button("Palette").clicked() {
                         palette.open();
                     }
+                    if ui.button("Presets").clicked() {
+                        presets.open();
+                    }
+                    #[cfg(target_arch = "wasm32")]
+                    if ui.button("Import").clicked() {
+                        import.open();
+                    }
+                    #[cfg(target_arch = "wasm32")]
+                    if ui
+                        .button("Upload…")
+                        .on_hover_text(
+                            "Check a JSON or CSV file of designs against these settings. \
+                             You can also drop files on the page.",
+                        )
+                        .clicked()
+                    {
+                        downloader::choose_upload(".json,.csv");
+                    }
+                    if ui
+                        .button("Copy link")
+                        .on_hover_text("Copy a link to these settings")
+                        .clicked()
+                    {
+                        ui.output().copied_text = share::link(fragment);
+                    }
                     if ui.button("Help / about").clicked() {
                         about.open();
                     }
                 });
             });
         });
-        palette.choose(ctx);
+        if !status.is_empty() {
+            egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(status.as_str());
+                    if ui.small_button("✖").clicked() {
+                        status.clear();
+                    }
+                });
+            });
+        }
+        // Which image to ask for, if any: `None` for a palette, or the marking for a picture
+        let mut open_image = None;
+        if palette.choose(ctx) {
+            open_image = Some(None);
+        }
         about.show(ctx);
         let mut changed = false;
+        let old_mode = *mode;
         egui::SidePanel::left("side_panel").show(ctx, |ui| {
             ui.radio_value(mode, SelectedMode::Tile, "Tiles");
             ui.radio_value(mode, SelectedMode::Ring, "Rings");
             changed = match mode {
-                SelectedMode::Tile => tile.settings(ui),
-                SelectedMode::Ring => ring.settings(ui),
+                SelectedMode::Tile => tile.settings(ui, palette.choice().len()),
+                SelectedMode::Ring => ring.settings(ui, palette.choice().len()),
+            };
+            ui.separator();
+            let n_colours = match mode {
+                SelectedMode::Tile => tile.spec().n_colours,
+                SelectedMode::Ring => ring.spec().n_colours,
             };
+            palette.vision_settings(ui, n_colours as usize);
+            ui.separator();
+            if let Some(index) = palette.symbols.settings(ui, n_colours as usize) {
+                open_image = Some(Some(index));
+            }
         });
+        if let Some(target) = open_image {
+            *picture_for = target;
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                file_action = Some(FileAction::ImportImage);
+            }
+            #[cfg(target_arch = "wasm32")]
+            downloader::choose_upload("image/*");
+        }
 
         if changed {
             match *mode {
@@ -110,16 +422,121 @@ impl eframe::App for BurnsideApp {
                 SelectedMode::Ring => ring.recompute_perms(),
             }
         }
+        if let Some(check) = check.as_mut().filter(|_| changed || *mode != old_mode) {
+            match *mode {
+                SelectedMode::Tile => check.recheck(tile),
+                SelectedMode::Ring => check.recheck(ring),
+            }
+        }
 
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::ScrollArea::vertical()
                 .auto_shrink([false; 2])
                 .show(ui, |ui| match *mode {
-                    SelectedMode::Tile => tile.render_results(palette.choice, ui),
-                    SelectedMode::Ring => ring.render_results(palette.choice, ui),
+                    SelectedMode::Tile => tile.render_results(palette, exports, ui),
+                    SelectedMode::Ring => ring.render_results(palette, exports, ui),
                 });
         });
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(action) = file_action {
+            self.run_file_action(action);
+        }
     }
 }
 
 // ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    #[cfg(feature = "persistence")]
+    fn test_state_round_trip() {
+        let mut app = BurnsideApp {
+            mode: SelectedMode::Ring,
+            ..Default::default()
+        };
+        app.tile.recompute_perms();
+        let saved = serde_json::to_string(&app).unwrap();
+        let loaded: BurnsideApp = serde_json::from_str(&saved).unwrap();
+        assert_eq!(loaded.mode, SelectedMode::Ring);
+        assert_eq!(loaded.tile.spec(), app.tile.spec());
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), saved);
+
+        // Settings saved by older versions are missing newer fields
+        let loaded: BurnsideApp = serde_json::from_str(r#"{"mode": "Tile"}"#).unwrap();
+        assert_eq!(loaded.ring.spec(), Ring::new().spec());
+    }
+
+    #[test]
+    fn test_set_shared() {
+        let mut app = BurnsideApp::default();
+        let shared =
+            Shared::decode("mode=ring&beads=5&colours=4&transforms=none&palette=3").unwrap();
+        app.set_shared(shared.clone());
+        assert_eq!(app.mode, SelectedMode::Ring);
+        assert_eq!(app.shared(), shared);
+    }
+
+    #[test]
+    fn test_too_big_to_list() {
+        use check::Canonical;
+        use files::Exportable;
+        // A rectangle bigger than a permutation holds is counted, but not listed
+        let mut tile = Tile::new();
+        let spec = crate::tile::TileSpec {
+            width: 6,
+            height: 5,
+            n_colours: 1,
+            ..tile.spec()
+        };
+        tile.set_spec(spec);
+        tile.recompute_perms();
+        assert_eq!(tile.n_distinct(), 1);
+        assert_eq!(tile.export_csv().lines().count(), 1);
+        assert!(tile.canonical(&vec![vec![0; 6]; 5]).is_err());
+        assert!(tile.breakdown().group_order() > 0);
+    }
+
+    #[test]
+    fn test_load_after_error() {
+        use check::Canonical;
+        // Settings loaded after ones that couldn't be counted get counted
+        let settings_changed = |settings: &mut dyn FnMut(&mut egui::Ui) -> bool| {
+            let mut changed = false;
+            let _ = egui::Context::default().run(Default::default(), |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| changed = settings(ui));
+            });
+            changed
+        };
+
+        let mut tile = Tile::new();
+        let spec = tile.spec();
+        tile.set_spec(crate::tile::TileSpec {
+            width: 8,
+            height: 8,
+            n_colours: 3,
+            ..spec
+        });
+        tile.recompute_perms();
+        assert_eq!(tile.n_distinct(), 0);
+        tile.set_spec(spec);
+        assert!(settings_changed(&mut |ui| tile.settings(ui, 8)));
+        tile.recompute_perms();
+        assert_eq!(tile.n_distinct(), crate::tile::count(&spec).unwrap());
+
+        let mut ring = Ring::new();
+        let spec = ring.spec();
+        ring.set_spec(crate::ring::RingSpec {
+            n_beads: 60,
+            ..spec
+        });
+        ring.recompute_perms();
+        assert_eq!(ring.n_distinct(), 0);
+        ring.set_spec(spec);
+        assert!(settings_changed(&mut |ui| ring.settings(ui, 8)));
+        ring.recompute_perms();
+        assert_eq!(ring.n_distinct(), crate::ring::count(&spec).unwrap());
+    }
+}
@@ -1,11 +1,13 @@
 use eframe::egui;
 
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 pub struct About {
     open: bool,
 }
 impl About {
     pub fn new() -> Self {
-        About { open: false }
+        Default::default()
     }
     pub fn open(&mut self) {
         self.open = true;
@@ -18,6 +20,7 @@ impl About {
 
             ui.label("Given a necklace with beads that can rotate freely around the string, and N colours of beads, how many distinct necklaces are there?");
             ui.label("Given a rectangular X×Y game tile with N possible markings in each position, how many distinct tiles are there? What if you can rotate or flip the tiles?");
+            ui.label("Markings are shown in the palette's colours, and can also carry shapes, letters, pips or pictures of your own: see Symbols in the side panel.");
             ui.label("Burnside's Lemma is a mathematical result that provides a tool to answer these, and similar questions.");
             ui.label("This calculator aims to be a useful tool for game designers, and those just curious about distinct combinations.");
             ui.separator();
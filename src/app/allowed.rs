@@ -0,0 +1,68 @@
+//! Picking which colours a cell of the shape editor or a bead of the bead editor may take.
+use eframe::egui;
+
+use crate::ColourSet;
+
+/// `allowed` cut down to the first `n_colours`, which is what a spec can take: all of them
+/// becomes any colour, and so does none, so fewer colours never leave a cell with nothing.
+pub fn within(allowed: ColourSet, n_colours: u64) -> ColourSet {
+    let colours = ColourSet::from_colours(
+        allowed
+            .colours()
+            .filter(|&colour| (colour as u64) < n_colours),
+    );
+    if colours.is_empty() || colours == ColourSet::from_colours(0..n_colours as u8) {
+        ColourSet::ANY
+    } else {
+        colours
+    }
+}
+
+/// Adds a right-click menu to `response` for ticking which of the first `n_colours` `allowed`
+/// includes; returns whether it changed. The last colour can't be unticked.
+pub fn colours_menu(response: egui::Response, n_colours: u64, allowed: &mut ColourSet) -> bool {
+    let mut changed = false;
+    response.context_menu(|ui| {
+        let current = within(*allowed, n_colours);
+        for colour in 0..n_colours as u8 {
+            let mut ticked = current.is_any() || current.contains(colour);
+            let letter = ((b'A' + colour) as char).to_string();
+            if ui.checkbox(&mut ticked, letter).changed() {
+                let all = ColourSet::from_colours(0..n_colours as u8);
+                let base = if current.is_any() { all } else { current };
+                let toggled = base.toggled(colour);
+                if !toggled.is_empty() {
+                    *allowed = within(toggled, n_colours);
+                    changed = true;
+                }
+            }
+        }
+        if ui.button("Any colour").clicked() {
+            changed |= !allowed.is_any();
+            *allowed = ColourSet::ANY;
+            ui.close_menu();
+        }
+    });
+    changed
+}
+
+/// Writes the letters of the colours `allowed` includes over `rect`, unless it's any colour.
+pub fn paint_allowed(ui: &egui::Ui, rect: egui::Rect, n_colours: u64, allowed: ColourSet) {
+    let allowed = within(allowed, n_colours);
+    if allowed.is_any() {
+        return;
+    }
+    let letters: String = allowed
+        .colours()
+        .map(|colour| (b'A' + colour) as char)
+        .collect();
+    // Shrink the letters to fit several in a cell
+    let size = if letters.len() > 1 { 8. } else { 12. };
+    ui.painter().text(
+        rect.center(),
+        egui::Align2::CENTER_CENTER,
+        letters,
+        egui::FontId::proportional(size),
+        ui.visuals().strong_text_color(),
+    );
+}
@@ -0,0 +1,469 @@
+//! Checking designs from elsewhere against the distinct designs for the current settings: which
+//! orbit each one falls in, which share an orbit, and which orbits nobody sent.
+use std::collections::{hash_map::Entry, HashMap};
+
+use eframe::egui;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{
+    csv::{colour_letter, parse_design},
+    export::import_json,
+    share::Shared,
+    sprites::{paint, Mark},
+    symbols::Look,
+};
+use crate::tile::GAP;
+
+/// Designs are drawn this many points across.
+const SIZE: f32 = 40.;
+
+/// What was in an uploaded file.
+#[derive(Debug, PartialEq)]
+pub struct Upload {
+    /// The settings the designs were made with, if the file says.
+    pub settings: Option<Shared>,
+    /// Each design as rows of colour indices; a ring is a single row.
+    pub designs: Vec<Vec<Vec<u8>>>,
+}
+
+/// A design in a JSON file: as in an export, or just its colours.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FileDesign {
+    Design { colours: Colours },
+    Colours(Colours),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Colours {
+    Rows(Vec<Vec<u8>>),
+    Beads(Vec<u8>),
+    Letters(String),
+}
+
+impl Upload {
+    /// Reads a JSON export (or just its settings, or just a list of designs), or a CSV with a
+    /// `design` column like the CSV export's. The file's extension says which. Designs written as
+    /// letters have an orientation after each one if markings can sit `states` ways round, unless
+    /// the file's own settings say otherwise.
+    pub fn parse(name: &str, bytes: &[u8], states: u64) -> Result<Self, String> {
+        if name.to_lowercase().ends_with(".csv") {
+            let csv = std::str::from_utf8(bytes).map_err(|e| e.to_string())?;
+            Self::parse_csv(csv, states)
+        } else {
+            Self::parse_json(bytes, states)
+        }
+    }
+
+    fn parse_json(json: &[u8], states: u64) -> Result<Self, String> {
+        let value: Value = serde_json::from_slice(json).map_err(|e| e.to_string())?;
+        let (settings, designs) = match value {
+            Value::Array(designs) => (None, designs),
+            Value::Object(mut object) => {
+                let settings = match object.contains_key("mode") {
+                    true => Some(import_json(json)?.settings),
+                    false => None,
+                };
+                let designs = match object.remove("designs") {
+                    Some(Value::Array(designs)) => designs,
+                    _ => vec![],
+                };
+                (settings, designs)
+            }
+            _ => return Err("Expected an export, settings or a list of designs".to_string()),
+        };
+        let states = settings
+            .as_ref()
+            .map_or(states, |settings| settings.states());
+        let designs = designs
+            .into_iter()
+            .enumerate()
+            .map(|(i, design)| {
+                let colours = match serde_json::from_value(design) {
+                    Ok(FileDesign::Design { colours } | FileDesign::Colours(colours)) => colours,
+                    Err(_) => return Err(format!("Design {i} has no colours")),
+                };
+                match colours {
+                    Colours::Rows(rows) => Ok(rows),
+                    Colours::Beads(beads) => Ok(vec![beads]),
+                    Colours::Letters(letters) => parse_design(&letters, states).ok_or_else(|| {
+                        format!("Design {i}: {letters:?} isn't a design like ABB/CAA")
+                    }),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if settings.is_none() && designs.is_empty() {
+            return Err("No settings or designs found".to_string());
+        }
+        Ok(Upload { settings, designs })
+    }
+
+    fn parse_csv(csv: &str, states: u64) -> Result<Self, String> {
+        let fields = |line: &str| -> Vec<String> {
+            line.split(',')
+                .map(|field| field.trim().trim_matches('"').to_string())
+                .collect()
+        };
+        let mut lines = csv
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .peekable();
+        // Use the `design` column if there's a header naming one, or the first column otherwise
+        let mut column = 0;
+        if let Some((_, header)) = lines.peek() {
+            if let Some(i) = fields(header)
+                .iter()
+                .position(|field| field.eq_ignore_ascii_case("design"))
+            {
+                column = i;
+                lines.next();
+            }
+        }
+        let designs = lines
+            .map(|(n, line)| {
+                let field = fields(line).into_iter().nth(column).unwrap_or_default();
+                parse_design(&field, states)
+                    .ok_or_else(|| format!("Line {}: {field:?} isn't a design like ABB/CAA", n + 1))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if designs.is_empty() {
+            return Err("No designs found".to_string());
+        }
+        Ok(Upload {
+            settings: None,
+            designs,
+        })
+    }
+}
+
+/// The width and height every one of `designs` has, if they all agree.
+pub fn common_shape(designs: &[Vec<Vec<u8>>]) -> Option<(usize, usize)> {
+    let first = designs.first()?;
+    let shape = (first.first()?.len(), first.len());
+    designs
+        .iter()
+        .all(|rows| rows.len() == shape.1 && rows.iter().all(|row| row.len() == shape.0))
+        .then_some(shape)
+}
+
+/// The fewest colours that would cover every one of `designs`, with markings that can sit
+/// `states` ways round.
+pub fn colours_used(designs: &[Vec<Vec<u8>>], states: u64) -> u64 {
+    designs
+        .iter()
+        .flatten()
+        .flatten()
+        .filter(|&&value| value != GAP)
+        .map(|&value| value as u64 / states + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+fn colour_name(colour: u8) -> String {
+    if colour < 26 {
+        colour_letter(colour).to_string()
+    } else {
+        format!("#{colour}")
+    }
+}
+
+/// Why `rows` can't be coloured from `n_colours` colours, each used at most `max_repeats` times,
+/// with markings that can sit `states` ways round.
+pub fn check_colours(
+    rows: &[Vec<u8>],
+    n_colours: u64,
+    states: u64,
+    max_repeats: Option<u64>,
+) -> Result<(), String> {
+    let mut used = vec![0; n_colours as usize];
+    for &value in rows.iter().flatten().filter(|&&value| value != GAP) {
+        let colour = value / states as u8;
+        match used.get_mut(colour as usize) {
+            Some(used) => *used += 1,
+            None => {
+                return Err(format!(
+                    "Uses colour {}, but there are only {n_colours}",
+                    colour_name(colour)
+                ))
+            }
+        }
+    }
+    if let Some(max_repeats) = max_repeats {
+        if let Some((colour, used)) = used
+            .iter()
+            .enumerate()
+            .find(|(_, &used)| used > max_repeats)
+        {
+            return Err(format!(
+                "Uses {} {used} times, but at most {max_repeats} are allowed",
+                colour_name(colour as u8)
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The distinct designs for the current settings, to check others against.
+pub trait Canonical {
+    /// The design listed for the orbit `rows` is in, or why `rows` isn't one of these designs.
+    fn canonical(&self, rows: &[Vec<u8>]) -> Result<Vec<Vec<u8>>, String>;
+    /// Every distinct design in the order they're listed, or `None` if there are too many to list.
+    fn distinct(&self) -> Option<Vec<Vec<Vec<u8>>>>;
+    fn n_distinct(&self) -> u64;
+    /// How to draw a design.
+    fn marks(&self) -> Marks;
+}
+
+/// Draws a design, as given by `Canonical::marks`.
+pub type Marks = Box<dyn Fn(&[Vec<u8>], &Look<'_>) -> Vec<Mark>>;
+
+/// Where one uploaded design landed.
+pub struct Checked {
+    rows: Vec<Vec<u8>>,
+    /// The listed design for its orbit, with its index in the list if there is one.
+    orbit: Result<(Option<usize>, Vec<Vec<u8>>), String>,
+    /// The first uploaded design in the same orbit, if it's not this one.
+    duplicate_of: Option<usize>,
+}
+
+pub struct DesignCheck {
+    /// The name of the file the designs came from.
+    source: String,
+    designs: Vec<Vec<Vec<u8>>>,
+    checked: Vec<Checked>,
+    /// The listed designs whose orbits none of the uploaded designs are in, by index.
+    missing: Option<Vec<(usize, Vec<Vec<u8>>)>>,
+    n_distinct: u64,
+    marks: Marks,
+}
+
+impl DesignCheck {
+    pub fn new<C: Canonical>(source: String, designs: Vec<Vec<Vec<u8>>>, canonical: &C) -> Self {
+        let mut check = DesignCheck {
+            source,
+            designs,
+            checked: vec![],
+            missing: None,
+            n_distinct: 0,
+            marks: canonical.marks(),
+        };
+        check.recheck(canonical);
+        check
+    }
+
+    /// Checks the designs again, after the settings have changed.
+    pub fn recheck<C: Canonical>(&mut self, canonical: &C) {
+        let distinct = canonical.distinct();
+        let index: HashMap<_, _> = distinct
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, rows)| (rows, i))
+            .collect();
+        let mut first_in_orbit = HashMap::new();
+        self.checked = self
+            .designs
+            .iter()
+            .enumerate()
+            .map(|(i, rows)| {
+                let orbit = canonical
+                    .canonical(rows)
+                    .map(|listed| (index.get(&listed).copied(), listed));
+                let duplicate_of = match &orbit {
+                    Ok((_, listed)) => match first_in_orbit.entry(listed.clone()) {
+                        Entry::Occupied(first) => Some(*first.get()),
+                        Entry::Vacant(first) => {
+                            first.insert(i);
+                            None
+                        }
+                    },
+                    Err(_) => None,
+                };
+                Checked {
+                    rows: rows.clone(),
+                    orbit,
+                    duplicate_of,
+                }
+            })
+            .collect();
+        self.missing = distinct.as_ref().map(|distinct| {
+            distinct
+                .iter()
+                .cloned()
+                .enumerate()
+                .filter(|(_, rows)| !first_in_orbit.contains_key(rows))
+                .collect()
+        });
+        self.n_distinct = canonical.n_distinct();
+        self.marks = canonical.marks();
+    }
+
+    /// How the designs matched up, in a line.
+    pub fn summary(&self) -> String {
+        let valid = self.checked.iter().filter(|checked| checked.orbit.is_ok());
+        let orbits = valid
+            .clone()
+            .filter(|checked| checked.duplicate_of.is_none());
+        let mut summary = format!(
+            "{}: {} designs, in {} of the {} orbits",
+            self.source,
+            self.checked.len(),
+            orbits.count(),
+            self.n_distinct
+        );
+        let duplicates = valid
+            .filter(|checked| checked.duplicate_of.is_some())
+            .count();
+        if duplicates > 0 {
+            summary.push_str(&format!("; {duplicates} repeat an earlier design's orbit"));
+        }
+        let invalid = self.checked.iter().filter(|c| c.orbit.is_err()).count();
+        if invalid > 0 {
+            summary.push_str(&format!("; {invalid} don't fit these settings"));
+        }
+        summary
+    }
+
+    /// Shows the uploaded designs beside the orbits they're in; returns whether it's still open.
+    pub fn show(&self, ctx: &egui::Context, look: &Look<'_>) -> bool {
+        let mut open = true;
+        let marks = &self.marks;
+        egui::Window::new("Uploaded designs")
+            .open(&mut open)
+            .default_width(360.)
+            .show(ctx, |ui| {
+                ui.label(self.summary());
+                egui::ScrollArea::vertical()
+                    .max_height(400.)
+                    .show(ui, |ui| {
+                        egui::Grid::new("uploaded_designs")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.strong("#");
+                                ui.strong("Design");
+                                ui.strong("Orbit");
+                                ui.end_row();
+                                for (i, checked) in self.checked.iter().enumerate() {
+                                    ui.label(i.to_string());
+                                    paint(ui, &marks(&checked.rows, look), SIZE);
+                                    match &checked.orbit {
+                                        Ok((index, listed)) => {
+                                            ui.horizontal(|ui| {
+                                                paint(ui, &marks(listed, look), SIZE);
+                                                if let Some(index) = index {
+                                                    ui.label(format!("#{index}"));
+                                                }
+                                                if let Some(first) = checked.duplicate_of {
+                                                    ui.label(format!("Same orbit as #{first}"));
+                                                }
+                                            });
+                                        }
+                                        Err(e) => {
+                                            ui.colored_label(egui::Color32::RED, e);
+                                        }
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                match &self.missing {
+                    Some(missing) if !missing.is_empty() => {
+                        ui.collapsing(format!("{} orbits not uploaded", missing.len()), |ui| {
+                            ui.horizontal_wrapped(|ui| {
+                                for (index, rows) in missing {
+                                    paint(ui, &marks(rows, look), SIZE)
+                                        .on_hover_text(format!("#{index}"));
+                                }
+                            });
+                        });
+                    }
+                    Some(_) => {
+                        ui.label("Every orbit is covered.");
+                    }
+                    None => {
+                        ui.label("Too many designs to list the ones not uploaded.");
+                    }
+                }
+            });
+        open
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::app::{share::SharedSpec, tile::Tile};
+    use crate::tile::{AllowedTransformFamiles, Orientation, TileSpec};
+    #[test]
+    fn test_check() {
+        let csv =
+            "index,design,stabilizer\n0,AB/BA,2\n1,ba/ab,2\n2,AA/AB,1\n3,AAA/BBB,1\n4,AC/AA,1\n";
+        let upload = Upload::parse("partner.csv", csv.as_bytes(), 1).unwrap();
+        assert_eq!(upload.settings, None);
+        assert_eq!(upload.designs.len(), 5);
+        assert_eq!(common_shape(&upload.designs), None);
+        assert_eq!(colours_used(&upload.designs, 1), 3);
+        assert!(Upload::parse("bad.csv", b"design\nAB/C1", 1).is_err());
+
+        let json = br#"{"mode": "tile", "width": 2, "height": 2, "colours": 2,
+            "designs": [{"colours": [[0, 1], [1, 0]]}, "AB/AB", [[1, 1], [1, 0]]]}"#;
+        let upload = Upload::parse("partner.json", json, 1).unwrap();
+        assert!(matches!(
+            upload.settings,
+            Some(Shared {
+                spec: SharedSpec::Tile(TileSpec { width: 2, .. }),
+                ..
+            })
+        ));
+        assert_eq!(upload.designs[1], [[0, 1], [0, 1]]);
+        assert!(Upload::parse("empty.json", b"{}", 1).is_err());
+
+        // 2×2 tiles in two colours under rotation: AA/AA, AA/AB, AA/BB, AB/BA, AB/BB, BB/BB
+        let mut tile = Tile::new();
+        tile.set_spec(TileSpec {
+            width: 2,
+            height: 2,
+            n_colours: 2,
+            max_repeats: None,
+            allowed_xforms: AllowedTransformFamiles::Rotate,
+            orientation: Orientation::Symmetric,
+            two_sided: false,
+            shape: None,
+        });
+        tile.recompute_perms();
+        let designs = Upload::parse("partner.csv", csv.as_bytes(), 1)
+            .unwrap()
+            .designs;
+        let check = DesignCheck::new("partner.csv".to_string(), designs, &tile);
+        let orbits: Vec<_> = check
+            .checked
+            .iter()
+            .map(|checked| checked.orbit.as_ref().map(|(index, _)| *index))
+            .collect();
+        assert_eq!(orbits[0], Ok(Some(3)));
+        assert_eq!(orbits[1], Ok(Some(3)));
+        assert_eq!(check.checked[1].duplicate_of, Some(0));
+        assert_eq!(orbits[2], Ok(Some(1)));
+        assert!(orbits[3].is_err());
+        assert_eq!(
+            check.checked[4].orbit,
+            Err("Uses colour C, but there are only 2".to_string())
+        );
+        let missing: Vec<_> = check
+            .missing
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|m| m.0)
+            .collect();
+        assert_eq!(missing, [0, 2, 4, 5]);
+        assert_eq!(
+            check.summary(),
+            "partner.csv: 5 designs, in 2 of the 6 orbits; 1 repeat an earlier design's orbit; \
+             2 don't fit these settings"
+        );
+    }
+}
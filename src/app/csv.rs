@@ -0,0 +1,186 @@
+//! Spreadsheet-friendly exports: one row per design, and the Burnside table behind the count.
+use std::fmt::Write;
+
+use crate::{tile::GAP, Breakdown};
+
+/// The letter standing for colour `index` in design strings: A for the first colour, and so on.
+pub fn colour_letter(index: u8) -> char {
+    (b'A' + index) as char
+}
+
+/// A design as letters, one row at a time, e.g. `ABBA/BAAB`. When markings can sit `states`
+/// ways round, each letter is followed by the marking's orientation, e.g. `A0B3/B1A2`. Gaps in a
+/// tile's shape are dots, e.g. `AB./.BA`.
+pub fn design_string(rows: &[Vec<u8>], states: u64) -> String {
+    let states = states as u8;
+    let rows: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            let mut cells = String::new();
+            for &value in row {
+                if value == GAP {
+                    cells.push('.');
+                    continue;
+                }
+                cells.push(colour_letter(value / states));
+                if states > 1 {
+                    let _ = write!(cells, "{}", value % states);
+                }
+            }
+            cells
+        })
+        .collect();
+    rows.join("/")
+}
+
+/// The colour index a letter stands for, either case; the inverse of `colour_letter`.
+pub fn colour_index(letter: char) -> Option<u8> {
+    letter
+        .is_ascii_alphabetic()
+        .then(|| letter.to_ascii_uppercase() as u8 - b'A')
+}
+
+/// Reads a design written by `design_string`.
+pub fn parse_design(design: &str, states: u64) -> Option<Vec<Vec<u8>>> {
+    design
+        .split('/')
+        .map(|row| {
+            let mut chars = row.trim().chars();
+            let mut cells = vec![];
+            while let Some(letter) = chars.next() {
+                if letter == '.' {
+                    cells.push(GAP);
+                    continue;
+                }
+                let colour = colour_index(letter)?;
+                let state = match states {
+                    1 => 0,
+                    _ => chars
+                        .next()?
+                        .to_digit(10)
+                        .filter(|&state| (state as u64) < states)?,
+                };
+                cells.push(colour * states as u8 + state as u8);
+            }
+            (!cells.is_empty()).then_some(cells)
+        })
+        .collect()
+}
+
+/// One row per design, given as rows of colour indices (with orientations, if markings can sit
+/// `states` ways round) with its stabilizer order, with how many times each colour is used.
+pub fn designs_csv(
+    n_colours: u64,
+    states: u64,
+    group_order: u64,
+    designs: impl Iterator<Item = (Vec<Vec<u8>>, u64)>,
+) -> String {
+    let mut csv = String::from("index,design,stabilizer,orbit");
+    for colour in 0..n_colours as u8 {
+        let _ = write!(csv, ",{}", colour_letter(colour));
+    }
+    csv.push('\n');
+    for (index, (rows, stabilizer)) in designs.enumerate() {
+        let orbit = group_order / stabilizer;
+        let _ = write!(
+            csv,
+            "{index},{},{stabilizer},{orbit}",
+            design_string(&rows, states)
+        );
+        for colour in 0..n_colours {
+            let used = rows
+                .iter()
+                .flatten()
+                .filter(|&&value| value as u64 / states == colour)
+                .count();
+            let _ = write!(csv, ",{used}");
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Each kind of transform with how many colourings it fixes, then the totals and the count.
+pub fn breakdown_csv(breakdown: &Breakdown) -> String {
+    let mut csv = String::from("transform,elements,fixed,total fixed\n");
+    for term in &breakdown.terms {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{}",
+            term.transform,
+            term.elements,
+            term.fixed,
+            term.total_fixed()
+        );
+    }
+    let _ = writeln!(
+        csv,
+        "Total,{},,{}",
+        breakdown.group_order(),
+        breakdown.total_fixed()
+    );
+    let _ = writeln!(csv, "Distinct designs,,,{}", breakdown.count());
+    csv
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tile::{self, AllowedTransformFamiles, Orientation, TileSpec};
+    #[test]
+    fn test_csv() {
+        assert_eq!(design_string(&[vec![0, 1, 1], vec![2, 0, 0]], 1), "ABB/CAA");
+        assert_eq!(
+            parse_design("ABB/caa", 1),
+            Some(vec![vec![0, 1, 1], vec![2, 0, 0]])
+        );
+        assert_eq!(parse_design("AB1", 1), None);
+        assert_eq!(parse_design("", 1), None);
+        // Arrows, with their orientations
+        assert_eq!(design_string(&[vec![0, 7], vec![9, 2]], 4), "A0B3/C1A2");
+        assert_eq!(
+            parse_design("A0B3/c1a2", 4),
+            Some(vec![vec![0, 7], vec![9, 2]])
+        );
+        assert_eq!(parse_design("A0B4", 4), None);
+        assert_eq!(parse_design("A0B", 4), None);
+        // Gaps in a shaped tile
+        assert_eq!(design_string(&[vec![0, GAP], vec![5, 2]], 4), "A0./B1A2");
+        assert_eq!(
+            parse_design("A0./B1A2", 4),
+            Some(vec![vec![0, GAP], vec![5, 2]])
+        );
+
+        let spec = TileSpec {
+            width: 2,
+            height: 2,
+            n_colours: 3,
+            max_repeats: None,
+            allowed_xforms: AllowedTransformFamiles::Rotate,
+            orientation: Orientation::Symmetric,
+            two_sided: false,
+            shape: None,
+        };
+        let designs = spec
+            .permutations()
+            .into_iter()
+            .map(|perm| (spec.rows(&perm), spec.stabilizer(&perm)));
+        let csv = designs_csv(3, 1, 4, designs);
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 25);
+        assert_eq!(lines[0], "index,design,stabilizer,orbit,A,B,C");
+        assert_eq!(lines[1], "0,AA/AA,4,1,4,0,0");
+        assert_eq!(lines[2], "1,AA/AB,1,4,3,1,0");
+
+        let csv = breakdown_csv(&tile::breakdown(&spec).unwrap());
+        assert_eq!(
+            csv,
+            "transform,elements,fixed,total fixed\n\
+             Identity,1,81,81\n\
+             Rotate 90°/270°,2,3,6\n\
+             Rotate 180°,1,9,9\n\
+             Total,4,,96\n\
+             Distinct designs,,,24\n"
+        );
+    }
+}
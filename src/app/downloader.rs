@@ -1,6 +1,26 @@
+//! Moving files between the web build and the browser. The JavaScript side lives in
+//! `assets/files.js`; dropped files arrive through egui instead.
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
 extern "C" {
     pub fn make_download(name: &str, bytes: &[u8], mime: &str);
 }
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    /// A file the user picked after `choose_upload`.
+    pub type UploadedFile;
+    #[wasm_bindgen(method, getter)]
+    pub fn name(this: &UploadedFile) -> String;
+    #[wasm_bindgen(method, getter)]
+    pub fn bytes(this: &UploadedFile) -> Vec<u8>;
+
+    /// Opens the browser's file picker for files matching `accept`, e.g. `.json,.csv`.
+    pub fn choose_upload(accept: &str);
+    /// Whether the file picker is open, or picked files are still being read.
+    pub fn upload_waiting() -> bool;
+    /// The next picked file, once it's been read.
+    pub fn take_upload() -> Option<UploadedFile>;
+}
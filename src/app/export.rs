@@ -0,0 +1,224 @@
+//! The JSON format for exported designs: the settings that produced them, how they were counted,
+//! and each design with the size of its orbit.
+use eframe::egui::Color32;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    palettes::{colour, PaletteChooser},
+    share::Shared,
+};
+use crate::Breakdown;
+
+/// Bump this whenever a change would confuse an older reader.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Export<D> {
+    pub schema_version: u32,
+    #[serde(flatten)]
+    pub settings: Shared,
+    /// The colour each index stands for, as `[r, g, b]`.
+    pub palette_rgb: Vec<[u8; 3]>,
+    /// The name of each colour, if the palette has names.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub palette_names: Vec<String>,
+    pub count: u64,
+    pub group_order: u64,
+    pub breakdown: Breakdown,
+    /// Empty when there were too many designs to list.
+    pub designs: Vec<Design<D>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Design<D> {
+    pub index: usize,
+    pub colours: D,
+    /// How many transforms leave the design unchanged.
+    pub stabilizer_size: u64,
+    /// How many different-looking designs it's equivalent to, itself included.
+    pub orbit_size: u64,
+}
+
+impl<D: Serialize> Export<D> {
+    /// `designs` gives each design with its stabilizer size; `palettes` has the palette
+    /// `settings` refers to chosen.
+    pub fn new(
+        settings: Shared,
+        palettes: &PaletteChooser,
+        breakdown: Breakdown,
+        designs: impl Iterator<Item = (D, u64)>,
+    ) -> Self {
+        let group_order = breakdown.group_order();
+        let n_colours = settings.n_colours() as u8;
+        let names = palettes.colour_names();
+        Export {
+            schema_version: SCHEMA_VERSION,
+            settings,
+            palette_rgb: (0..n_colours)
+                .map(|i| colour(palettes.choice(), i))
+                .map(|colour| [colour.r(), colour.g(), colour.b()])
+                .collect(),
+            palette_names: match names.iter().any(|name| !name.is_empty()) {
+                true => (0..n_colours as usize)
+                    .map(|i| names.get(i).cloned().unwrap_or_default())
+                    .collect(),
+                false => vec![],
+            },
+            count: breakdown.count(),
+            group_order,
+            breakdown,
+            designs: designs
+                .enumerate()
+                .map(|(index, (colours, stabilizer_size))| Design {
+                    index,
+                    colours,
+                    stabilizer_size,
+                    orbit_size: group_order / stabilizer_size,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(self).unwrap()
+    }
+}
+
+/// What an export brings back: its settings, and the colours its designs were shown in.
+#[derive(Debug, PartialEq)]
+pub struct Imported {
+    pub settings: Shared,
+    /// Empty if the export didn't say.
+    pub palette: Vec<Color32>,
+    pub palette_names: Vec<String>,
+}
+
+/// The settings and palette from an export, to recompute and show its designs again. Anything
+/// with the settings will do: the rest of the export is ignored.
+pub fn import_json(json: &[u8]) -> Result<Imported, String> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Settings {
+        #[serde(default)]
+        schema_version: u32,
+        #[serde(flatten)]
+        settings: Shared,
+        #[serde(default)]
+        palette_rgb: Vec<[u8; 3]>,
+        #[serde(default)]
+        palette_names: Vec<String>,
+    }
+
+    let export: Settings = serde_json::from_slice(json).map_err(|e| e.to_string())?;
+    if export.schema_version > SCHEMA_VERSION {
+        return Err(format!(
+            "This file was exported by a newer version (schema {}, we read up to {SCHEMA_VERSION})",
+            export.schema_version
+        ));
+    }
+    export.settings.validate().map_err(|e| e.to_string())?;
+    Ok(Imported {
+        settings: export.settings,
+        palette: export
+            .palette_rgb
+            .iter()
+            .map(|&[r, g, b]| Color32::from_rgb(r, g, b))
+            .collect(),
+        palette_names: export.palette_names,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::app::{
+        palettes::{PaletteKey, B},
+        share::SharedSpec,
+    };
+    use crate::ring::{AllowedTransformFamiles, Facing, RingSpec};
+    #[test]
+    fn test_export_import() {
+        let spec = RingSpec {
+            n_beads: 4,
+            n_colours: 2,
+            max_repeats: None,
+            allowed_xforms: AllowedTransformFamiles::Rotate,
+            facing: Facing::Symmetric,
+            allowed: None,
+        };
+        let settings = Shared {
+            spec: SharedSpec::Ring(spec),
+            palette: PaletteKey::BuiltIn(1),
+        };
+        let designs = spec
+            .permutations()
+            .into_iter()
+            .map(|perm| (spec.beads(&perm), spec.stabilizer(&perm)));
+        let mut palettes = PaletteChooser::new();
+        palettes.select(&PaletteKey::BuiltIn(1));
+        let export = Export::new(settings.clone(), &palettes, spec.breakdown(), designs);
+        let json: serde_json::Value = serde_json::from_slice(&export.to_json()).unwrap();
+        assert_eq!(json["schemaVersion"], SCHEMA_VERSION);
+        assert_eq!(json["mode"], "ring");
+        assert_eq!(json["beads"], 4);
+        assert_eq!(
+            json["paletteRgb"],
+            serde_json::json!([[27, 158, 119], [217, 95, 2]])
+        );
+        assert_eq!(json["count"], 6);
+        assert_eq!(json["groupOrder"], 4);
+        assert_eq!(json["breakdown"][0]["fixed"], 16);
+        // 0000, 0001, 0011, 0101, 0111, 1111
+        let orbits: Vec<_> = json["designs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|design| design["orbitSize"].as_u64().unwrap())
+            .collect();
+        assert_eq!(orbits, [1, 4, 4, 2, 4, 1]);
+        assert_eq!(
+            json["designs"][3]["colours"],
+            serde_json::json!([0, 1, 0, 1])
+        );
+        assert_eq!(json["designs"][3]["stabilizerSize"], 2);
+
+        let imported = import_json(&export.to_json()).unwrap();
+        assert_eq!(imported.settings, settings);
+        assert_eq!(imported.palette, B[..2]);
+        let mut restored = PaletteChooser::new();
+        restored.restore(&imported.palette, &imported.palette_names);
+        assert_eq!(restored.key(), PaletteKey::BuiltIn(1));
+        let newer = br#"{"schemaVersion": 99, "mode": "ring", "beads": 4, "colours": 2,
+            "paletteRgb": [], "count": 6, "groupOrder": 4, "breakdown": [], "designs": []}"#;
+        assert!(import_json(newer).is_err());
+        let settings_only = br#"{"mode": "ring", "beads": 4, "colours": 2, "palette": 1}"#;
+        assert_eq!(import_json(settings_only).unwrap().settings, settings);
+
+        // A palette we don't have comes back as a custom one
+        let custom = vec![Color32::RED, Color32::BLUE, Color32::GREEN];
+        palettes.select(&PaletteKey::Colours(custom.clone()));
+        let settings = Shared {
+            palette: palettes.key(),
+            ..settings
+        };
+        let export: Export<Vec<u8>> =
+            Export::new(settings, &palettes, spec.breakdown(), std::iter::empty());
+        let imported = import_json(&export.to_json()).unwrap();
+        let mut restored = PaletteChooser::new();
+        restored.restore(&imported.palette, &imported.palette_names);
+        assert_eq!(restored.choice(), &custom[..2]);
+        restored.select(&PaletteKey::BuiltIn(0));
+        restored.restore(&custom[..2], &[]);
+        assert_eq!(restored.choice(), &custom[..2]);
+
+        let named = br#"{"mode": "ring", "beads": 4, "colours": 2,
+            "paletteRgb": [[255, 0, 0], [0, 0, 255]], "paletteNames": ["Red", "Blue"]}"#;
+        let imported = import_json(named).unwrap();
+        let mut restored = PaletteChooser::new();
+        restored.restore(&imported.palette, &imported.palette_names);
+        assert_eq!(restored.choice(), &custom[..2]);
+        assert_eq!(restored.colour_names(), ["Red", "Blue"]);
+    }
+}
@@ -0,0 +1,144 @@
+//! Getting designs out of (and back into) the app: downloads on the web, file dialogs on desktop.
+use super::{
+    csv::breakdown_csv, palettes::PaletteChooser, sprites::Mark, symbols::Look, ExportSettings,
+};
+use crate::Breakdown;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    BurnsideTable,
+    Png,
+    Svg,
+    Pdf,
+    TabletopDeck,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 7] = [
+        ExportFormat::Json,
+        ExportFormat::Csv,
+        ExportFormat::BurnsideTable,
+        ExportFormat::Png,
+        ExportFormat::Svg,
+        ExportFormat::Pdf,
+        ExportFormat::TabletopDeck,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "JSON",
+            ExportFormat::Csv => "CSV",
+            ExportFormat::BurnsideTable => "Burnside table (CSV)",
+            ExportFormat::Png => "PNG sprite sheet",
+            ExportFormat::Svg => "SVG",
+            ExportFormat::Pdf => "Print-and-play PDF",
+            ExportFormat::TabletopDeck => "Tabletop Simulator deck",
+        }
+    }
+}
+
+/// The MIME type to download `name` as, going by its extension.
+pub fn mime(name: &str) -> &'static str {
+    match name.rsplit('.').next() {
+        Some("json") => "application/json",
+        Some("csv") => "text/csv",
+        Some("png") => "image/png",
+        Some("svg") => "image/svg+xml",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+/// A set of designs that can be written out in each `ExportFormat`.
+pub trait Exportable {
+    /// A file name for exports, without the extension.
+    fn file_stem(&self) -> String;
+    fn breakdown(&self) -> Breakdown;
+    /// All the designs with their settings, in the chosen palette.
+    fn export_json(&self, palettes: &PaletteChooser) -> Vec<u8>;
+    /// One row per design, for spreadsheets.
+    fn export_csv(&self) -> String;
+    /// Each design drawn with `look`, for the image and print exports.
+    fn sprites(&self, look: &Look<'_>) -> Vec<Vec<Mark>>;
+
+    /// The files making up an export, as `(file name, contents)`.
+    fn export(
+        &self,
+        format: ExportFormat,
+        palettes: &PaletteChooser,
+        exports: &ExportSettings,
+    ) -> Vec<(String, Vec<u8>)> {
+        let stem = self.file_stem();
+        let look = palettes.look();
+        let file = |extension: &str, bytes: Vec<u8>| vec![(format!("{stem}.{extension}"), bytes)];
+        match format {
+            ExportFormat::Json => file("json", self.export_json(palettes)),
+            ExportFormat::Csv => file("csv", self.export_csv().into_bytes()),
+            ExportFormat::BurnsideTable => vec![(
+                format!("{stem} Burnside table.csv"),
+                breakdown_csv(&self.breakdown()).into_bytes(),
+            )],
+            ExportFormat::Png => file("png", exports.sheet.png(&self.sprites(&look))),
+            ExportFormat::Svg => file("svg", exports.sheet.svg(&self.sprites(&look)).into_bytes()),
+            ExportFormat::Pdf => file("pdf", exports.print.pdf(&self.sprites(&look))),
+            ExportFormat::TabletopDeck => exports.deck.files(&stem, &self.sprites(&look)),
+        }
+    }
+}
+
+/// Asks where to save `files` and writes them there: to a chosen file when there's just one, or
+/// into a chosen folder otherwise. Gives a message saying where they went, or `None` if the user
+/// cancelled.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(files: Vec<(String, Vec<u8>)>) -> Option<Result<String, String>> {
+    use rfd::FileDialog;
+    if let [(name, bytes)] = &files[..] {
+        let mut dialog = FileDialog::new().set_file_name(name);
+        if let Some(extension) = name.rsplit('.').next() {
+            dialog = dialog.add_filter(extension.to_uppercase(), &[extension]);
+        }
+        let path = dialog.save_file()?;
+        Some(
+            std::fs::write(&path, bytes)
+                .map(|()| format!("Saved {}", path.display()))
+                .map_err(|e| format!("Couldn't save {}: {e}", path.display())),
+        )
+    } else {
+        let folder = FileDialog::new()
+            .set_title("Choose a folder for the exported files")
+            .pick_folder()?;
+        for (name, bytes) in &files {
+            let path = folder.join(name);
+            if let Err(e) = std::fs::write(&path, bytes) {
+                return Some(Err(format!("Couldn't save {}: {e}", path.display())));
+            }
+        }
+        Some(Ok(format!(
+            "Saved {} files to {}",
+            files.len(),
+            folder.display()
+        )))
+    }
+}
+
+/// Asks for a file of one of `extensions` to open, and reads it as `(file name, contents)`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn open(kind: &str, extensions: &[&str]) -> Option<Result<(String, Vec<u8>), String>> {
+    let path = rfd::FileDialog::new()
+        .add_filter(kind, extensions)
+        .pick_file()?;
+    Some(read(&path))
+}
+
+/// Reads a file opened or dropped on the app, as `(file name, contents)`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read(path: &std::path::Path) -> Result<(String, Vec<u8>), String> {
+    let name = path
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let bytes =
+        std::fs::read(path).map_err(|e| format!("Couldn't read {}: {e}", path.display()))?;
+    Ok((name, bytes))
+}
@@ -0,0 +1,49 @@
+use eframe::egui;
+
+use super::export::{import_json, Imported};
+
+#[derive(Default)]
+pub struct Import {
+    visible: bool,
+    /// JSON pasted in for importing.
+    text: String,
+    message: String,
+}
+
+impl Import {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn open(&mut self) {
+        self.visible = true;
+    }
+
+    /// Shows the import window; returns the imported settings once the user has loaded some.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<Imported> {
+        let mut visible = self.visible;
+        let mut imported = None;
+        egui::Window::new("Import designs")
+            .open(&mut visible)
+            .default_width(320.)
+            .show(ctx, |ui| {
+                ui.label("Paste a JSON export to show its designs again:");
+                ui.text_edit_multiline(&mut self.text);
+                if ui.button("Import").clicked() {
+                    match import_json(self.text.as_bytes()) {
+                        Ok(export) => {
+                            imported = Some(export);
+                            self.text.clear();
+                            self.message.clear();
+                        }
+                        Err(e) => self.message = format!("Couldn't import designs: {e}"),
+                    }
+                }
+                if !self.message.is_empty() {
+                    ui.label(&self.message);
+                }
+            });
+        self.visible = visible && imported.is_none();
+        imported
+    }
+}
@@ -1,6 +1,20 @@
 use eframe::egui::{self, Color32, Context, Sense, Ui};
-pub type Palette = [Color32; 8];
-pub static A: Palette = [
+
+use super::{
+    csv::colour_letter,
+    quantise,
+    symbols::{Look, Symbols},
+    vision::{self, Vision},
+};
+use crate::tile::MAX_COLOURS;
+
+/// A colour for each marking, in order.
+pub type Palette = [Color32];
+
+/// Shown for markings past the end of a palette.
+pub const MISSING: Color32 = Color32::GRAY;
+
+pub static A: [Color32; 8] = [
     Color32::from_rgb(127, 201, 127),
     Color32::from_rgb(190, 174, 212),
     Color32::from_rgb(253, 192, 134),
@@ -11,7 +25,7 @@ pub static A: Palette = [
     Color32::from_rgb(102, 102, 102),
 ];
 
-pub static B: Palette = [
+pub static B: [Color32; 8] = [
     Color32::from_rgb(27, 158, 119),
     Color32::from_rgb(217, 95, 2),
     Color32::from_rgb(117, 112, 179),
@@ -22,7 +36,7 @@ pub static B: Palette = [
     Color32::from_rgb(102, 102, 102),
 ];
 
-pub static C: Palette = [
+pub static C: [Color32; 8] = [
     Color32::from_rgb(166, 206, 227),
     Color32::from_rgb(31, 120, 180),
     Color32::from_rgb(178, 223, 138),
@@ -33,7 +47,7 @@ pub static C: Palette = [
     Color32::from_rgb(255, 127, 0),
 ];
 
-pub static D: Palette = [
+pub static D: [Color32; 8] = [
     Color32::from_rgb(251, 180, 174),
     Color32::from_rgb(179, 205, 227),
     Color32::from_rgb(204, 235, 197),
@@ -44,7 +58,7 @@ pub static D: Palette = [
     Color32::from_rgb(253, 218, 236),
 ];
 
-pub static E: Palette = [
+pub static E: [Color32; 8] = [
     Color32::from_rgb(179, 226, 205),
     Color32::from_rgb(253, 205, 172),
     Color32::from_rgb(203, 213, 232),
@@ -55,7 +69,7 @@ pub static E: Palette = [
     Color32::from_rgb(204, 204, 204),
 ];
 
-pub static F: Palette = [
+pub static F: [Color32; 8] = [
     Color32::from_rgb(228, 26, 28),
     Color32::from_rgb(55, 126, 184),
     Color32::from_rgb(77, 175, 74),
@@ -66,7 +80,7 @@ pub static F: Palette = [
     Color32::from_rgb(247, 129, 191),
 ];
 
-pub static G: Palette = [
+pub static G: [Color32; 8] = [
     Color32::from_rgb(102, 194, 165),
     Color32::from_rgb(252, 141, 98),
     Color32::from_rgb(141, 160, 203),
@@ -77,7 +91,7 @@ pub static G: Palette = [
     Color32::from_rgb(179, 179, 179),
 ];
 
-pub static H: Palette = [
+pub static H: [Color32; 8] = [
     Color32::from_rgb(141, 211, 199),
     Color32::from_rgb(255, 255, 179),
     Color32::from_rgb(190, 186, 218),
@@ -88,27 +102,349 @@ pub static H: Palette = [
     Color32::from_rgb(252, 205, 229),
 ];
 
+pub static PALETTES: [(&str, &Palette); 8] = [
+    ("Palette A", &A),
+    ("Palette B", &B),
+    ("Palette C", &C),
+    ("Palette D", &D),
+    ("Palette E", &E),
+    ("Palette F", &F),
+    ("Palette G", &G),
+    ("Palette H", &H),
+];
+
+/// The colour `palette` gives marking `index`, or `MISSING` if the palette is too short.
+pub fn colour(palette: &Palette, index: u8) -> Color32 {
+    palette.get(index as usize).copied().unwrap_or(MISSING)
+}
+
+/// `colour` as `#rrggbb`.
+pub fn hex(colour: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", colour.r(), colour.g(), colour.b())
+}
+
+/// Reads `#rrggbb` or `#rgb`, with or without the `#`.
+pub fn parse_hex(hex: &str) -> Option<Color32> {
+    let hex = hex.trim().trim_start_matches('#');
+    if !hex.is_ascii() {
+        return None;
+    }
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    match hex.len() {
+        3 => {
+            let [r, g, b] = [0, 1, 2].map(|i| channel(&hex[i..=i]).map(|c| c * 17));
+            Some(Color32::from_rgb(r?, g?, b?))
+        }
+        6 => {
+            let [r, g, b] = [0, 2, 4].map(|i| channel(&hex[i..i + 2]));
+            Some(Color32::from_rgb(r?, g?, b?))
+        }
+        _ => None,
+    }
+}
+
+/// Saves colours as `#rrggbb` strings.
+mod hex_colours {
+    use eframe::egui::Color32;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(colours: &[Color32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(colours.iter().map(|&colour| super::hex(colour)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Color32>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|hex| {
+                super::parse_hex(hex).ok_or_else(|| D::Error::custom(format!("bad colour {hex:?}")))
+            })
+            .collect()
+    }
+}
+
+/// Which palette settings use, in a form that stays right as custom palettes come and go and
+/// that means the same on someone else's machine: built-in palettes by their place in
+/// `PALETTES`, and custom ones by their colours. Serialises as `1` or `["#1b9e77", "#d95f02"]`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+pub enum PaletteKey {
+    BuiltIn(usize),
+    Colours(#[serde(with = "hex_colours")] Vec<Color32>),
+}
+
+impl Default for PaletteKey {
+    fn default() -> Self {
+        PaletteKey::BuiltIn(0)
+    }
+}
+
+/// A palette made in the editor, of any length, with an optional name for each colour.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct CustomPalette {
+    pub name: String,
+    #[serde(with = "hex_colours")]
+    pub colours: Vec<Color32>,
+    /// One for each colour; empty for unnamed colours.
+    pub colour_names: Vec<String>,
+}
+
+impl Default for CustomPalette {
+    fn default() -> Self {
+        CustomPalette {
+            name: "Custom palette".to_string(),
+            colours: A.to_vec(),
+            colour_names: vec![String::new(); A.len()],
+        }
+    }
+}
+
+impl CustomPalette {
+    /// Edits the palette; returns whether it should be deleted. `hex_text` holds the text of each
+    /// colour's hex field while it's being typed in.
+    fn edit(&mut self, ui: &mut Ui, hex_text: &mut Vec<String>) -> bool {
+        self.colour_names.resize(self.colours.len(), String::new());
+        if hex_text.len() != self.colours.len() {
+            *hex_text = self.colours.iter().map(|&colour| hex(colour)).collect();
+        }
+        ui.horizontal(|ui| {
+            ui.label("Name");
+            ui.text_edit_singleline(&mut self.name);
+        });
+        let mut remove = None;
+        let removable = self.colours.len() > 1;
+        egui::Grid::new("palette_editor").show(ui, |ui| {
+            for (i, colour) in self.colours.iter_mut().enumerate() {
+                ui.label(colour_letter(i as u8).to_string());
+                if ui.color_edit_button_srgba(colour).changed() {
+                    hex_text[i] = hex(*colour);
+                }
+                let field = ui.add(egui::TextEdit::singleline(&mut hex_text[i]).desired_width(64.));
+                if field.changed() {
+                    if let Some(parsed) = parse_hex(&hex_text[i]) {
+                        *colour = parsed;
+                    }
+                }
+                if field.lost_focus() {
+                    hex_text[i] = hex(*colour);
+                }
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.colour_names[i])
+                        .hint_text("Name")
+                        .desired_width(100.),
+                );
+                if removable && ui.small_button("✖").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = remove {
+            self.colours.remove(i);
+            self.colour_names.remove(i);
+            hex_text.remove(i);
+        }
+        let mut delete = false;
+        ui.horizontal(|ui| {
+            if self.colours.len() < MAX_COLOURS as usize && ui.button("Add colour").clicked() {
+                let colour = MISSING;
+                self.colours.push(colour);
+                self.colour_names.push(String::new());
+                hex_text.push(hex(colour));
+            }
+            delete = ui.button("Delete palette").clicked();
+        });
+        delete
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 pub struct PaletteChooser {
-    pub choice: &'static Palette,
+    /// Index into `PALETTES`, then on into `custom`
+    choice: usize,
+    custom: Vec<CustomPalette>,
     visible: bool,
+    /// How many colours to take from an image.
+    image_colours: usize,
+    /// Shows colours as seen with this kind of vision.
+    vision: Vision,
+    pub symbols: Symbols,
+    /// What's been typed in each hex field of the palette being edited.
+    #[serde(skip)]
+    hex: Vec<String>,
+}
+
+impl Default for PaletteChooser {
+    fn default() -> Self {
+        Self {
+            choice: 0,
+            custom: vec![],
+            visible: false,
+            image_colours: 8,
+            vision: Vision::Normal,
+            symbols: Symbols::default(),
+            hex: vec![],
+        }
+    }
 }
 
 impl PaletteChooser {
     pub fn new() -> Self {
-        PaletteChooser {
-            choice: &A,
-            visible: false,
+        Default::default()
+    }
+
+    pub fn choice(&self) -> &Palette {
+        match self.choice.checked_sub(PALETTES.len()) {
+            None => PALETTES[self.choice].1,
+            Some(i) => self.custom.get(i).map_or(&A, |palette| &palette.colours),
         }
     }
 
-    fn render_palette(ui: &mut Ui, palette: &Palette) {
-        let (mut rect, _response) = ui.allocate_exact_size((160., 20.).into(), Sense::hover());
-        rect.set_width(20.);
-        #[allow(clippy::needless_range_loop)]
-        for i in 0..8 {
-            let offset = (20 * i) as f32;
-            ui.painter()
-                .rect_filled(rect.translate((offset, 0.).into()), 1., palette[i]);
+    /// The name of each colour of the chosen palette, where it has one.
+    pub fn colour_names(&self) -> &[String] {
+        self.choice
+            .checked_sub(PALETTES.len())
+            .and_then(|i| self.custom.get(i))
+            .map_or(&[], |palette| &palette.colour_names)
+    }
+
+    pub fn key(&self) -> PaletteKey {
+        match self.choice.checked_sub(PALETTES.len()) {
+            None => PaletteKey::BuiltIn(self.choice),
+            Some(_) => PaletteKey::Colours(self.choice().to_vec()),
+        }
+    }
+
+    /// Chooses the palette `key` refers to. Colours that aren't one of our palettes yet become a
+    /// new custom palette.
+    pub fn select(&mut self, key: &PaletteKey) {
+        match key {
+            PaletteKey::BuiltIn(index) => {
+                if *index < PALETTES.len() {
+                    self.choice = *index;
+                }
+            }
+            PaletteKey::Colours(colours) => self.select_colours(colours, &[]),
+        }
+    }
+
+    /// Chooses a palette starting with `colours`, like an export's, keeping the current one if it
+    /// does. If none does, `colours` become a new custom palette with `names`.
+    pub fn restore(&mut self, colours: &[Color32], names: &[String]) {
+        if colours.is_empty() || self.choice().starts_with(colours) {
+            return;
+        }
+        let built_in = PALETTES.iter().map(|(_, palette)| *palette);
+        let custom = self.custom.iter().map(|palette| &palette.colours[..]);
+        match built_in
+            .chain(custom)
+            .position(|palette| palette.starts_with(colours))
+        {
+            Some(i) => {
+                self.choice = i;
+                self.hex.clear();
+            }
+            None => self.select_colours(colours, names),
+        }
+    }
+
+    /// Chooses the custom palette with `colours`, adding it with `names` if there isn't one.
+    fn select_colours(&mut self, colours: &[Color32], names: &[String]) {
+        if colours.is_empty() {
+            return;
+        }
+        let i = match self.custom.iter().position(|p| p.colours == colours) {
+            Some(i) => i,
+            None => {
+                let mut colour_names = names.to_vec();
+                colour_names.resize(colours.len(), String::new());
+                self.custom.push(CustomPalette {
+                    name: format!("Custom palette {}", self.custom.len() + 1),
+                    colours: colours.to_vec(),
+                    colour_names,
+                });
+                self.custom.len() - 1
+            }
+        };
+        self.choice = PALETTES.len() + i;
+        self.hex.clear();
+    }
+
+    /// How markings look in exports.
+    pub fn look(&self) -> Look<'_> {
+        Look {
+            palette: self.choice().into(),
+            symbols: &self.symbols,
+        }
+    }
+
+    /// How markings are shown, which may be simulating a colour vision deficiency.
+    pub fn preview(&self) -> Look<'_> {
+        let vision = self.vision;
+        Look {
+            palette: self.choice().iter().map(|&c| vision.simulate(c)).collect(),
+            symbols: &self.symbols,
+        }
+    }
+
+    /// Picks the kind of vision to preview colours with, and warns if any of the first
+    /// `n_colours` colours look alike with a colour vision deficiency.
+    pub fn vision_settings(&mut self, ui: &mut Ui, n_colours: usize) {
+        egui::ComboBox::from_label("Preview")
+            .selected_text(self.vision.label())
+            .show_ui(ui, |ui| {
+                for vision in Vision::ALL {
+                    ui.selectable_value(&mut self.vision, vision, vision.label());
+                }
+            })
+            .response
+            .on_hover_text("See the designs as they look with a colour vision deficiency");
+        let palette = self.choice();
+        let confusable = vision::confusable(&palette[..n_colours.min(palette.len())]);
+        if !confusable.is_empty() {
+            ui.colored_label(Color32::YELLOW, vision::describe(&confusable));
+        }
+    }
+
+    /// Adds a palette of the main colours of an image, named after its file, and chooses it.
+    pub fn add_from_image(&mut self, name: &str, bytes: &[u8]) -> Result<String, String> {
+        let colours = quantise::extract_palette(bytes, self.image_colours)
+            .map_err(|e| format!("Couldn't read {name}: {e}"))?;
+        let name = std::path::Path::new(name).file_stem().map_or_else(
+            || name.to_string(),
+            |stem| stem.to_string_lossy().into_owned(),
+        );
+        let message = format!("Added palette {name} with {} colours", colours.len());
+        self.custom.push(CustomPalette {
+            name,
+            colour_names: vec![String::new(); colours.len()],
+            colours,
+        });
+        self.choice = PALETTES.len() + self.custom.len() - 1;
+        self.hex.clear();
+        Ok(message)
+    }
+
+    /// Shows `palette` as seen with `vision`, with a warning if some of its colours look alike
+    /// with a colour vision deficiency.
+    fn render_palette(ui: &mut Ui, palette: &Palette, names: &[String], vision: Vision) {
+        // Long palettes get narrower swatches, so they all take the same room
+        let width = (160. / palette.len().max(1) as f32).min(20.);
+        for (i, &colour) in palette.iter().enumerate() {
+            let (rect, response) = ui.allocate_exact_size((width, 20.).into(), Sense::hover());
+            ui.painter().rect_filled(rect, 1., vision.simulate(colour));
+            if let Some(name) = names.get(i).filter(|name| !name.is_empty()) {
+                response.on_hover_text(name);
+            }
+        }
+        let confusable = vision::confusable(palette);
+        if !confusable.is_empty() {
+            ui.add_space(4.);
+            ui.colored_label(Color32::YELLOW, "⚠")
+                .on_hover_text(vision::describe(&confusable));
         }
     }
 
@@ -116,46 +452,145 @@ impl PaletteChooser {
         self.visible = true;
     }
 
-    pub fn choose(&mut self, ctx: &Context) {
-        let PaletteChooser { visible, choice } = self;
+    /// Shows the picker if it's open. Gives true when asked to load an image to take a palette
+    /// from.
+    pub fn choose(&mut self, ctx: &Context) -> bool {
+        let PaletteChooser {
+            visible,
+            choice,
+            custom,
+            image_colours,
+            vision,
+            hex,
+            ..
+        } = self;
+        let mut load_image = false;
         if *visible {
             egui::Window::new("Palette picker")
                 .open(visible)
                 .auto_sized()
                 .show(ctx, |ui| {
+                    for (i, (name, palette)) in PALETTES.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.radio_value(choice, i, *name);
+                            ui.spacing_mut().item_spacing.x = 0.;
+                            Self::render_palette(ui, palette, &[], *vision);
+                        });
+                    }
+                    for (i, palette) in custom.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            if ui
+                                .radio(*choice == PALETTES.len() + i, &palette.name)
+                                .clicked()
+                            {
+                                *choice = PALETTES.len() + i;
+                                hex.clear();
+                            }
+                            ui.spacing_mut().item_spacing.x = 0.;
+                            Self::render_palette(
+                                ui,
+                                &palette.colours,
+                                &palette.colour_names,
+                                *vision,
+                            );
+                        });
+                    }
+                    ui.separator();
+                    if ui
+                        .button("New palette")
+                        .on_hover_text("Start a palette of your own from the chosen one")
+                        .clicked()
+                    {
+                        let colours = match choice.checked_sub(PALETTES.len()) {
+                            None => PALETTES[*choice].1.to_vec(),
+                            Some(i) => custom.get(i).map_or(A.to_vec(), |p| p.colours.clone()),
+                        };
+                        custom.push(CustomPalette {
+                            name: format!("Custom palette {}", custom.len() + 1),
+                            colour_names: vec![String::new(); colours.len()],
+                            colours,
+                        });
+                        *choice = PALETTES.len() + custom.len() - 1;
+                        hex.clear();
+                    }
                     ui.horizontal(|ui| {
-                        ui.radio_value(choice, &A, "Palette A");
-                        Self::render_palette(ui, &A);
-                    });
-                    ui.horizontal(|ui| {
-                        ui.radio_value(choice, &B, "Palette B");
-                        Self::render_palette(ui, &B);
-                    });
-                    ui.horizontal(|ui| {
-                        ui.radio_value(choice, &C, "Palette C");
-                        Self::render_palette(ui, &C);
-                    });
-                    ui.horizontal(|ui| {
-                        ui.radio_value(choice, &D, "Palette D");
-                        Self::render_palette(ui, &D);
-                    });
-                    ui.horizontal(|ui| {
-                        ui.radio_value(choice, &E, "Palette E");
-                        Self::render_palette(ui, &E);
-                    });
-                    ui.horizontal(|ui| {
-                        ui.radio_value(choice, &F, "Palette F");
-                        Self::render_palette(ui, &F);
-                    });
-                    ui.horizontal(|ui| {
-                        ui.radio_value(choice, &G, "Palette G");
-                        Self::render_palette(ui, &G);
-                    });
-                    ui.horizontal(|ui| {
-                        ui.radio_value(choice, &H, "Palette H");
-                        Self::render_palette(ui, &H);
+                        load_image = ui
+                            .button("From an image…")
+                            .on_hover_text(
+                                "Take the main colours of a picture, e.g. the box art. \
+                                 You can also drop an image on the window.",
+                            )
+                            .clicked();
+                        ui.add(
+                            egui::Slider::new(image_colours, 2..=MAX_COLOURS as usize)
+                                .text("colours"),
+                        );
                     });
+                    let editing = choice.checked_sub(PALETTES.len());
+                    if let Some((i, palette)) = editing.and_then(|i| Some((i, custom.get_mut(i)?)))
+                    {
+                        ui.separator();
+                        if palette.edit(ui, hex) {
+                            custom.remove(i);
+                            *choice = 0;
+                            hex.clear();
+                        }
+                    }
                 });
         }
+        load_image
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_custom_palettes() {
+        assert_eq!(parse_hex("#1b9e77"), Some(B[0]));
+        assert_eq!(parse_hex("fff"), Some(Color32::WHITE));
+        assert_eq!(parse_hex("#12345"), None);
+        assert_eq!(parse_hex("#12345g"), None);
+        assert_eq!(hex(B[1]), "#d95f02");
+
+        let mut chooser = PaletteChooser::new();
+        chooser.custom.push(CustomPalette {
+            name: "Sixteen".to_string(),
+            colours: (0..16).map(|i| Color32::from_gray(i * 16)).collect(),
+            colour_names: vec!["Black".to_string()],
+        });
+        let sixteen = PaletteKey::Colours(chooser.custom[0].colours.clone());
+        chooser.select(&sixteen);
+        assert_eq!(chooser.custom.len(), 1);
+        assert_eq!(chooser.choice().len(), 16);
+        assert_eq!(colour(chooser.choice(), 15), Color32::from_gray(240));
+        assert_eq!(colour(chooser.choice(), 16), MISSING);
+        assert_eq!(chooser.colour_names()[0], "Black");
+        chooser.select(&PaletteKey::BuiltIn(PALETTES.len()));
+        assert_eq!(chooser.key(), sixteen);
+
+        let saved = serde_json::to_value(&chooser).unwrap();
+        assert_eq!(saved["custom"][0]["colours"][1], "#101010");
+        let loaded: PaletteChooser = serde_json::from_value(saved).unwrap();
+        assert_eq!(loaded.choice(), chooser.choice());
+
+        // Deleting an earlier palette mustn't change what the key refers to
+        chooser.custom.insert(0, CustomPalette::default());
+        chooser.choice += 1;
+        assert_eq!(chooser.key(), sixteen);
+        chooser.custom.remove(0);
+        chooser.choice = 0;
+        chooser.select(&sixteen);
+        assert_eq!(chooser.choice().len(), 16);
+        let key = serde_json::to_value(&sixteen).unwrap();
+        assert_eq!(key[1], "#101010");
+        assert_eq!(serde_json::from_value::<PaletteKey>(key).unwrap(), sixteen);
+        assert_eq!(serde_json::to_value(PaletteKey::BuiltIn(2)).unwrap(), 2);
+
+        // Colours from elsewhere become a palette of their own
+        let shared = PaletteKey::Colours(vec![Color32::RED, Color32::BLUE]);
+        chooser.select(&shared);
+        assert_eq!(chooser.custom.len(), 2);
+        assert_eq!(chooser.key(), shared);
     }
 }
@@ -0,0 +1,198 @@
+use eframe::egui;
+
+#[cfg(target_arch = "wasm32")]
+use super::downloader::make_download;
+use super::share::Shared;
+
+/// A saved configuration. Serialises flat, e.g.
+/// `{"name": "Dungeon tiles", "mode": "tile", "width": 3, "height": 3, "colours": 2, "palette": 1}`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Preset {
+    pub name: String,
+    #[serde(flatten)]
+    pub settings: Shared,
+}
+
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Presets {
+    presets: Vec<Preset>,
+    #[serde(skip)]
+    visible: bool,
+    #[serde(skip)]
+    new_name: String,
+    /// JSON pasted in for importing.
+    #[serde(skip)]
+    import_text: String,
+    #[serde(skip)]
+    message: String,
+}
+
+impl Presets {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn open(&mut self) {
+        self.visible = true;
+    }
+
+    /// Saves `settings` under `name`, replacing any preset already called that.
+    pub fn save(&mut self, name: &str, settings: Shared) {
+        let preset = Preset {
+            name: name.to_string(),
+            settings,
+        };
+        match self.presets.iter_mut().find(|p| p.name == name) {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+    }
+
+    pub fn export_json(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(&self.presets).unwrap()
+    }
+
+    /// Adds the presets in `json` to the collection, returning how many there were. Nothing is
+    /// imported unless every preset is valid.
+    pub fn import_json(&mut self, json: &[u8]) -> Result<usize, String> {
+        let presets: Vec<Preset> = serde_json::from_slice(json).map_err(|e| e.to_string())?;
+        for preset in &presets {
+            preset
+                .settings
+                .validate()
+                .map_err(|e| format!("{}: {e}", preset.name))?;
+        }
+        let imported = presets.len();
+        for preset in presets {
+            self.save(&preset.name, preset.settings);
+        }
+        Ok(imported)
+    }
+
+    /// Shows the presets window; returns the settings to switch to if one was loaded.
+    pub fn show(&mut self, ctx: &egui::Context, current: Shared) -> Option<Shared> {
+        let mut visible = self.visible;
+        let mut load = None;
+        egui::Window::new("Presets")
+            .open(&mut visible)
+            .default_width(320.)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.new_name);
+                    let name = self.new_name.trim().to_string();
+                    if ui
+                        .add_enabled(!name.is_empty(), egui::Button::new("Save current"))
+                        .clicked()
+                    {
+                        self.save(&name, current);
+                        self.new_name.clear();
+                    }
+                });
+                ui.separator();
+
+                if self.presets.is_empty() {
+                    ui.label("No presets saved yet");
+                }
+                let mut delete = None;
+                egui::Grid::new("presets").striped(true).show(ui, |ui| {
+                    for (i, preset) in self.presets.iter().enumerate() {
+                        ui.label(&preset.name);
+                        ui.label(preset.settings.describe());
+                        if ui.button("Load").clicked() {
+                            load = Some(preset.settings.clone());
+                        }
+                        if ui.button("Delete").clicked() {
+                            delete = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(i) = delete {
+                    self.presets.remove(i);
+                }
+
+                ui.collapsing("Import / export", |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.button("Copy JSON").clicked() {
+                            ui.output().copied_text =
+                                String::from_utf8(self.export_json()).unwrap();
+                        }
+                        #[cfg(target_arch = "wasm32")]
+                        if ui.button("Download JSON").clicked() {
+                            make_download("presets.json", &self.export_json(), "application/json");
+                        }
+                    });
+                    ui.label("Paste presets JSON to import:");
+                    ui.text_edit_multiline(&mut self.import_text);
+                    if ui.button("Import").clicked() {
+                        let json = std::mem::take(&mut self.import_text);
+                        self.message = match self.import_json(json.as_bytes()) {
+                            Ok(n) => format!("Imported {n} presets"),
+                            Err(e) => {
+                                self.import_text = json;
+                                format!("Couldn't import presets: {e}")
+                            }
+                        };
+                    }
+                    if !self.message.is_empty() {
+                        ui.label(&self.message);
+                    }
+                });
+            });
+        self.visible = visible;
+        load
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::app::{palettes::PaletteKey, share::SharedSpec};
+    use crate::ring::{AllowedTransformFamiles, Facing, RingSpec};
+    #[test]
+    fn test_import_export() {
+        let ring = Shared {
+            spec: SharedSpec::Ring(RingSpec {
+                n_beads: 6,
+                n_colours: 3,
+                max_repeats: Some(3),
+                allowed_xforms: AllowedTransformFamiles::RotateAndFlip,
+                facing: Facing::Symmetric,
+                allowed: None,
+            }),
+            palette: PaletteKey::BuiltIn(2),
+        };
+        let mut presets = Presets::new();
+        presets.save("Bracelets", ring.clone());
+        let json = presets.export_json();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(value[0]["name"], "Bracelets");
+        assert_eq!(value[0]["mode"], "ring");
+        assert_eq!(value[0]["beads"], 6);
+        assert_eq!(value[0]["palette"], 2);
+
+        let mut imported = Presets::new();
+        imported.save(
+            "Bracelets",
+            Shared {
+                palette: PaletteKey::BuiltIn(0),
+                ..ring.clone()
+            },
+        );
+        assert_eq!(imported.import_json(&json), Ok(1));
+        assert_eq!(imported.presets, presets.presets);
+
+        let tile = br#"[{"name": "Tiles", "mode": "tile", "width": 2, "height": 2, "colours": 3}]"#;
+        assert_eq!(imported.import_json(tile), Ok(1));
+        assert_eq!(imported.presets.len(), 2);
+        assert_eq!(
+            imported.presets[1].settings.describe(),
+            "2×2 tile, 3 colours"
+        );
+
+        let too_big = br#"[{"name": "Big", "mode": "ring", "beads": 50, "colours": 3}]"#;
+        assert!(imported.import_json(too_big).is_err());
+        assert_eq!(imported.presets.len(), 2);
+    }
+}
@@ -0,0 +1,546 @@
+//! Print-and-play sheets: every design at a fixed physical size on A4 or Letter pages, with bleed,
+//! cut marks and optional index labels, written out as a PDF.
+use std::{fmt::Write, sync::Arc};
+
+use eframe::egui::{self, Color32};
+use image::RgbaImage;
+
+use super::sprites::{pictures, Mark};
+
+/// Points per millimetre.
+const PT: f32 = 72. / 25.4;
+/// The unprintable border most printers need, in mm.
+const MARGIN: f32 = 10.;
+/// The space between neighbouring pieces' bleed, in mm; cut marks and labels go here.
+const GUTTER: f32 = 8.;
+const CUT_MARK: f32 = 3.;
+const LABEL_SIZE: f32 = 6.;
+/// Bézier control point distance for a quarter circle, as a fraction of the radius.
+const KAPPA: f32 = 0.552_284_8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Paper {
+    A4,
+    Letter,
+}
+
+impl Paper {
+    /// Width and height in mm, portrait.
+    fn size(self) -> (f32, f32) {
+        match self {
+            Paper::A4 => (210., 297.),
+            Paper::Letter => (215.9, 279.4),
+        }
+    }
+}
+
+/// Where a design goes on a page, in mm from the top left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Placement {
+    page: usize,
+    x: f32,
+    y: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct PrintLayout {
+    pub paper: Paper,
+    /// The longer side of each piece, in mm.
+    pub size: f32,
+    /// How far the artwork runs past the cut lines, in mm.
+    pub bleed: f32,
+    /// Print each design's index under it.
+    pub labels: bool,
+}
+
+impl Default for PrintLayout {
+    fn default() -> Self {
+        PrintLayout {
+            paper: Paper::A4,
+            size: 25.,
+            bleed: 3.,
+            labels: true,
+        }
+    }
+}
+
+impl PrintLayout {
+    /// The width and height of a piece in mm, when `trim` (in slot units) is scaled to `size`.
+    fn piece(&self, trim: &Trim) -> (f32, f32) {
+        let scale = self.size / trim.width().max(trim.height());
+        (trim.width() * scale, trim.height() * scale)
+    }
+
+    /// Where each of `n_designs` pieces of the given size goes, filling pages row by row.
+    fn placements(&self, n_designs: usize, piece: (f32, f32)) -> Vec<Placement> {
+        let (page_width, page_height) = self.paper.size();
+        let pitch_x = piece.0 + 2. * self.bleed + GUTTER;
+        let pitch_y = piece.1 + 2. * self.bleed + GUTTER;
+        let columns = (((page_width - 2. * MARGIN + GUTTER) / pitch_x) as usize).max(1);
+        let rows = (((page_height - 2. * MARGIN + GUTTER) / pitch_y) as usize).max(1);
+        // Centre the grid across the page
+        let left = (page_width - (columns as f32 * pitch_x - GUTTER)) / 2.;
+        (0..n_designs)
+            .map(|i| {
+                let on_page = i % (columns * rows);
+                Placement {
+                    page: i / (columns * rows),
+                    x: left + (on_page % columns) as f32 * pitch_x + self.bleed,
+                    y: MARGIN + (on_page / columns) as f32 * pitch_y + self.bleed,
+                }
+            })
+            .collect()
+    }
+
+    /// A PDF with every design, each cut to the edges of its square cells if it has any, or to
+    /// its whole slot otherwise.
+    pub fn pdf(&self, designs: &[Vec<Mark>]) -> Vec<u8> {
+        let trims: Vec<_> = designs.iter().map(|marks| Trim::of(marks)).collect();
+        let piece = trims
+            .iter()
+            .map(|trim| self.piece(trim))
+            .fold((0., 0.), |(w, h): (f32, f32), (pw, ph)| {
+                (w.max(pw), h.max(ph))
+            });
+        let placements = self.placements(designs.len(), piece);
+        let n_pages = placements.last().map_or(1, |placement| placement.page + 1);
+        let mut pages = vec![Page::new(self.paper); n_pages];
+        let pictures = pictures(designs);
+        for (i, ((marks, trim), placement)) in
+            designs.iter().zip(&trims).zip(&placements).enumerate()
+        {
+            let page = &mut pages[placement.page];
+            let (width, height) = self.piece(trim);
+            let scale = width / trim.width();
+            let point = |x: f32, y: f32| {
+                (
+                    placement.x + (x - trim.left) * scale,
+                    placement.y + (y - trim.top) * scale,
+                )
+            };
+            for mark in marks {
+                match *mark {
+                    Mark::Square { x, y, size, colour } => {
+                        let mut left = placement.x + (x - trim.left) * scale;
+                        let mut top = placement.y + (y - trim.top) * scale;
+                        let mut right = left + size * scale;
+                        let mut bottom = top + size * scale;
+                        // Cells on the edge run out into the bleed
+                        if x - trim.left < EPSILON {
+                            left -= self.bleed;
+                        }
+                        if y - trim.top < EPSILON {
+                            top -= self.bleed;
+                        }
+                        if trim.right - (x + size) < EPSILON {
+                            right += self.bleed;
+                        }
+                        if trim.bottom - (y + size) < EPSILON {
+                            bottom += self.bleed;
+                        }
+                        page.rect(left, top, right - left, bottom - top, colour);
+                    }
+                    Mark::Circle {
+                        x,
+                        y,
+                        radius,
+                        colour,
+                    } => {
+                        let (cx, cy) = point(x, y);
+                        page.circle(cx, cy, radius * scale, colour);
+                    }
+                    Mark::Path {
+                        ref contours,
+                        colour,
+                    } => {
+                        let contours: Vec<Vec<_>> = contours
+                            .iter()
+                            .map(|contour| contour.iter().map(|&(x, y)| point(x, y)).collect())
+                            .collect();
+                        page.path(&contours, colour);
+                    }
+                    Mark::Image {
+                        x,
+                        y,
+                        size,
+                        ref image,
+                        ..
+                    } => {
+                        let i = pictures
+                            .iter()
+                            .position(|picture| Arc::ptr_eq(picture, image))
+                            .unwrap();
+                        let (left, top) = point(x, y);
+                        page.picture(i, left, top, size * scale);
+                    }
+                }
+            }
+            page.cut_marks(placement.x, placement.y, width, height, self.bleed);
+            if self.labels {
+                let baseline = placement.y + height + self.bleed + CUT_MARK + 2.5;
+                page.label(placement.x + width / 2., baseline, &format!("#{i}"));
+            }
+        }
+        let (width, height) = self.paper.size();
+        write_pdf(&pages, &pictures, width * PT, height * PT)
+    }
+
+    pub fn settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.paper, Paper::A4, "A4");
+            ui.radio_value(&mut self.paper, Paper::Letter, "Letter");
+        });
+        ui.add(egui::Slider::new(&mut self.size, 10.0..=100.0).text("Piece size (mm)"));
+        ui.add(egui::Slider::new(&mut self.bleed, 0.0..=5.0).text("Bleed (mm)"));
+        ui.checkbox(&mut self.labels, "Label each piece with its index");
+    }
+}
+
+const EPSILON: f32 = 1e-4;
+
+/// The cut lines around a design, in slot units.
+struct Trim {
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+}
+
+impl Trim {
+    fn of(marks: &[Mark]) -> Self {
+        let squares = marks.iter().filter_map(|mark| match *mark {
+            Mark::Square { x, y, size, .. } => Some((x, y, size)),
+            _ => None,
+        });
+        let mut trim: Option<Trim> = None;
+        for (x, y, size) in squares {
+            let trim = trim.get_or_insert(Trim {
+                left: x,
+                top: y,
+                right: x + size,
+                bottom: y + size,
+            });
+            trim.left = trim.left.min(x);
+            trim.top = trim.top.min(y);
+            trim.right = trim.right.max(x + size);
+            trim.bottom = trim.bottom.max(y + size);
+        }
+        trim.unwrap_or(Trim {
+            left: 0.,
+            top: 0.,
+            right: 1.,
+            bottom: 1.,
+        })
+    }
+
+    fn width(&self) -> f32 {
+        self.right - self.left
+    }
+
+    fn height(&self) -> f32 {
+        self.bottom - self.top
+    }
+}
+
+/// A page's content stream. Takes mm from the top left; PDF wants points from the bottom left.
+#[derive(Clone)]
+struct Page {
+    height: f32,
+    content: String,
+}
+
+impl Page {
+    fn new(paper: Paper) -> Self {
+        Page {
+            height: paper.size().1,
+            content: String::new(),
+        }
+    }
+
+    fn point(&self, x: f32, y: f32) -> (f32, f32) {
+        (x * PT, (self.height - y) * PT)
+    }
+
+    fn fill(&mut self, colour: Color32) {
+        let [r, g, b, _] = colour.to_array();
+        let _ = writeln!(
+            self.content,
+            "{:.3} {:.3} {:.3} rg",
+            r as f32 / 255.,
+            g as f32 / 255.,
+            b as f32 / 255.
+        );
+    }
+
+    fn rect(&mut self, left: f32, top: f32, width: f32, height: f32, colour: Color32) {
+        self.fill(colour);
+        let (x, y) = self.point(left, top + height);
+        let _ = writeln!(
+            self.content,
+            "{x:.2} {y:.2} {:.2} {:.2} re f",
+            width * PT,
+            height * PT
+        );
+    }
+
+    fn circle(&mut self, cx: f32, cy: f32, radius: f32, colour: Color32) {
+        self.fill(colour);
+        let (x, y) = self.point(cx, cy);
+        let r = radius * PT;
+        let k = r * KAPPA;
+        let _ = writeln!(
+            self.content,
+            "{:.2} {y:.2} m\n\
+             {:.2} {:.2} {:.2} {:.2} {x:.2} {:.2} c\n\
+             {:.2} {:.2} {:.2} {:.2} {:.2} {y:.2} c\n\
+             {:.2} {:.2} {:.2} {:.2} {x:.2} {:.2} c\n\
+             {:.2} {:.2} {:.2} {:.2} {:.2} {y:.2} c f",
+            x + r,
+            x + r,
+            y + k,
+            x + k,
+            y + r,
+            y + r,
+            x - k,
+            y + r,
+            x - r,
+            y + k,
+            x - r,
+            x - r,
+            y - k,
+            x - k,
+            y - r,
+            y - r,
+            x + k,
+            y - r,
+            x + r,
+            y - k,
+            x + r,
+        );
+    }
+
+    /// Fills `contours` (in mm) by the nonzero rule.
+    fn path(&mut self, contours: &[Vec<(f32, f32)>], colour: Color32) {
+        self.fill(colour);
+        for contour in contours {
+            for (i, &(x, y)) in contour.iter().enumerate() {
+                let (x, y) = self.point(x, y);
+                let operator = if i == 0 { 'm' } else { 'l' };
+                let _ = writeln!(self.content, "{x:.2} {y:.2} {operator}");
+            }
+            self.content.push_str("h\n");
+        }
+        self.content.push_str("f\n");
+    }
+
+    /// Picture `index` as a `size` mm square with its top left corner at (`left`, `top`).
+    fn picture(&mut self, index: usize, left: f32, top: f32, size: f32) {
+        let (x, y) = self.point(left, top + size);
+        let size = size * PT;
+        let _ = writeln!(
+            self.content,
+            "q {size:.2} 0 0 {size:.2} {x:.2} {y:.2} cm /Im{index} Do Q"
+        );
+    }
+
+    fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        let (x1, y1) = self.point(x1, y1);
+        let (x2, y2) = self.point(x2, y2);
+        let _ = writeln!(
+            self.content,
+            "0.25 w 0 G {x1:.2} {y1:.2} m {x2:.2} {y2:.2} l S"
+        );
+    }
+
+    /// Short lines continuing each cut line out past the bleed.
+    fn cut_marks(&mut self, left: f32, top: f32, width: f32, height: f32, bleed: f32) {
+        let right = left + width;
+        let bottom = top + height;
+        for x in [left, right] {
+            self.line(x, top - bleed - CUT_MARK, x, top - bleed);
+            self.line(x, bottom + bleed, x, bottom + bleed + CUT_MARK);
+        }
+        for y in [top, bottom] {
+            self.line(left - bleed - CUT_MARK, y, left - bleed, y);
+            self.line(right + bleed, y, right + bleed + CUT_MARK, y);
+        }
+    }
+
+    /// `text` centred on `x`. Labels are only ever `#` and digits, which are all the same width
+    /// in Helvetica.
+    fn label(&mut self, x: f32, baseline: f32, text: &str) {
+        let width = text.len() as f32 * 0.556 * LABEL_SIZE;
+        let (x, y) = self.point(x, baseline);
+        let _ = writeln!(
+            self.content,
+            "0 g BT /F1 {LABEL_SIZE} Tf {:.2} {y:.2} Td ({text}) Tj ET",
+            x - width / 2.
+        );
+    }
+}
+
+fn write_pdf(pages: &[Page], pictures: &[Arc<RgbaImage>], width: f32, height: f32) -> Vec<u8> {
+    // 1 is the catalogue, 2 the page tree, 3 the font, and then each page is followed by its
+    // content. After those, each picture is followed by its transparency.
+    let first_picture = 4 + 2 * pages.len();
+    let x_objects: Vec<_> = (0..pictures.len())
+        .map(|i| format!("/Im{i} {} 0 R", first_picture + 2 * i))
+        .collect();
+    let kids: Vec<_> = (0..pages.len())
+        .map(|i| format!("{} 0 R", 4 + 2 * i))
+        .collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+    ];
+    for (i, page) in pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {width:.2} {height:.2}] \
+             /Resources << /Font << /F1 3 0 R >> /XObject << {} >> >> /Contents {} 0 R >>",
+            x_objects.join(" "),
+            5 + 2 * i
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            page.content.len(),
+            page.content
+        ));
+    }
+    for (i, picture) in pictures.iter().enumerate() {
+        let (w, h) = picture.dimensions();
+        let hex = |channels: &[usize]| {
+            let mut hex = String::new();
+            for pixel in picture.pixels() {
+                for &channel in channels {
+                    let _ = write!(hex, "{:02x}", pixel[channel]);
+                }
+            }
+            hex.push('>');
+            hex
+        };
+        let image = |colour_space: &str, extra: &str, data: String| {
+            format!(
+                "<< /Type /XObject /Subtype /Image /Width {w} /Height {h} /ColorSpace {colour_space} \
+                 /BitsPerComponent 8{extra} /Filter /ASCIIHexDecode /Length {} >>\nstream\n{data}\nendstream",
+                data.len()
+            )
+        };
+        let mask = format!(" /SMask {} 0 R", first_picture + 2 * i + 1);
+        objects.push(image("/DeviceRGB", &mask, hex(&[0, 1, 2])));
+        objects.push(image("/DeviceGray", "", hex(&[3])));
+    }
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = vec![];
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        let _ = write!(pdf, "{} 0 obj\n{object}\nendobj\n", i + 1);
+    }
+    let xref = pdf.len();
+    let _ = write!(pdf, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(pdf, "{offset:010} 00000 n ");
+    }
+    let _ = write!(
+        pdf,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+        objects.len() + 1
+    );
+    pdf.into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::app::{
+        palettes::A,
+        sprites::tile_marks,
+        symbols::{Look, Symbols},
+    };
+    use crate::tile::Orientation;
+    use std::io::Cursor;
+    #[test]
+    fn test_pdf() {
+        let layout = PrintLayout::default();
+        // 25 mm pieces with 3 mm bleed are 39 mm apart: 5 across and 7 down on A4
+        let placements = layout.placements(36, (25., 25.));
+        assert_eq!(placements[4].page, 0);
+        assert_eq!(placements[5].y, placements[0].y + 39.);
+        assert_eq!(placements[34].page, 0);
+        assert_eq!(placements[35].page, 1);
+        assert_eq!(placements[35].x, placements[0].x);
+
+        let designs = vec![
+            tile_marks(
+                &[vec![0, 1], vec![2, 0]],
+                Orientation::Symmetric,
+                &Look::plain(&A)
+            );
+            36
+        ];
+        let pdf = String::from_utf8(layout.pdf(&designs)).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.contains("/Count 2"));
+        assert_eq!(pdf.matches("re f").count(), 36 * 4);
+        assert!(pdf.contains("(#35) Tj"));
+
+        // The cross-reference table has to point at each object exactly
+        let xref: usize = pdf
+            .rsplit("startxref\n")
+            .next()
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(pdf[xref..].starts_with("xref\n0 8\n"));
+        for (i, entry) in pdf[xref..].lines().skip(3).take(7).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj", i + 1)));
+        }
+
+        // Pictures follow the pages, each with its transparency as a separate image
+        let mut png = vec![];
+        image::RgbaImage::new(2, 2)
+            .write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)
+            .unwrap();
+        let mut symbols = Symbols::default();
+        symbols.set_picture(1, "blank.png", &png).unwrap();
+        let look = Look {
+            palette: A[..].into(),
+            symbols: &symbols,
+        };
+        let pdf = String::from_utf8(layout.pdf(&[tile_marks(
+            &[vec![0, 1, 1]],
+            Orientation::Symmetric,
+            &look,
+        )]))
+        .unwrap();
+        assert_eq!(pdf.matches("/Im0 Do").count(), 2);
+        assert!(pdf.contains("/XObject << /Im0 6 0 R >>"));
+        assert!(pdf.contains("6 0 obj\n<< /Type /XObject /Subtype /Image /Width 2 /Height 2"));
+        assert!(pdf.contains("/SMask 7 0 R"));
+        assert!(pdf.contains("stream\n00000000>\nendstream"));
+    }
+
+    #[test]
+    fn test_trim() {
+        // A 3×2 tile sits in the middle third of its slot, and is cut to 25 × 16.7 mm
+        let trim = Trim::of(&tile_marks(
+            &[vec![0, 1, 2], vec![2, 1, 0]],
+            Orientation::Symmetric,
+            &Look::plain(&A),
+        ));
+        assert!((trim.top - 1. / 6.).abs() < EPSILON);
+        let (width, height) = PrintLayout::default().piece(&trim);
+        assert!((width - 25.).abs() < EPSILON);
+        assert!((height - 25. * 2. / 3.).abs() < 1e-3);
+    }
+}
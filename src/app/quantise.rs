@@ -0,0 +1,151 @@
+//! Palettes taken from pictures, e.g. box art, by median cut quantisation.
+use eframe::egui::Color32;
+use image::imageops::FilterType;
+
+/// Images are shrunk to fit this many pixels across before quantising; the palette hardly changes
+/// and it's much quicker.
+const SAMPLE_SIZE: u32 = 128;
+/// Rounds of k-means to tidy up the median cut's colours.
+const ROUNDS: usize = 8;
+
+/// Whether `name` looks like an image we can read, going by its extension.
+pub fn is_image(name: &str) -> bool {
+    image::ImageFormat::from_path(name).is_ok()
+}
+
+/// The `n` colours that best stand in for the image in `bytes`, most common first. Transparent
+/// pixels are left out.
+pub fn extract_palette(bytes: &[u8], n: usize) -> Result<Vec<Color32>, String> {
+    let mut image = image::load_from_memory(bytes).map_err(|e| e.to_string())?;
+    if image.width() > SAMPLE_SIZE || image.height() > SAMPLE_SIZE {
+        // Sample rather than blend, so we only see colours that are really there
+        image = image.resize(SAMPLE_SIZE, SAMPLE_SIZE, FilterType::Nearest);
+    }
+    let pixels: Vec<[u8; 3]> = image
+        .to_rgba8()
+        .pixels()
+        .filter(|pixel| pixel[3] >= 128)
+        .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+    if pixels.is_empty() {
+        return Err("The image is completely transparent".to_string());
+    }
+    let centres = median_cut(pixels.clone(), n);
+    Ok(k_means(&pixels, centres))
+}
+
+/// Splits `pixels` into up to `n` boxes, each time halving the box whose widest channel is
+/// widest at the median of that channel, and gives the average of each box.
+fn median_cut(pixels: Vec<[u8; 3]>, n: usize) -> Vec<[f32; 3]> {
+    // The widest channel of a box, and how wide it is
+    let widest = |pixels: &[[u8; 3]]| {
+        (0..3)
+            .map(|channel| {
+                let values = pixels.iter().map(|pixel| pixel[channel]);
+                let range = values.clone().max().unwrap() - values.min().unwrap();
+                (channel, range)
+            })
+            .max_by_key(|&(_, range)| range)
+            .unwrap()
+    };
+    let mut boxes = vec![pixels];
+    while boxes.len() < n {
+        let Some((i, channel)) = boxes
+            .iter()
+            .enumerate()
+            .map(|(i, pixels)| (i, widest(pixels)))
+            .filter(|&(_, (_, range))| range > 0)
+            .max_by_key(|&(_, (_, range))| range)
+            .map(|(i, (channel, _))| (i, channel))
+        else {
+            // Every box is a single colour
+            break;
+        };
+        let mut pixels = boxes.swap_remove(i);
+        pixels.sort_unstable_by_key(|pixel| pixel[channel]);
+        let upper = pixels.split_off(pixels.len() / 2);
+        boxes.push(pixels);
+        boxes.push(upper);
+    }
+    boxes.iter().map(|pixels| mean(pixels.iter())).collect()
+}
+
+fn mean<'a>(pixels: impl Iterator<Item = &'a [u8; 3]>) -> [f32; 3] {
+    let mut total = [0.; 3];
+    let mut count = 0;
+    for pixel in pixels {
+        for channel in 0..3 {
+            total[channel] += pixel[channel] as f32;
+        }
+        count += 1;
+    }
+    total.map(|total| total / count.max(1) as f32)
+}
+
+/// Moves each of `centres` to the mean of the pixels nearest it, a few times over, and gives the
+/// colours that are left, most used first.
+fn k_means(pixels: &[[u8; 3]], mut centres: Vec<[f32; 3]>) -> Vec<Color32> {
+    let nearest = |centres: &[[f32; 3]], pixel: &[u8; 3]| {
+        let distance = |centre: &[f32; 3]| -> f32 {
+            (0..3).map(|c| (centre[c] - pixel[c] as f32).powi(2)).sum()
+        };
+        (0..centres.len())
+            .min_by(|&a, &b| distance(&centres[a]).total_cmp(&distance(&centres[b])))
+            .unwrap()
+    };
+    let mut members = vec![];
+    for _ in 0..ROUNDS {
+        members = pixels
+            .iter()
+            .map(|pixel| nearest(&centres, pixel))
+            .collect();
+        for (i, centre) in centres.iter_mut().enumerate() {
+            let cluster = pixels.iter().zip(&members).filter(|&(_, &m)| m == i);
+            if cluster.clone().next().is_some() {
+                *centre = mean(cluster.map(|(pixel, _)| pixel));
+            }
+        }
+    }
+    let mut clusters: Vec<_> = centres
+        .iter()
+        .enumerate()
+        .map(|(i, centre)| (members.iter().filter(|&&m| m == i).count(), centre))
+        .filter(|&(size, _)| size > 0)
+        .collect();
+    clusters.sort_by_key(|&(size, _)| std::cmp::Reverse(size));
+    clusters
+        .into_iter()
+        .map(|(_, centre)| {
+            let [r, g, b] = centre.map(|channel| channel.round() as u8);
+            Color32::from_rgb(r, g, b)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{ImageOutputFormat, Rgba, RgbaImage};
+    use std::io::Cursor;
+    #[test]
+    fn test_extract_palette() {
+        // Three quarters red, a quarter blue, with a transparent stripe
+        let image = RgbaImage::from_fn(40, 40, |x, y| match (x, y) {
+            (0..=9, _) => Rgba([0, 0, 255, 255]),
+            (_, 0..=3) => Rgba([0, 255, 0, 0]),
+            _ => Rgba([255, 0, 0, 255]),
+        });
+        let mut png = vec![];
+        image
+            .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+            .unwrap();
+        let palette = extract_palette(&png, 2).unwrap();
+        assert_eq!(palette, [Color32::RED, Color32::BLUE]);
+        // Asking for more colours than there are can't invent any
+        assert_eq!(extract_palette(&png, 8).unwrap().len(), 2);
+
+        assert!(is_image("box art.JPG"));
+        assert!(!is_image("designs.csv"));
+        assert!(extract_palette(b"not an image", 4).is_err());
+    }
+}
@@ -1,31 +1,47 @@
-use std::collections::HashSet;
-
-use crate::app::{
-    bag_draw::*,
-    downloader::make_download,
-    ring::permutation::{build_permutations, Permutation, Transforms},
+use crate::{
+    app::downloader::make_download,
+    bag_draw::simple_count,
+    ring::{
+        self, gcd, AllowedTransformFamiles, BeadColours, Facing, Permutation, RingSpec, MAX_BEADS,
+    },
+    Breakdown, ColourSet, SpecError,
 };
 
-use super::palettes::Palette;
+use super::{
+    allowed::{colours_menu, paint_allowed, within},
+    check::{check_colours, colours_used, common_shape, Canonical, Marks},
+    csv::designs_csv,
+    export::Export,
+    files::{mime, ExportFormat, Exportable},
+    palettes::PaletteChooser,
+    share::{Shared, SharedSpec},
+    sprites::{paint_at, ring_marks, Mark},
+    symbols::Look,
+    ExportSettings,
+};
 use eframe::egui;
-
-mod permutation;
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum AllowedTransformFamiles {
-    NoTransforms,
-    Rotate,
-    RotateAndFlip,
-}
 use AllowedTransformFamiles::*;
 
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 pub struct Ring {
     n_colours: u64,
     n_beads: u64,
     limit_repeats: bool,
     max_repeats: u64,
     allowed_xforms: AllowedTransformFamiles,
+    facing: Facing,
+    limit_beads: bool,
+    /// The colours each bead may take, for as many beads as a ring can have, so changing the
+    /// number of beads doesn't lose them.
+    bead_colours: Vec<ColourSet>,
+    /// Recomputed on load: a zero count makes `settings` report a change.
+    #[serde(skip)]
     perm_count: u64,
+    /// Why the settings can't be counted, if they can't.
+    #[serde(skip)]
+    error: Option<SpecError>,
+    #[serde(skip)]
     permutations: Vec<Permutation>,
 }
 
@@ -37,7 +53,11 @@ impl Default for Ring {
             limit_repeats: false,
             max_repeats: 0,
             allowed_xforms: Rotate,
+            facing: Facing::Symmetric,
+            limit_beads: false,
+            bead_colours: vec![ColourSet::ANY; MAX_BEADS as usize],
             perm_count: 0,
+            error: None,
             permutations: vec![],
         }
     }
@@ -46,30 +66,47 @@ impl Ring {
     pub fn new() -> Self {
         Default::default()
     }
-    pub fn settings(&mut self, ui: &mut egui::Ui) -> bool {
+    /// Shows the settings, offering as many colours as the palette has; returns whether they changed.
+    pub fn settings(&mut self, ui: &mut egui::Ui, palette_len: usize) -> bool {
         let Self {
             n_beads,
             n_colours,
             limit_repeats,
             max_repeats,
             allowed_xforms,
+            facing,
+            limit_beads,
+            bead_colours,
             perm_count,
+            error,
             ..
         } = self;
-        let mut changed = *perm_count == 0;
-        let max_beads = (64. / (*n_colours as f64).log2()) as u64;
-        let max_colours = (64. / (*n_beads as f64)).exp2() as u64;
-        let min_max_repeats = (*n_beads + *n_colours - 1) / *n_colours;
+        let mut changed = *perm_count == 0 && error.is_none();
+        let states = facing.states();
+        let max_beads = (64. / ((*n_colours * states) as f64).log2()) as u64;
+        let max_colours = (64. / (*n_beads as f64)).exp2() as u64 / states;
+        let min_max_repeats = n_beads.div_ceil(*n_colours);
         let max_max_repeats = *n_beads;
         ui.heading("Settings");
 
         changed |= ui
             .add(
-                egui::Slider::new(n_colours, 2..=max_colours.min(8))
-                    .text("Number of colours")
-                    .clamp_to_range(true),
+                egui::Slider::new(
+                    n_colours,
+                    2..=max_colours
+                        .min(facing.max_colours())
+                        .min(palette_len.max(2) as u64),
+                )
+                .text("Number of colours")
+                .clamp_to_range(true),
             )
             .changed();
+        if *n_colours > palette_len as u64 {
+            ui.colored_label(
+                egui::Color32::YELLOW,
+                format!("The palette only has {palette_len} colours; the rest show grey"),
+            );
+        }
         changed |= ui
             .add(
                 egui::Slider::new(n_beads, 2..=max_beads.min(10))
@@ -77,6 +114,13 @@ impl Ring {
                     .clamp_to_range(true),
             )
             .changed();
+        changed |= ui
+            .checkbox(limit_beads, "Limit bead colours")
+            .on_hover_text("Right-click a bead to choose the colours it may be")
+            .changed();
+        if *limit_beads {
+            changed |= bead_editor(ui, *n_beads, *n_colours, bead_colours);
+        }
         changed |= ui
             .checkbox(limit_repeats, "Limit colour repetions")
             .changed();
@@ -107,63 +151,111 @@ impl Ring {
         changed |= ui
             .radio_value(allowed_xforms, RotateAndFlip, "Rotation and flip")
             .changed();
+        ui.label("Beads:")
+            .on_hover_text("Turning the ring over turns every bead over too");
+        for (choice, label) in [
+            (Facing::Symmetric, "The same either way up"),
+            (Facing::TwoFaced, "Front and back, like charms"),
+            (Facing::Pointing, "Pointing along the string"),
+        ] {
+            if ui.radio_value(facing, choice, label).changed() {
+                changed = true;
+                *n_colours = (*n_colours).min(facing.max_colours());
+            }
+        }
 
         ui.label(format!("There are {} distinct tiles", perm_count));
 
         changed
     }
 
-    pub fn recompute_perms(&mut self) {
-        let Self {
-            allowed_xforms,
-            n_beads,
-            n_colours,
-            limit_repeats,
-            max_repeats,
-            perm_count,
-            permutations,
-            ..
-        } = self;
+    pub fn spec(&self) -> RingSpec {
+        RingSpec {
+            n_beads: self.n_beads,
+            n_colours: self.n_colours,
+            max_repeats: self.limit_repeats.then_some(self.max_repeats),
+            allowed_xforms: self.allowed_xforms,
+            facing: self.facing,
+            allowed: self.allowed(self.n_beads),
+        }
+    }
 
-        let (orbits, fixed) = if *limit_repeats {
-            limited_count(*n_colours, *n_beads, *max_repeats, *allowed_xforms)
-        } else {
-            unlimited_count(*n_colours, *n_beads, *allowed_xforms)
-        };
+    /// The colours each of the first `n_beads` beads may take, if they're limited. Beads only
+    /// allow the colours there are.
+    fn allowed(&self, n_beads: u64) -> Option<BeadColours> {
+        self.limit_beads.then(|| {
+            BeadColours::from_sets((0..n_beads as usize).map(|n| {
+                let allowed = self.bead_colours.get(n).copied();
+                within(allowed.unwrap_or(ColourSet::ANY), self.n_colours)
+            }))
+        })
+    }
 
-        if orbits > 0 {
-            *perm_count = fixed / orbits;
-        } else {
-            *perm_count = 0;
+    /// Replaces the settings with `spec`; the results are recomputed on the next frame.
+    pub fn set_spec(&mut self, spec: RingSpec) {
+        self.n_beads = spec.n_beads;
+        self.n_colours = spec.n_colours;
+        self.limit_repeats = spec.max_repeats.is_some();
+        if let Some(max_repeats) = spec.max_repeats {
+            self.max_repeats = max_repeats;
+        }
+        self.allowed_xforms = spec.allowed_xforms;
+        self.facing = spec.facing;
+        self.limit_beads = spec.allowed.is_some();
+        if let Some(allowed) = spec.allowed {
+            self.bead_colours = vec![ColourSet::ANY; MAX_BEADS as usize];
+            for (n, colours) in allowed.sets().enumerate() {
+                self.bead_colours[n] = colours;
+            }
         }
+        self.perm_count = 0;
+        self.error = None;
+    }
 
-        let transforms = Transforms::new(*n_beads, *allowed_xforms);
-        if *perm_count <= 10000 {
-            let mut seen = HashSet::with_capacity(*perm_count as usize);
-            let mut colour_counts = vec![0; *n_colours as usize];
-            build_permutations(
-                &transforms,
-                *n_beads,
-                *n_colours,
-                0,
-                Permutation::new(),
-                &mut colour_counts,
-                if *limit_repeats {
-                    *max_repeats
-                } else {
-                    u64::MAX
-                },
-                &mut seen,
-            );
-            permutations.clear();
-            permutations.extend(seen);
-            permutations.sort_unstable();
+    /// Takes the number of beads and colours from `designs`, for lists that come without settings.
+    pub fn fit(&mut self, designs: &[Vec<Vec<u8>>]) -> Result<(), String> {
+        let mut spec = self.spec();
+        if let Some((n_beads, 1)) = common_shape(designs) {
+            spec.n_beads = n_beads as u64;
+            spec.allowed = self.allowed(spec.n_beads);
+        }
+        spec.n_colours = spec
+            .n_colours
+            .max(colours_used(designs, spec.facing.states()));
+        spec.validate().map_err(|e| e.to_string())?;
+        if spec != self.spec() {
+            self.set_spec(spec);
+        }
+        Ok(())
+    }
+
+    pub fn recompute_perms(&mut self) {
+        let spec = self.spec();
+        self.error = spec.validate().err();
+        if self.error.is_some() {
+            self.perm_count = 0;
+            self.permutations.clear();
+            return;
+        }
+        self.perm_count = spec.breakdown().count();
+        if self.perm_count <= 10000 {
+            self.permutations = spec.permutations();
         } else {
-            permutations.clear();
+            self.permutations.clear();
         }
     }
 
-    pub fn render_results(&self, palette: &Palette, ui: &mut egui::Ui) {
+    pub fn render_results(
+        &self,
+        palettes: &PaletteChooser,
+        exports: &mut ExportSettings,
+        ui: &mut egui::Ui,
+    ) {
+        if let Some(error) = self.error {
+            ui.colored_label(egui::Color32::from_rgb(255, 0, 0), error.to_string());
+            return;
+        }
+        let look = palettes.preview();
         let Self {
             n_beads,
             perm_count,
@@ -183,7 +275,8 @@ impl Ring {
                         // We basically have a bag of max_repeats / orbit_size stones of each colour, from which we will be making `rotations` draws.
                         // How many distinct outcomes are there?
                         let n_combis =
-                            simple_count(rotations, self.n_colours, self.max_repeats / orbit_size);
+                            simple_count(rotations, self.n_colours, self.max_repeats / orbit_size)
+                                * self.facing.states().pow(rotations as u32);
                         let expected = n_combis / self.n_beads;
                         let mut count = 0;
                         for perm in permutations {
@@ -206,332 +299,157 @@ impl Ring {
                 }
                 ui.colored_label(egui::Color32::from_rgb(255, 0, 0), message);
             }
-            if cfg!(target_arch = "wasm32") && ui.button("Download JSON").clicked() {
-                let (name, bytes) = self.export_json();
-                make_download(&name, &bytes, "application/json");
+            if cfg!(target_arch = "wasm32") {
+                ui.horizontal_wrapped(|ui| {
+                    for format in ExportFormat::ALL {
+                        if ui.button(format!("Download {}", format.label())).clicked() {
+                            for (name, bytes) in self.export(format, palettes, exports) {
+                                make_download(&name, &bytes, mime(&name));
+                            }
+                        }
+                    }
+                });
             }
+            exports.settings(ui);
             ui.horizontal_wrapped(|ui| {
+                let spec = self.spec();
                 for permutation in permutations {
                     let (rect, _response) =
                         ui.allocate_exact_size((60., 60.).into(), egui::Sense::hover());
-                    let delta = std::f64::consts::TAU / (*n_beads as f64);
-                    let distance = 15.;
-                    let radius = {
-                        let x1 = distance;
-                        let y1 = 0.;
-                        let x2 = delta.cos() * distance;
-                        let y2 = delta.sin() * distance;
-                        ((x1 - x2).powf(2.) + (y1 - y2).powf(2.)).sqrt() / 2.
-                    } as f32;
-
-                    let mut theta: f64 = if *n_beads % 2 == 0 { delta / 2. } else { 0. };
-                    for n in 0..*n_beads {
-                        let x = theta.sin() * distance;
-                        let y = -theta.cos() * distance;
-                        ui.painter().circle_filled(
-                            rect.center() + egui::vec2(x as f32, y as f32),
-                            radius,
-                            palette[permutation.get(n) as usize],
+                    // Only build the marks for the designs scrolled into view
+                    if ui.is_rect_visible(rect) {
+                        paint_at(
+                            ui,
+                            rect.min,
+                            60.,
+                            &ring_marks(&spec.beads(permutation), spec.facing, &look),
                         );
-                        theta += delta;
                     }
                 }
             });
         }
     }
-    pub fn export_json(&self) -> (String, Vec<u8>) {
-        let Self {
-            n_beads,
-            n_colours,
-            allowed_xforms,
-            permutations,
-            ..
-        } = self;
-        let mut rv = vec![];
-        for permutation in permutations {
-            let mut entry = vec![];
-            for y in 0..*n_beads {
-                entry.push(permutation.get(y));
-            }
-            rv.push(entry)
-        }
-        let name = format!(
-            "{} beads {} col {:?}.json",
-            n_beads, n_colours, allowed_xforms
-        );
-        let bytes = serde_json::to_vec(&rv).unwrap();
-        (name, bytes)
-    }
 }
 
-fn gcd(a: u64, b: u64) -> u64 {
-    // a and b should be small enough that trial division is feasible
-    // Heck, we could even just have a look-up table
-    assert!(b <= a);
-    for i in (1..=b).rev() {
-        if a % i == 0 && b % i == 0 {
-            return i;
+impl Canonical for Ring {
+    fn canonical(&self, rows: &[Vec<u8>]) -> Result<Vec<Vec<u8>>, String> {
+        let spec = self.spec();
+        spec.validate().map_err(|e| e.to_string())?;
+        let beads = match rows {
+            [beads] if beads.len() == self.n_beads as usize => beads,
+            _ => return Err(format!("Isn't a ring of {} beads", self.n_beads)),
+        };
+        check_colours(rows, self.n_colours, spec.facing.states(), spec.max_repeats)?;
+        let allowed = spec.bead_colours();
+        for (n, &value) in beads.iter().enumerate() {
+            let colours = allowed.allowed(n as u64);
+            if !colours.contains(spec.facing.split(value).0) {
+                return Err(format!(
+                    "Bead {} has a colour where the ring only allows {colours}",
+                    n + 1
+                ));
+            }
         }
+        Ok(vec![
+            spec.beads(&spec.canonicalize(&spec.permutation(beads)))
+        ])
     }
-    1
-}
 
-fn limited_count(
-    n_colours: u64,
-    n_beads: u64,
-    max_repeats: u64,
-    allowed_xforms: AllowedTransformFamiles,
-) -> (u64, u64) {
-    // Special-case no rotation;
-    let (mut orbits, mut fixed) = limited_base_count(n_colours, n_beads, max_repeats);
-    if matches!(allowed_xforms, Rotate | RotateAndFlip) {
-        let (extra_orbits, extra_fixed) = limited_rotate_count(n_colours, n_beads, max_repeats);
-        orbits += extra_orbits;
-        fixed += extra_fixed;
+    fn distinct(&self) -> Option<Vec<Vec<Vec<u8>>>> {
+        let spec = self.spec();
+        (self.perm_count <= 10000).then(|| {
+            self.permutations
+                .iter()
+                .map(|perm| vec![spec.beads(perm)])
+                .collect()
+        })
     }
-    if matches!(allowed_xforms, RotateAndFlip) {
-        let (extra_orbits, extra_fixed) = limited_flip_count(n_colours, n_beads, max_repeats);
-        orbits += extra_orbits;
-        fixed += extra_fixed;
-    }
-    (orbits, fixed)
-}
 
-fn limited_base_count(n_colours: u64, n_beads: u64, max_repeats: u64) -> (u64, u64) {
-    let n_combis = simple_count(n_beads, n_colours, max_repeats);
-    if n_combis > 0 {
-        (1, n_combis)
-    } else {
-        (0, 0)
+    fn n_distinct(&self) -> u64 {
+        self.perm_count
     }
-}
 
-fn limited_rotate_count(n_colours: u64, n_beads: u64, max_repeats: u64) -> (u64, u64) {
-    let mut orbits = 0;
-    let mut fixed = 0;
-
-    for i in 1..n_beads {
-        // This is the number of 'free' points. If the GCD == 1, then we require that all points the be same colour. If it's 2, then we have two points that can be independently coloured, and so on.
-        // E.g. if i == 2, and n_beads == 6, then bead 0 maps to bead 2 maps to bead 4, while bead 1 maps to bead 3 maps to bead 5. If i = 4, then bead 0 maps to bead 4 maps to bead 2, etc.
-        let rotations = gcd(n_beads, i);
-
-        let orbit_size = n_beads / rotations;
-        // We basically have a bag of max_repeats / orbit_size stones of each colour, from which we will be making `rotations` draws.
-        // How many distinct outcomes are there?
-        let n_combis = simple_count(rotations, n_colours, max_repeats / orbit_size);
-        fixed += n_combis;
-        orbits += 1;
+    fn marks(&self) -> Marks {
+        let facing = self.facing;
+        Box::new(move |rows, look| ring_marks(&rows.concat(), facing, look))
     }
-
-    (orbits, fixed)
 }
 
-fn limited_flip_count(n_colours: u64, n_beads: u64, max_repeats: u64) -> (u64, u64) {
-    let mut orbits = 0;
-    let mut fixed = 0;
-    let reduced_max = max_repeats / 2;
-    if n_beads % 2 == 0 {
-        // We can flip on edges, or corners
-        // Edges will fix n_beads/2, and we have n_beads/2 flip axes
-        // While corners will fix n_beads/2 + 1, and again we have n_beads/2 flip axes
-        orbits += n_beads;
-
-        // For edge flips, it's straight-forward
-        let n_combis = (n_beads / 2) * simple_count(n_beads / 2, n_colours, reduced_max);
-        fixed += n_combis;
-        // For corner flips, we don't have the same matching constraints.
-        // If both axes of the corner match in colour, that one colour will be less available for the other points
-        // Otherwise, it will depend on whether max_repeats is even or odd.
-        let mut counts = vec![0; max_repeats as usize + 1];
-        counts[max_repeats as usize] = n_colours;
-        let mut draws = vec![2; (n_beads - 2) as usize / 2];
-        draws.push(1);
-        draws.push(1);
-        let n_combis = (n_beads / 2) * count3(&draws, &mut counts);
-        fixed += n_combis;
-    } else {
-        // Each corner flip is an edge flip and vice versa.
-        // Each will fix (n - 1) / 2 + 1
-        orbits += n_beads;
-        let mut counts = vec![0; max_repeats as usize / 2 + 1];
-        counts[reduced_max as usize] = n_colours - 1;
-        counts[(max_repeats - 1) as usize / 2] += 1;
-        let n_combis = n_beads * n_colours * count2((n_beads - 1) / 2, &mut counts);
-        // fixed += n_beads * n_colours.pow((n_beads / 2 + 1) as u32);
-        fixed += n_combis;
+impl Exportable for Ring {
+    fn file_stem(&self) -> String {
+        let stem = format!(
+            "{} beads {} col {:?}",
+            self.n_beads, self.n_colours, self.allowed_xforms
+        );
+        let stem = match self.facing {
+            Facing::Symmetric => stem,
+            facing => format!("{stem} {facing:?}"),
+        };
+        if self.limit_beads {
+            format!("{stem} limited")
+        } else {
+            stem
+        }
     }
-    (orbits, fixed)
-}
 
-fn unlimited_count(
-    n_colours: u64,
-    n_beads: u64,
-    allowed_xforms: AllowedTransformFamiles,
-) -> (u64, u64) {
-    let (mut orbits, mut fixed) = unlimited_base_count(n_colours, n_beads);
-    if matches!(allowed_xforms, Rotate | RotateAndFlip) {
-        let (extra_orbits, extra_fixed) = unlimited_rotate_count(n_colours, n_beads);
-        orbits += extra_orbits;
-        fixed += extra_fixed;
-    }
-    if matches!(allowed_xforms, RotateAndFlip) {
-        let (extra_orbits, extra_fixed) = unlimited_flip_count(n_colours, n_beads);
-        orbits += extra_orbits;
-        fixed += extra_fixed;
+    fn breakdown(&self) -> Breakdown {
+        ring::breakdown(&self.spec()).unwrap_or_default()
     }
-    (orbits, fixed)
-}
-
-fn unlimited_base_count(n_colours: u64, n_beads: u64) -> (u64, u64) {
-    (1, n_colours.pow(n_beads as u32))
-}
-
-fn unlimited_rotate_count(n_colours: u64, n_beads: u64) -> (u64, u64) {
-    let mut orbits = 0;
-    let mut fixed = 0;
 
-    for i in 1..n_beads {
-        let rotations = gcd(n_beads, i);
-        orbits += 1;
-        fixed += n_colours.pow(rotations as u32);
+    fn export_json(&self, palettes: &PaletteChooser) -> Vec<u8> {
+        let spec = self.spec();
+        let designs = self
+            .permutations
+            .iter()
+            .map(|perm| (spec.beads(perm), spec.stabilizer(perm)));
+        let settings = Shared {
+            spec: SharedSpec::Ring(spec),
+            palette: palettes.key(),
+        };
+        Export::new(settings, palettes, self.breakdown(), designs).to_json()
     }
 
-    (orbits, fixed)
-}
-
-fn unlimited_flip_count(n_colours: u64, n_beads: u64) -> (u64, u64) {
-    let mut orbits = 0;
-    let mut fixed = 0;
-    if n_beads % 2 == 0 {
-        // We can flip on edges, or corners
-        // Edges will fix n_beads/2, and we have n_beads/2 flip axes
-        // While corners will fix n_beads/2 + 1, and again we have n_beads/2 flip axes
-        orbits += n_beads;
-        fixed += (n_beads / 2) * n_colours.pow((n_beads / 2) as u32);
-        fixed += (n_beads / 2) * n_colours.pow((n_beads / 2 + 1) as u32);
-    } else {
-        // Each corner flip is an edge flip and vice versa.
-        // Each will fix (n - 1) / 2 + 1
-        orbits += n_beads;
-        // Rely on rounding down so we don't need to subtract 1 first
-        fixed += n_beads * n_colours.pow((n_beads / 2 + 1) as u32);
+    fn export_csv(&self) -> String {
+        let spec = self.spec();
+        let designs = self
+            .permutations
+            .iter()
+            .map(|perm| (vec![spec.beads(perm)], spec.stabilizer(perm)));
+        designs_csv(
+            self.n_colours,
+            spec.facing.states(),
+            self.breakdown().group_order(),
+            designs,
+        )
     }
-    (orbits, fixed)
-}
-
-#[cfg(test)]
-mod test {
-    use std::collections::HashSet;
-
-    use super::{
-        limited_count,
-        permutation::{build_permutations, Permutation, Transforms},
-        unlimited_count, AllowedTransformFamiles,
-    };
-
-    // #[test]
-    // fn test_ring_limited2() {
-    //     let xform = AllowedTransformFamiles::RotateAndFlip;
-    //     let beads = 3;
-    //     let transforms = Transforms::new(beads, xform);
-    //     let colours = 2;
-    //     let max_repeats = 3;
-    //     let (orbits, fixed) = limited_count(colours, beads, max_repeats, xform);
-    //     assert_eq!(0, fixed % orbits, "Fixed count indivisible by orbits on {colours} colours, {beads} beads, {max_repeats} max repeats and {xform:?} transform");
-    //     let perm_count = if orbits == 0 {
-    //         0
-    //     } else {
-    //         fixed / orbits
-    //     };
-    //     if perm_count <= 10000 {
-    //         let mut seen = HashSet::with_capacity(perm_count as usize);
-    //         let mut colour_counts = vec![0; colours as usize];
-    //         build_permutations(
-    //             &transforms,
-    //             beads,
-    //             colours,
-    //             0,
-    //             Permutation::new(),
-    //             &mut colour_counts,
-    //             max_repeats,
-    //             &mut seen,
-    //         );
-    //         assert_eq!(seen.len(), perm_count as usize, "Mismatch on {colours} colours, {beads} beads, {max_repeats} max repeats and {xform:?} transform");
-    //     }
-    // }
 
-    #[test]
-    #[cfg_attr(not(feature = "slow_tests"), ignore = "Skipping slow tests")]
-    fn test_ring_limited() {
-        for xform in [
-            AllowedTransformFamiles::NoTransforms,
-            AllowedTransformFamiles::Rotate,
-            AllowedTransformFamiles::RotateAndFlip,
-        ] {
-            for beads in 2..10 {
-                let transforms = Transforms::new(beads, xform);
-                for colours in 1..8 {
-                    let min_max_repeats = (beads + colours - 1) / colours;
-                    let max_max_repeats = beads;
-                    for max_repeats in min_max_repeats..=max_max_repeats {
-                        let (orbits, fixed) = limited_count(colours, beads, max_repeats, xform);
-                        assert_eq!(0, fixed % orbits, "Fixed count indivisible by orbits on {colours} colours, {beads} beads, {max_repeats} max repeats and {xform:?} transform");
-                        let perm_count = if orbits == 0 { 0 } else { fixed / orbits };
-                        if perm_count <= 10000 {
-                            let mut seen = HashSet::with_capacity(perm_count as usize);
-                            let mut colour_counts = vec![0; colours as usize];
-                            build_permutations(
-                                &transforms,
-                                beads,
-                                colours,
-                                0,
-                                Permutation::new(),
-                                &mut colour_counts,
-                                max_repeats,
-                                &mut seen,
-                            );
-                            assert_eq!(seen.len(), perm_count as usize, "Mismatch on {colours} colours, {beads} beads, {max_repeats} max repeats and {xform:?} transform");
-                        }
-                    }
-                }
-            }
-        }
+    fn sprites(&self, look: &Look<'_>) -> Vec<Vec<Mark>> {
+        let spec = self.spec();
+        self.permutations
+            .iter()
+            .map(|perm| ring_marks(&spec.beads(perm), spec.facing, look))
+            .collect()
     }
+}
 
-    #[test]
-    #[cfg_attr(not(feature = "slow_tests"), ignore = "Skipping slow tests")]
-    fn test_ring_unlimited() {
-        for xform in [
-            AllowedTransformFamiles::NoTransforms,
-            AllowedTransformFamiles::Rotate,
-            AllowedTransformFamiles::RotateAndFlip,
-        ] {
-            for beads in 2..10 {
-                let transforms = Transforms::new(beads, xform);
-                for colours in 1..8 {
-                    let (orbits, fixed) = unlimited_count(colours, beads, xform);
-                    let perm_count = if orbits == 0 { 0 } else { fixed / orbits };
-                    if perm_count <= 10000 {
-                        let mut seen = HashSet::with_capacity(perm_count as usize);
-                        let mut colour_counts = vec![0; colours as usize];
-                        build_permutations(
-                            &transforms,
-                            beads,
-                            colours,
-                            0,
-                            Permutation::new(),
-                            &mut colour_counts,
-                            u64::MAX,
-                            &mut seen,
-                        );
-                        assert_eq!(
-                            seen.len(),
-                            perm_count as usize,
-                            "Mismatch on {colours} colours, {beads} beads and {xform:?} transform"
-                        );
-                    }
-                }
-            }
+/// Shows the first `n_beads` beads in a row, to right-click and choose which of the `n_colours`
+/// they may be; returns whether any changed.
+fn bead_editor(
+    ui: &mut egui::Ui,
+    n_beads: u64,
+    n_colours: u64,
+    bead_colours: &mut Vec<ColourSet>,
+) -> bool {
+    let mut changed = false;
+    bead_colours.resize(MAX_BEADS as usize, ColourSet::ANY);
+    ui.horizontal(|ui| {
+        for allowed in &mut bead_colours[..n_beads as usize] {
+            let (rect, response) = ui.allocate_exact_size((18., 18.).into(), egui::Sense::click());
+            ui.painter()
+                .circle_filled(rect.center(), 9., ui.visuals().selection.bg_fill);
+            paint_allowed(ui, rect, n_colours, *allowed);
+            changed |= colours_menu(response, n_colours, allowed);
         }
-    }
+    });
+    changed
 }
@@ -0,0 +1,329 @@
+//! Encodes the current configuration into the URL fragment, so a link opens exactly what its
+//! sender was looking at, e.g. `#mode=tile&colours=2&height=3&transforms=rotate-flip&width=3&palette=1`.
+use serde_json::{Map, Value};
+
+use super::palettes::{hex, parse_hex, PaletteKey};
+use crate::{
+    ring::{Facing, RingSpec},
+    tile::TileSpec,
+    SpecError,
+};
+
+/// Where desktop builds point their links.
+const SITE: &str = "http://burnside-calc.com/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum SharedSpec {
+    Tile(TileSpec),
+    Ring(RingSpec),
+}
+
+/// Everything needed to reproduce a view: the mode, its settings and the palette.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Shared {
+    #[serde(flatten)]
+    pub spec: SharedSpec,
+    #[serde(default)]
+    pub palette: PaletteKey,
+}
+
+impl Shared {
+    /// A one-line summary, e.g. `3×3 tile, 2 colours`.
+    pub fn describe(&self) -> String {
+        match &self.spec {
+            SharedSpec::Tile(spec) => {
+                let mut description = format!(
+                    "{}×{} tile, {} colours",
+                    spec.width, spec.height, spec.n_colours
+                );
+                if !spec.orientation.is_symmetric() {
+                    let markings = format!(", {:?} markings", spec.orientation);
+                    description.push_str(&markings.to_lowercase());
+                }
+                if spec.two_sided {
+                    description.push_str(", two-sided");
+                }
+                if let Some(shape) = spec.shape {
+                    description.push_str(&format!(", shaped {shape}"));
+                }
+                description
+            }
+            SharedSpec::Ring(spec) => {
+                let mut description =
+                    format!("{} bead ring, {} colours", spec.n_beads, spec.n_colours);
+                match spec.facing {
+                    Facing::Symmetric => {}
+                    Facing::TwoFaced => description.push_str(", two-faced beads"),
+                    Facing::Pointing => description.push_str(", pointing beads"),
+                }
+                if let Some(allowed) = spec.allowed {
+                    description.push_str(&format!(", beads {allowed}"));
+                }
+                description
+            }
+        }
+    }
+
+    pub fn n_colours(&self) -> u64 {
+        match &self.spec {
+            SharedSpec::Tile(spec) => spec.n_colours,
+            SharedSpec::Ring(spec) => spec.n_colours,
+        }
+    }
+
+    /// How many ways round each marking or bead can sit.
+    pub fn states(&self) -> u64 {
+        match &self.spec {
+            SharedSpec::Tile(spec) => spec.orientation.states(),
+            SharedSpec::Ring(spec) => spec.facing.states(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), SpecError> {
+        match &self.spec {
+            SharedSpec::Tile(spec) => spec.validate(),
+            SharedSpec::Ring(spec) => spec.validate(),
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let (mode, fields) = match &self.spec {
+            SharedSpec::Tile(spec) => ("tile", serde_json::to_value(spec)),
+            SharedSpec::Ring(spec) => ("ring", serde_json::to_value(spec)),
+        };
+        let mut pairs = vec![format!("mode={mode}")];
+        if let Ok(Value::Object(fields)) = fields {
+            for (key, value) in fields {
+                match value {
+                    Value::Null => {}
+                    Value::String(value) => pairs.push(format!("{key}={}", escape(&value))),
+                    value => pairs.push(format!("{key}={value}")),
+                }
+            }
+        }
+        let palette = match &self.palette {
+            PaletteKey::BuiltIn(index) => index.to_string(),
+            PaletteKey::Colours(colours) => colours
+                .iter()
+                .map(|&colour| hex(colour).trim_start_matches('#').to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        };
+        pairs.push(format!("palette={}", escape(&palette)));
+        pairs.join("&")
+    }
+
+    /// Reads back a fragment written by `encode`, with or without the leading `#`. Anything
+    /// malformed or out of range gives `None`, so a bad link just opens the usual settings.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub fn decode(fragment: &str) -> Option<Self> {
+        let mut mode = None;
+        let mut palette = PaletteKey::default();
+        let mut fields = Map::new();
+        for pair in fragment.trim_start_matches('#').split('&') {
+            let (key, value) = pair.split_once('=')?;
+            let value = unescape(value)?;
+            let value = value.as_str();
+            match key {
+                "mode" => mode = Some(value.to_string()),
+                "palette" => {
+                    palette = match value.parse() {
+                        Ok(index) => PaletteKey::BuiltIn(index),
+                        Err(_) => PaletteKey::Colours(
+                            value.split(',').map(parse_hex).collect::<Option<_>>()?,
+                        ),
+                    }
+                }
+                _ => {
+                    let value = match (value.parse::<u64>(), value.parse::<bool>()) {
+                        (Ok(number), _) => Value::from(number),
+                        (_, Ok(flag)) => Value::from(flag),
+                        _ => Value::from(value),
+                    };
+                    fields.insert(key.to_string(), value);
+                }
+            }
+        }
+        let fields = Value::Object(fields);
+        let spec = match mode?.as_str() {
+            "tile" => SharedSpec::Tile(serde_json::from_value(fields).ok()?),
+            "ring" => SharedSpec::Ring(serde_json::from_value(fields).ok()?),
+            _ => return None,
+        };
+        let shared = Shared { spec, palette };
+        shared.validate().ok()?;
+        Some(shared)
+    }
+}
+
+/// `value` with everything but letters, digits and `-._~` percent-encoded, so shapes like
+/// `[AB]x/xx` survive being pasted into chat or email.
+fn escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                escaped.push(byte as char)
+            }
+            _ => escaped.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    escaped
+}
+
+/// Undoes `escape`, or any other percent-encoding; `None` if it's malformed.
+fn unescape(value: &str) -> Option<String> {
+    let mut bytes = vec![];
+    let mut rest = value.as_bytes();
+    while let Some((&byte, after)) = rest.split_first() {
+        if byte == b'%' {
+            let digits = std::str::from_utf8(after.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(digits, 16).ok()?);
+            rest = &after[2..];
+        } else {
+            bytes.push(byte);
+            rest = after;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// The full address of the page showing `fragment`.
+pub fn link(fragment: &str) -> String {
+    #[cfg(target_arch = "wasm32")]
+    let site = web_sys::window()
+        .and_then(|window| window.location().href().ok())
+        .map(|href| href.split('#').next().unwrap_or_default().to_string())
+        .unwrap_or_else(|| SITE.to_string());
+    #[cfg(not(target_arch = "wasm32"))]
+    let site = SITE;
+    format!("{site}#{fragment}")
+}
+
+/// Swaps the fragment in the address bar without adding a history entry for every slider nudge.
+#[cfg(target_arch = "wasm32")]
+pub fn show_in_address_bar(fragment: &str) {
+    if let Some(history) = web_sys::window().and_then(|window| window.history().ok()) {
+        let url = format!("#{fragment}");
+        let _ = history.replace_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some(&url));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ring, tile};
+    use eframe::egui::Color32;
+    #[test]
+    fn test_round_trip() {
+        let shared = Shared {
+            spec: SharedSpec::Tile(TileSpec {
+                width: 3,
+                height: 2,
+                n_colours: 4,
+                max_repeats: Some(2),
+                allowed_xforms: tile::AllowedTransformFamiles::RotateAndFlip,
+                orientation: tile::Orientation::Symmetric,
+                two_sided: false,
+                shape: None,
+            }),
+            palette: PaletteKey::BuiltIn(5),
+        };
+        let fragment = shared.encode();
+        assert_eq!(
+            fragment,
+            "mode=tile&colours=4&height=2&maxRepeats=2&transforms=rotate-flip&width=3&palette=5"
+        );
+        assert_eq!(
+            Shared::decode(&format!("#{fragment}")),
+            Some(shared.clone())
+        );
+
+        let mut arrows = shared.clone();
+        if let SharedSpec::Tile(spec) = &mut arrows.spec {
+            spec.orientation = tile::Orientation::Arrow;
+        }
+        assert!(arrows.encode().contains("&orientation=arrow&"));
+        assert_eq!(arrows.describe(), "3×2 tile, 4 colours, arrow markings");
+        assert_eq!(Shared::decode(&arrows.encode()), Some(arrows.clone()));
+
+        let mut tokens = shared.clone();
+        if let SharedSpec::Tile(spec) = &mut tokens.spec {
+            spec.two_sided = true;
+        }
+        assert!(tokens.encode().contains("&twoSided=true&"));
+        assert_eq!(tokens.describe(), "3×2 tile, 4 colours, two-sided");
+        assert_eq!(Shared::decode(&tokens.encode()), Some(tokens.clone()));
+
+        let mut cross = shared.clone();
+        if let SharedSpec::Tile(spec) = &mut cross.spec {
+            spec.shape = Some(".x./xxx".parse().unwrap());
+        }
+        assert!(cross.encode().contains("&shape=.x.%2Fxxx&"));
+        assert_eq!(cross.describe(), "3×2 tile, 4 colours, shaped .x./xxx");
+        assert_eq!(Shared::decode(&cross.encode()), Some(cross.clone()));
+
+        let mut limited = shared.clone();
+        if let SharedSpec::Tile(spec) = &mut limited.spec {
+            spec.shape = Some("[AB]x./xDx".parse().unwrap());
+        }
+        let fragment = limited.encode();
+        assert!(
+            fragment.contains("&shape=%5BAB%5Dx.%2FxDx&"),
+            "{}",
+            fragment
+        );
+        assert_eq!(Shared::decode(&fragment), Some(limited.clone()));
+        // Links that were never encoded still read
+        let plain = fragment
+            .replace("%5B", "[")
+            .replace("%5D", "]")
+            .replace("%2F", "/");
+        assert_eq!(Shared::decode(&plain), Some(limited));
+        assert_eq!(Shared::decode(&fragment.replace("%2F", "%2")), None);
+
+        let shared = Shared {
+            spec: SharedSpec::Ring(RingSpec {
+                n_beads: 7,
+                n_colours: 3,
+                max_repeats: None,
+                allowed_xforms: ring::AllowedTransformFamiles::NoTransforms,
+                facing: ring::Facing::Symmetric,
+                allowed: None,
+            }),
+            palette: PaletteKey::BuiltIn(0),
+        };
+        assert_eq!(Shared::decode(&shared.encode()), Some(shared.clone()));
+
+        let mut charms = shared.clone();
+        if let SharedSpec::Ring(spec) = &mut charms.spec {
+            spec.facing = Facing::TwoFaced;
+        }
+        assert!(charms.encode().contains("&facing=two-faced&"));
+        assert_eq!(charms.describe(), "7 bead ring, 3 colours, two-faced beads");
+        assert_eq!(Shared::decode(&charms.encode()), Some(charms.clone()));
+
+        let mut clasp = shared.clone();
+        if let SharedSpec::Ring(spec) = &mut clasp.spec {
+            spec.allowed = Some("A[BC]xxxx[BC]".parse().unwrap());
+        }
+        assert!(clasp.encode().contains("&allowed=A%5BBC%5Dxxxx%5BBC%5D&"));
+        assert_eq!(
+            clasp.describe(),
+            "7 bead ring, 3 colours, beads A[BC]xxxx[BC]"
+        );
+        assert_eq!(Shared::decode(&clasp.encode()), Some(clasp.clone()));
+
+        let custom = Shared {
+            palette: PaletteKey::Colours(vec![Color32::RED, Color32::from_rgb(1, 2, 3)]),
+            ..shared.clone()
+        };
+        assert!(custom.encode().ends_with("&palette=ff0000%2C010203"));
+        assert_eq!(Shared::decode(&custom.encode()), Some(custom.clone()));
+
+        assert_eq!(Shared::decode(""), None);
+        assert_eq!(Shared::decode("#dev"), None);
+        assert_eq!(Shared::decode("mode=ring&beads=70&colours=3"), None);
+    }
+}
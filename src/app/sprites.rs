@@ -0,0 +1,802 @@
+//! Renders the whole set of designs into one image, either a PNG sprite sheet or an SVG, in the
+//! active palette and symbols.
+use std::{f32::consts::TAU, fmt::Write, io::Cursor, sync::Arc};
+
+use eframe::egui::{self, Color32, TextureId};
+use image::{imageops::FilterType, ImageOutputFormat, Pixel, Rgba, RgbaImage};
+
+use super::{
+    palettes::hex,
+    symbols::{png_base64, Look},
+};
+use crate::{
+    ring::Facing,
+    tile::{Orientation, GAP},
+};
+
+/// One filled shape, in units where the design's slot is 1×1.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mark {
+    Square {
+        x: f32,
+        y: f32,
+        size: f32,
+        colour: Color32,
+    },
+    Circle {
+        x: f32,
+        y: f32,
+        radius: f32,
+        colour: Color32,
+    },
+    /// Closed outlines, filled by the nonzero rule.
+    Path {
+        contours: Vec<Vec<(f32, f32)>>,
+        colour: Color32,
+    },
+    /// A picture stretched over a square; `texture` is the same picture loaded for the UI.
+    Image {
+        x: f32,
+        y: f32,
+        size: f32,
+        image: Arc<RgbaImage>,
+        texture: Option<TextureId>,
+    },
+}
+
+impl Mark {
+    /// The mark scaled by `scale` towards the slot's top left corner, then moved by `(dx, dy)`.
+    fn placed(self, scale: f32, dx: f32, dy: f32) -> Mark {
+        let at = |x: f32, y: f32| (x * scale + dx, y * scale + dy);
+        match self {
+            Mark::Square { x, y, size, colour } => {
+                let (x, y) = at(x, y);
+                Mark::Square {
+                    x,
+                    y,
+                    size: size * scale,
+                    colour,
+                }
+            }
+            Mark::Circle {
+                x,
+                y,
+                radius,
+                colour,
+            } => {
+                let (x, y) = at(x, y);
+                Mark::Circle {
+                    x,
+                    y,
+                    radius: radius * scale,
+                    colour,
+                }
+            }
+            Mark::Path { contours, colour } => Mark::Path {
+                contours: contours
+                    .into_iter()
+                    .map(|contour| contour.into_iter().map(|(x, y)| at(x, y)).collect())
+                    .collect(),
+                colour,
+            },
+            Mark::Image {
+                x,
+                y,
+                size,
+                image,
+                texture,
+            } => {
+                let (x, y) = at(x, y);
+                Mark::Image {
+                    x,
+                    y,
+                    size: size * scale,
+                    image,
+                    texture,
+                }
+            }
+        }
+    }
+}
+
+/// Where the row at height `y` is inside `contours`, as `(start, end)` pairs from left to right.
+fn spans(contours: &[Vec<(f32, f32)>], y: f32) -> Vec<(f32, f32)> {
+    let mut crossings = vec![];
+    for contour in contours {
+        for (i, &(x0, y0)) in contour.iter().enumerate() {
+            let (x1, y1) = contour[(i + 1) % contour.len()];
+            if (y0 <= y) != (y1 <= y) {
+                let winding = if y1 > y0 { 1 } else { -1 };
+                crossings.push((x0 + (y - y0) / (y1 - y0) * (x1 - x0), winding));
+            }
+        }
+    }
+    crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut spans = vec![];
+    let mut winding = 0;
+    let mut start = 0.;
+    for (x, change) in crossings {
+        if winding == 0 {
+            start = x;
+        }
+        winding += change;
+        if winding == 0 {
+            spans.push((start, x));
+        }
+    }
+    spans
+}
+
+/// The pictures used in `designs`, each once.
+pub fn pictures(designs: &[Vec<Mark>]) -> Vec<Arc<RgbaImage>> {
+    let mut pictures: Vec<Arc<RgbaImage>> = vec![];
+    for mark in designs.iter().flatten() {
+        if let Mark::Image { image, .. } = mark {
+            if !pictures.iter().any(|picture| Arc::ptr_eq(picture, image)) {
+                pictures.push(image.clone());
+            }
+        }
+    }
+    pictures
+}
+
+/// How thick outlines are, as a fraction of a cell or bead's width.
+const OUTLINE: f32 = 0.04;
+
+/// How much of a cell an oriented marking's symbol takes up, leaving room for its pointer.
+const ORIENTED_SYMBOL: f32 = 0.7;
+
+/// What shows which way an oriented marking faces, pointing up in a 1×1 cell centred on the
+/// origin.
+fn pointer(orientation: Orientation) -> Vec<Vec<(f32, f32)>> {
+    let wedge = vec![(0., -0.46), (0.14, -0.3), (-0.14, -0.3)];
+    match orientation {
+        Orientation::Symmetric => vec![],
+        Orientation::Line => {
+            let opposite = wedge.iter().map(|&(x, y)| (-x, -y)).collect();
+            vec![wedge, opposite]
+        }
+        Orientation::Arrow => vec![wedge],
+        Orientation::Chiral => vec![vec![(-0.16, -0.46), (0.16, -0.46), (-0.16, -0.3)]],
+    }
+}
+
+/// The pointer for orientation `state` in a `size` cell centred on (`x`, `y`).
+fn pointer_mark(
+    orientation: Orientation,
+    state: u8,
+    x: f32,
+    y: f32,
+    size: f32,
+    colour: Color32,
+) -> Option<Mark> {
+    let (turns, mirrored) = orientation.pose(state);
+    let contours: Vec<Vec<_>> = pointer(orientation)
+        .into_iter()
+        .map(|contour| {
+            contour
+                .into_iter()
+                .map(|(px, py)| {
+                    let mut point = (if mirrored { -px } else { px }, py);
+                    // A quarter turn clockwise, with y pointing down
+                    for _ in 0..turns {
+                        point = (-point.1, point.0);
+                    }
+                    (x + point.0 * size, y + point.1 * size)
+                })
+                .collect()
+        })
+        .collect();
+    (!contours.is_empty()).then_some(Mark::Path { contours, colour })
+}
+
+/// A tile's cells, scaled to fit the slot and centred in it. Oriented markings get a pointer
+/// showing which way they face, and gaps in the tile's shape are left empty.
+pub fn tile_marks(rows: &[Vec<u8>], orientation: Orientation, look: &Look<'_>) -> Vec<Mark> {
+    let height = rows.len();
+    let width = rows.first().map_or(0, Vec::len);
+    let size = 1. / width.max(height) as f32;
+    let left = (1. - size * width as f32) / 2.;
+    let top = (1. - size * height as f32) / 2.;
+    let symbol_size = match orientation {
+        Orientation::Symmetric => size,
+        _ => size * ORIENTED_SYMBOL,
+    };
+    let mut marks = vec![];
+    for (y, row) in rows.iter().enumerate() {
+        for (x, &value) in row.iter().enumerate() {
+            if value == GAP {
+                continue;
+            }
+            let (index, state) = orientation.split(value);
+            let (x, y) = (left + x as f32 * size, top + y as f32 * size);
+            match look.outline() {
+                Some(outline) => {
+                    let inset = size * OUTLINE;
+                    marks.push(Mark::Square {
+                        x,
+                        y,
+                        size,
+                        colour: outline,
+                    });
+                    marks.push(Mark::Square {
+                        x: x + inset,
+                        y: y + inset,
+                        size: size - 2. * inset,
+                        colour: look.fill(index),
+                    });
+                }
+                None => marks.push(Mark::Square {
+                    x,
+                    y,
+                    size,
+                    colour: look.fill(index),
+                }),
+            }
+            let (x, y) = (x + size / 2., y + size / 2.);
+            marks.extend(look.symbol(index, x, y, symbol_size));
+            marks.extend(pointer_mark(
+                orientation,
+                state,
+                x,
+                y,
+                size,
+                look.ink(index),
+            ));
+        }
+    }
+    marks
+}
+
+/// How far apart the faces of a two-sided tile are drawn, as a fraction of the slot.
+const FACE_GAP: f32 = 0.06;
+
+/// A two-sided tile, whose rows are its front and then its back: the front on the left and the
+/// back on the right, each drawn as `tile_marks` would in half the slot.
+pub fn two_sided_marks(rows: &[Vec<u8>], orientation: Orientation, look: &Look<'_>) -> Vec<Mark> {
+    let (front, back) = rows.split_at(rows.len() / 2);
+    let scale = (1. - FACE_GAP) / 2.;
+    let top = (1. - scale) / 2.;
+    let mut marks: Vec<_> = tile_marks(front, orientation, look)
+        .into_iter()
+        .map(|mark| mark.placed(scale, 0., top))
+        .collect();
+    marks.extend(
+        tile_marks(back, orientation, look)
+            .into_iter()
+            .map(|mark| mark.placed(scale, 1. - scale, top)),
+    );
+    marks
+}
+
+/// A ring's beads, laid out the same way as in the results list. The backs of two-faced beads
+/// have a ring around them, and pointing beads have a pointer along the string.
+pub fn ring_marks(beads: &[u8], facing: Facing, look: &Look<'_>) -> Vec<Mark> {
+    let delta = TAU / beads.len() as f32;
+    let distance = 0.25;
+    // Neighbouring beads just touch
+    let radius = if beads.len() > 1 {
+        distance * (delta / 2.).sin()
+    } else {
+        distance
+    };
+    let mut theta = if beads.len().is_multiple_of(2) {
+        delta / 2.
+    } else {
+        0.
+    };
+    // The biggest square that fits in the bead
+    let symbol_size = match facing {
+        Facing::Symmetric => radius * std::f32::consts::SQRT_2,
+        _ => radius * std::f32::consts::SQRT_2 * ORIENTED_SYMBOL,
+    };
+    let mut marks = vec![];
+    for &value in beads {
+        let (index, state) = facing.split(value);
+        let (x, y) = (0.5 + theta.sin() * distance, 0.5 - theta.cos() * distance);
+        if let Some(outline) = look.outline() {
+            marks.push(Mark::Circle {
+                x,
+                y,
+                radius,
+                colour: outline,
+            });
+        }
+        let fill_radius = match look.outline() {
+            Some(_) => radius * (1. - 2. * OUTLINE),
+            None => radius,
+        };
+        marks.push(Mark::Circle {
+            x,
+            y,
+            radius: fill_radius,
+            colour: look.fill(index),
+        });
+        let ink = look.ink(index);
+        match (facing, state) {
+            (Facing::TwoFaced, 1) => {
+                marks.push(Mark::Circle {
+                    x,
+                    y,
+                    radius: fill_radius * 0.9,
+                    colour: ink,
+                });
+                marks.push(Mark::Circle {
+                    x,
+                    y,
+                    radius: fill_radius * 0.78,
+                    colour: look.fill(index),
+                });
+            }
+            (Facing::Pointing, _) => {
+                // Along the string, clockwise unless the bead is the other way round, and out
+                // towards the rim
+                let along = if state == 0 { 1. } else { -1. };
+                let (tx, ty) = (theta.cos() * along, theta.sin() * along);
+                let (ox, oy) = (theta.sin(), -theta.cos());
+                let point = |t: f32, o: f32| {
+                    (
+                        x + (tx * t + ox * o) * radius,
+                        y + (ty * t + oy * o) * radius,
+                    )
+                };
+                marks.push(Mark::Path {
+                    contours: vec![vec![point(0.4, 0.6), point(-0.2, 0.85), point(-0.2, 0.35)]],
+                    colour: ink,
+                });
+            }
+            _ => {}
+        }
+        marks.extend(look.symbol(index, x, y, symbol_size));
+        theta += delta;
+    }
+    marks
+}
+
+/// How designs are arranged on a sheet, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct SheetLayout {
+    /// The width and height of each design's slot.
+    pub cell_size: u32,
+    /// The gap between slots, and around the edge of the sheet.
+    pub padding: u32,
+    pub columns: u32,
+}
+
+impl Default for SheetLayout {
+    fn default() -> Self {
+        SheetLayout {
+            cell_size: 64,
+            padding: 8,
+            columns: 10,
+        }
+    }
+}
+
+impl SheetLayout {
+    fn columns(&self, n_designs: usize) -> u32 {
+        self.columns.min(n_designs as u32).max(1)
+    }
+
+    /// The width and height of a sheet holding `n_designs`.
+    pub fn size(&self, n_designs: usize) -> (u32, u32) {
+        let columns = self.columns(n_designs);
+        let rows = (n_designs as u32).div_ceil(columns).max(1);
+        let pitch = self.cell_size + self.padding;
+        (self.padding + columns * pitch, self.padding + rows * pitch)
+    }
+
+    /// The top left corner of design `index`'s slot.
+    fn origin(&self, index: usize, n_designs: usize) -> (u32, u32) {
+        let columns = self.columns(n_designs) as usize;
+        let pitch = self.cell_size + self.padding;
+        (
+            self.padding + (index % columns) as u32 * pitch,
+            self.padding + (index / columns) as u32 * pitch,
+        )
+    }
+
+    /// A transparent PNG with each design in its own slot.
+    pub fn png(&self, designs: &[Vec<Mark>]) -> Vec<u8> {
+        let (width, height) = self.size(designs.len());
+        let mut image = RgbaImage::new(width, height);
+        for (i, marks) in designs.iter().enumerate() {
+            let (x, y) = self.origin(i, designs.len());
+            for mark in marks {
+                draw(&mut image, mark, x as f32, y as f32, self.cell_size as f32);
+            }
+        }
+        let mut bytes = vec![];
+        image
+            .write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    /// The same layout as `png`, with each design in its own `<g id="design-N">`.
+    pub fn svg(&self, designs: &[Vec<Mark>]) -> String {
+        let (width, height) = self.size(designs.len());
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n"
+        );
+        // Each picture is stored once, and used wherever it appears
+        let pictures = pictures(designs);
+        if !pictures.is_empty() {
+            svg.push_str("<defs>\n");
+            for (i, picture) in pictures.iter().enumerate() {
+                let _ = writeln!(
+                    svg,
+                    "<image id=\"picture-{i}\" width=\"1\" height=\"1\" preserveAspectRatio=\"none\" href=\"data:image/png;base64,{}\"/>",
+                    png_base64(picture)
+                );
+            }
+            svg.push_str("</defs>\n");
+        }
+        for (i, marks) in designs.iter().enumerate() {
+            let (x, y) = self.origin(i, designs.len());
+            let scale = self.cell_size;
+            let _ = writeln!(
+                svg,
+                "<g id=\"design-{i}\" transform=\"translate({x} {y}) scale({scale})\">"
+            );
+            for mark in marks {
+                let _ = match mark {
+                    Mark::Square { x, y, size, colour } => writeln!(
+                        svg,
+                        "<rect x=\"{x}\" y=\"{y}\" width=\"{size}\" height=\"{size}\" fill=\"{}\"/>",
+                        hex(*colour)
+                    ),
+                    Mark::Circle {
+                        x,
+                        y,
+                        radius,
+                        colour,
+                    } => writeln!(
+                        svg,
+                        "<circle cx=\"{x}\" cy=\"{y}\" r=\"{radius}\" fill=\"{}\"/>",
+                        hex(*colour)
+                    ),
+                    Mark::Path { contours, colour } => {
+                        let mut d = String::new();
+                        for contour in contours {
+                            for (i, (x, y)) in contour.iter().enumerate() {
+                                let command = if i == 0 { 'M' } else { 'L' };
+                                let _ = write!(d, "{command}{x:.4} {y:.4}");
+                            }
+                            d.push('Z');
+                        }
+                        writeln!(svg, "<path d=\"{d}\" fill=\"{}\"/>", hex(*colour))
+                    }
+                    Mark::Image {
+                        x, y, size, image, ..
+                    } => {
+                        let i = pictures
+                            .iter()
+                            .position(|picture| Arc::ptr_eq(picture, image))
+                            .unwrap();
+                        writeln!(
+                            svg,
+                            "<use href=\"#picture-{i}\" transform=\"translate({x} {y}) scale({size})\"/>"
+                        )
+                    }
+                };
+            }
+            svg.push_str("</g>\n");
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    pub fn settings(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.cell_size, 8..=256).text("Cell size (px)"));
+        ui.add(egui::Slider::new(&mut self.padding, 0..=64).text("Padding (px)"));
+        ui.add(egui::Slider::new(&mut self.columns, 1..=50).text("Columns"));
+    }
+}
+
+/// Draws `marks` in a new `size`-point square in `ui`.
+pub fn paint(ui: &mut egui::Ui, marks: &[Mark], size: f32) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(size, size), egui::Sense::hover());
+    paint_at(ui, rect.min, size, marks);
+    response
+}
+
+/// Draws `marks` in the `size`-point slot with its top left corner at `origin`.
+pub fn paint_at(ui: &egui::Ui, origin: egui::Pos2, size: f32, marks: &[Mark]) {
+    let painter = ui.painter();
+    let point = |x: f32, y: f32| origin + egui::vec2(x, y) * size;
+    for mark in marks {
+        match *mark {
+            Mark::Square {
+                x,
+                y,
+                size: side,
+                colour,
+            } => {
+                let square = egui::Rect::from_min_size(point(x, y), egui::vec2(side, side) * size);
+                painter.rect_filled(square, 0., colour);
+            }
+            Mark::Circle {
+                x,
+                y,
+                radius,
+                colour,
+            } => {
+                painter.circle_filled(point(x, y), radius * size, colour);
+            }
+            Mark::Path {
+                ref contours,
+                colour,
+            } => {
+                // egui only fills convex shapes, so fill a row of pixels at a time
+                let step = 1. / ui.ctx().pixels_per_point() / size;
+                let (top, bottom) = contours
+                    .iter()
+                    .flatten()
+                    .fold((f32::MAX, f32::MIN), |(top, bottom), &(_, y)| {
+                        (top.min(y), bottom.max(y))
+                    });
+                let mut mesh = egui::Mesh::default();
+                let mut y = top + step / 2.;
+                while y < bottom {
+                    for (left, right) in spans(contours, y) {
+                        let row = egui::Rect::from_min_max(
+                            point(left, y - step / 2.),
+                            point(right, y + step / 2.),
+                        );
+                        mesh.add_colored_rect(row, colour);
+                    }
+                    y += step;
+                }
+                painter.add(egui::Shape::mesh(mesh));
+            }
+            Mark::Image {
+                x,
+                y,
+                size: side,
+                texture,
+                ..
+            } => {
+                if let Some(texture) = texture {
+                    let square =
+                        egui::Rect::from_min_size(point(x, y), egui::vec2(side, side) * size);
+                    let uv = egui::Rect::from_min_max(egui::pos2(0., 0.), egui::pos2(1., 1.));
+                    painter.add(egui::Shape::image(texture, square, uv, Color32::WHITE));
+                }
+            }
+        }
+    }
+}
+
+/// Fills `mark` into the slot at (`left`, `top`), `scale` pixels across.
+pub fn draw(image: &mut RgbaImage, mark: &Mark, left: f32, top: f32, scale: f32) {
+    match *mark {
+        Mark::Square { x, y, size, colour } => {
+            // Round both edges the same way so neighbouring cells meet without gaps or overlaps
+            let x0 = (left + x * scale).round() as u32;
+            let x1 = (left + (x + size) * scale).round() as u32;
+            let y0 = (top + y * scale).round() as u32;
+            let y1 = (top + (y + size) * scale).round() as u32;
+            let pixel = Rgba([colour.r(), colour.g(), colour.b(), 255]);
+            for py in y0..y1.min(image.height()) {
+                for px in x0..x1.min(image.width()) {
+                    image.put_pixel(px, py, pixel);
+                }
+            }
+        }
+        Mark::Circle {
+            x,
+            y,
+            radius,
+            colour,
+        } => {
+            let cx = left + x * scale;
+            let cy = top + y * scale;
+            let r = radius * scale;
+            let x0 = (cx - r).floor().max(0.) as u32;
+            let x1 = ((cx + r).ceil() as u32).min(image.width());
+            let y0 = (cy - r).floor().max(0.) as u32;
+            let y1 = ((cy + r).ceil() as u32).min(image.height());
+            for py in y0..y1 {
+                for px in x0..x1 {
+                    let distance = (px as f32 + 0.5 - cx).hypot(py as f32 + 0.5 - cy);
+                    // Anti-alias the edge over about a pixel
+                    let coverage = (r - distance + 0.5).clamp(0., 1.);
+                    if coverage > 0. {
+                        let alpha = (coverage * 255.).round() as u8;
+                        let pixel = Rgba([colour.r(), colour.g(), colour.b(), alpha]);
+                        image.get_pixel_mut(px, py).blend(&pixel);
+                    }
+                }
+            }
+        }
+        Mark::Path {
+            ref contours,
+            colour,
+        } => {
+            let contours: Vec<Vec<(f32, f32)>> = contours
+                .iter()
+                .map(|contour| {
+                    contour
+                        .iter()
+                        .map(|&(x, y)| (left + x * scale, top + y * scale))
+                        .collect()
+                })
+                .collect();
+            let (top, bottom) = contours
+                .iter()
+                .flatten()
+                .fold((f32::MAX, f32::MIN), |(top, bottom), &(_, y)| {
+                    (top.min(y), bottom.max(y))
+                });
+            let y0 = top.floor().max(0.) as u32;
+            let y1 = (bottom.ceil().max(0.) as u32).min(image.height());
+            // Anti-alias by measuring how much of each pixel a few rows through it cover
+            const ROWS: usize = 4;
+            let mut coverage = vec![0.; image.width() as usize];
+            for py in y0..y1 {
+                coverage.iter_mut().for_each(|c| *c = 0.);
+                for row in 0..ROWS {
+                    let y = py as f32 + (row as f32 + 0.5) / ROWS as f32;
+                    for (start, end) in spans(&contours, y) {
+                        let start = start.max(0.);
+                        let end = end.min(image.width() as f32);
+                        let mut px = start.floor();
+                        while px < end {
+                            let covered = end.min(px + 1.) - start.max(px);
+                            coverage[px as usize] += covered / ROWS as f32;
+                            px += 1.;
+                        }
+                    }
+                }
+                for (px, &covered) in coverage.iter().enumerate() {
+                    if covered > 0. {
+                        let alpha = (covered.min(1.) * 255.).round() as u8;
+                        let pixel = Rgba([colour.r(), colour.g(), colour.b(), alpha]);
+                        image.get_pixel_mut(px as u32, py).blend(&pixel);
+                    }
+                }
+            }
+        }
+        Mark::Image {
+            x,
+            y,
+            size,
+            image: ref picture,
+            ..
+        } => {
+            let side = (size * scale).round().max(1.) as u32;
+            let picture = image::imageops::resize(&**picture, side, side, FilterType::Triangle);
+            let x0 = (left + x * scale).round() as i64;
+            let y0 = (top + y * scale).round() as i64;
+            for (px, py, pixel) in picture.enumerate_pixels() {
+                let (x, y) = (x0 + px as i64, y0 + py as i64);
+                if (0..image.width() as i64).contains(&x) && (0..image.height() as i64).contains(&y)
+                {
+                    image.get_pixel_mut(x as u32, y as u32).blend(pixel);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::app::{palettes::A, symbols::Symbols};
+    #[test]
+    fn test_sheet() {
+        let plain = Look::plain(&A);
+        let layout = SheetLayout {
+            cell_size: 20,
+            padding: 2,
+            columns: 2,
+        };
+        let designs = vec![
+            tile_marks(&[vec![0, 1], vec![1, 0]], Orientation::Symmetric, &plain),
+            tile_marks(&[vec![1, 1], vec![1, 1]], Orientation::Symmetric, &plain),
+            ring_marks(&[0, 1, 2], Facing::Symmetric, &plain),
+        ];
+        assert_eq!(layout.size(designs.len()), (46, 46));
+        assert_eq!(layout.size(1), (24, 24));
+
+        let png = image::load_from_memory(&layout.png(&designs))
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(png.dimensions(), (46, 46));
+        let rgba = |c: Color32| Rgba([c.r(), c.g(), c.b(), 255]);
+        // Top left cell of the first tile, then the bottom left cell
+        assert_eq!(*png.get_pixel(3, 3), rgba(A[0]));
+        assert_eq!(*png.get_pixel(3, 20), rgba(A[1]));
+        // Padding stays transparent
+        assert_eq!(png.get_pixel(1, 1)[3], 0);
+        assert_eq!(png.get_pixel(23, 10)[3], 0);
+
+        let svg = layout.svg(&designs);
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<rect").count(), 8);
+        assert_eq!(svg.matches("<circle").count(), 3);
+        assert!(svg.contains("<g id=\"design-2\" transform=\"translate(2 24) scale(20)\">"));
+        assert!(svg.contains(&format!("fill=\"{}\"", hex(A[2]))));
+
+        // Letters are paths, and each picture is stored once however often it's used
+        let mut png = vec![];
+        RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 255]))
+            .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+            .unwrap();
+        let mut symbols = Symbols::default();
+        symbols.set_picture(1, "dot.png", &png).unwrap();
+        let look = Look {
+            palette: A[..].into(),
+            symbols: &symbols,
+        };
+        let designs = vec![ring_marks(&[0, 1, 1], Facing::Symmetric, &look)];
+        let svg = layout.svg(&designs);
+        assert_eq!(svg.matches("<path").count(), 1);
+        assert_eq!(svg.matches("<image").count(), 1);
+        assert_eq!(svg.matches("<use href=\"#picture-0\"").count(), 2);
+        let png = image::load_from_memory(&layout.png(&designs))
+            .unwrap()
+            .to_rgba8();
+        // The middle of the second bead is the picture
+        assert_eq!(*png.get_pixel(2 + 14, 2 + 12), Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_pointers() {
+        let plain = Look::plain(&A);
+        // Which edge of the cell each arrow's pointer is against
+        let tips = |orientation: Orientation, state: u8| -> Vec<(f32, f32)> {
+            let marks = tile_marks(&[vec![orientation.join(0, state)]], orientation, &plain);
+            match marks.last() {
+                Some(Mark::Path { contours, colour }) => {
+                    assert_eq!(*colour, plain.ink(0));
+                    contours.iter().map(|contour| contour[0]).collect()
+                }
+                _ => vec![],
+            }
+        };
+        assert_eq!(tips(Orientation::Symmetric, 0), []);
+        let arrow = |state| tips(Orientation::Arrow, state)[0];
+        assert!((arrow(0).1 - 0.04).abs() < 1e-6);
+        assert!((arrow(1).0 - 0.96).abs() < 1e-6);
+        assert!((arrow(2).1 - 0.96).abs() < 1e-6);
+        assert!((arrow(3).0 - 0.04).abs() < 1e-6);
+        assert_eq!(tips(Orientation::Line, 1).len(), 2);
+        // A mirrored L-shape has its corner on the other side
+        let corner = |state| tips(Orientation::Chiral, state)[0].0;
+        assert!(corner(0) < 0.5 && corner(4) > 0.5);
+
+        // The backs of charms have a ring around them, and pointing beads a pointer
+        let marks = |facing: Facing, beads: &[u8]| ring_marks(beads, facing, &plain);
+        assert_eq!(marks(Facing::TwoFaced, &[0, 1]).len(), 4);
+        assert_eq!(marks(Facing::TwoFaced, &[0, 0]).len(), 2);
+        let pointer = |bead| match &marks(Facing::Pointing, &[bead, 0, 0])[1] {
+            Mark::Path { contours, .. } => contours[0][0].0,
+            mark => panic!("{:?} isn't a pointer", mark),
+        };
+        // The first bead is at the top, so clockwise is to the right
+        assert!(pointer(0) > 0.5 && pointer(1) < 0.5);
+    }
+
+    #[test]
+    fn test_two_sided() {
+        let plain = Look::plain(&A);
+        let squares: Vec<_> = two_sided_marks(&[vec![0], vec![1]], Orientation::Symmetric, &plain)
+            .into_iter()
+            .filter_map(|mark| match mark {
+                Mark::Square { x, y, size, colour } => Some((x, x + size, y, y + size, colour)),
+                _ => None,
+            })
+            .collect();
+        // The front fills the left half, less the gap, and the back the right half
+        assert_eq!(squares.len(), 2);
+        let (left, right, top, bottom, colour) = squares[0];
+        assert_eq!((left, colour), (0., A[0]));
+        assert!(right < 0.5 && (top + bottom - 1.).abs() < 1e-6);
+        let (left, right, _, _, colour) = squares[1];
+        assert_eq!(colour, A[1]);
+        assert!(left > 0.5 && (right - 1.).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,591 @@
+//! Symbols drawn on each marking as well as, or instead of, its colour: shapes, letters, pips or
+//! small pictures, so designs look like the real pieces and still read in greyscale.
+use std::{borrow::Cow, f32::consts::TAU, io::Cursor, sync::Arc};
+
+use ab_glyph::{Font, FontVec, OutlineCurve};
+use eframe::egui::{self, Color32, ColorImage, Context, TextureFilter, TextureHandle, Ui};
+use image::{imageops::FilterType, ImageOutputFormat, RgbaImage};
+use once_cell::sync::Lazy;
+
+use super::{
+    csv::colour_letter,
+    palettes::{colour, Palette},
+    sprites::Mark,
+};
+use crate::tile::MAX_COLOURS;
+
+/// Pictures are shrunk to fit this many pixels across when they're loaded.
+const PICTURE_SIZE: u32 = 64;
+/// How much of the space inside a marking its symbol takes up.
+const SYMBOL_SCALE: f32 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum SymbolKind {
+    /// Just the palette colour
+    None,
+    Shapes,
+    Letters,
+    Pips,
+    Pictures,
+}
+
+impl SymbolKind {
+    const ALL: [SymbolKind; 5] = [
+        SymbolKind::None,
+        SymbolKind::Shapes,
+        SymbolKind::Letters,
+        SymbolKind::Pips,
+        SymbolKind::Pictures,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            SymbolKind::None => "Colour only",
+            SymbolKind::Shapes => "Shapes",
+            SymbolKind::Letters => "Letters",
+            SymbolKind::Pips => "Pips",
+            SymbolKind::Pictures => "Pictures",
+        }
+    }
+}
+
+/// The closed outlines of a symbol in a 1×1 box centred on the origin, filled by the nonzero rule.
+type Contours = Vec<Vec<(f32, f32)>>;
+
+fn regular(points: usize, radius: f32, turn: f32) -> Vec<(f32, f32)> {
+    (0..points)
+        .map(|i| {
+            let theta = TAU * (i as f32 / points as f32 + turn);
+            (radius * theta.sin(), -radius * theta.cos())
+        })
+        .collect()
+}
+
+fn star(points: usize, outer: f32, inner: f32) -> Vec<(f32, f32)> {
+    (0..2 * points)
+        .map(|i| {
+            let radius = if i % 2 == 0 { outer } else { inner };
+            let theta = TAU * i as f32 / (2 * points) as f32;
+            (radius * theta.sin(), -radius * theta.cos())
+        })
+        .collect()
+}
+
+/// A plus sign with arms `width` wide, turned by `turn` of a full turn.
+fn plus(width: f32, turn: f32) -> Vec<(f32, f32)> {
+    let (a, b) = (width / 2., 0.5);
+    let points = [
+        (-a, -b),
+        (a, -b),
+        (a, -a),
+        (b, -a),
+        (b, a),
+        (a, a),
+        (a, b),
+        (-a, b),
+        (-a, a),
+        (-b, a),
+        (-b, -a),
+        (-a, -a),
+    ];
+    let (sin, cos) = (TAU * turn).sin_cos();
+    points
+        .iter()
+        .map(|&(x, y)| (x * cos - y * sin, x * sin + y * cos))
+        .collect()
+}
+
+/// The shapes, in the order markings get them. A circle comes first, but is drawn as a `Mark`.
+static SHAPES: Lazy<Vec<Contours>> = Lazy::new(|| {
+    let mut hole = regular(32, 0.25, 0.);
+    hole.reverse();
+    vec![
+        vec![],
+        vec![vec![(-0.4, -0.4), (0.4, -0.4), (0.4, 0.4), (-0.4, 0.4)]],
+        vec![vec![(0., -0.5), (0.5, 0.4), (-0.5, 0.4)]],
+        vec![regular(4, 0.5, 0.)],
+        vec![star(5, 0.5, 0.2)],
+        vec![plus(0.3, 0.)],
+        vec![regular(6, 0.5, 0.)],
+        vec![vec![(-0.5, -0.4), (0.5, -0.4), (0., 0.5)]],
+        vec![plus(0.25, 0.125)],
+        vec![regular(5, 0.5, 0.)],
+        vec![regular(32, 0.5, 0.), hole],
+        vec![vec![(-0.5, -0.2), (0.5, -0.2), (0.5, 0.2), (-0.5, 0.2)]],
+    ]
+});
+
+/// The outline of each letter, with capitals 1 high, or smaller if they'd not fit a 1×1 box.
+static LETTERS: Lazy<Vec<Contours>> = Lazy::new(|| {
+    let fonts = egui::FontDefinitions::default();
+    let font = FontVec::try_from_vec(fonts.font_data["Hack"].font.to_vec()).unwrap();
+    let outline = |c: char| -> Contours {
+        let Some(outline) = font.outline(font.glyph_id(c)) else {
+            return vec![];
+        };
+        // Fonts have y going up
+        let point = |p: ab_glyph::Point| (p.x, -p.y);
+        let mut contours: Contours = vec![];
+        for curve in &outline.curves {
+            let (start, points) = match *curve {
+                OutlineCurve::Line(p0, p1) => (p0, vec![point(p1)]),
+                OutlineCurve::Quad(p0, p1, p2) => (
+                    p0,
+                    (1..=4)
+                        .map(|i| {
+                            let t = i as f32 / 4.;
+                            let u = 1. - t;
+                            point(ab_glyph::point(
+                                u * u * p0.x + 2. * u * t * p1.x + t * t * p2.x,
+                                u * u * p0.y + 2. * u * t * p1.y + t * t * p2.y,
+                            ))
+                        })
+                        .collect(),
+                ),
+                OutlineCurve::Cubic(p0, p1, p2, p3) => (
+                    p0,
+                    (1..=6)
+                        .map(|i| {
+                            let t = i as f32 / 6.;
+                            let u = 1. - t;
+                            let (a, b, c, d) =
+                                (u * u * u, 3. * u * u * t, 3. * u * t * t, t * t * t);
+                            point(ab_glyph::point(
+                                a * p0.x + b * p1.x + c * p2.x + d * p3.x,
+                                a * p0.y + b * p1.y + c * p2.y + d * p3.y,
+                            ))
+                        })
+                        .collect(),
+                ),
+            };
+            let start = point(start);
+            match contours.last_mut() {
+                Some(contour) if contour.last() == Some(&start) => contour.extend(points),
+                _ => contours.push([vec![start], points].concat()),
+            }
+        }
+        contours
+    };
+    // The left, top, right and bottom of some contours
+    let bounds = |contours: &Contours| {
+        contours.iter().flatten().fold(
+            [f32::MAX, f32::MAX, f32::MIN, f32::MIN],
+            |[left, top, right, bottom], &(x, y)| {
+                [left.min(x), top.min(y), right.max(x), bottom.max(y)]
+            },
+        )
+    };
+    let [_, top, _, bottom] = bounds(&outline('H'));
+    let cap_height = bottom - top;
+    (0..MAX_COLOURS as u8)
+        .map(|index| {
+            let contours = outline(colour_letter(index));
+            let [left, top, right, bottom] = bounds(&contours);
+            let scale = cap_height.max(right - left).max(bottom - top);
+            let centre = ((left + right) / 2., (top + bottom) / 2.);
+            contours
+                .iter()
+                .map(|contour| {
+                    contour
+                        .iter()
+                        .map(|&(x, y)| ((x - centre.0) / scale, (y - centre.1) / scale))
+                        .collect()
+                })
+                .collect()
+        })
+        .collect()
+});
+
+/// Where the pips go for a count, like a die's up to 9, and in rows after that.
+fn pips(count: usize) -> (Vec<(f32, f32)>, f32) {
+    let at = |cells: &[usize]| -> Vec<(f32, f32)> {
+        cells
+            .iter()
+            .map(|&cell| {
+                (
+                    (cell % 3) as f32 * 0.35 - 0.35,
+                    (cell / 3) as f32 * 0.35 - 0.35,
+                )
+            })
+            .collect()
+    };
+    match count {
+        1 => (at(&[4]), 0.14),
+        2 => (at(&[2, 6]), 0.13),
+        3 => (at(&[2, 4, 6]), 0.13),
+        4 => (at(&[0, 2, 6, 8]), 0.13),
+        5 => (at(&[0, 2, 4, 6, 8]), 0.13),
+        6 => (at(&[0, 2, 3, 5, 6, 8]), 0.13),
+        7 => (at(&[0, 2, 3, 4, 5, 6, 8]), 0.12),
+        8 => (at(&[0, 1, 2, 3, 5, 6, 7, 8]), 0.12),
+        9 => (at(&[0, 1, 2, 3, 4, 5, 6, 7, 8]), 0.12),
+        _ => {
+            let columns = (count as f32).sqrt().ceil() as usize;
+            let pitch = 1. / columns as f32;
+            let rows = count.div_ceil(columns);
+            let top = -(rows as f32 - 1.) * pitch / 2.;
+            let points = (0..count)
+                .map(|i| {
+                    let x = ((i % columns) as f32 + 0.5) * pitch - 0.5;
+                    (x, top + (i / columns) as f32 * pitch)
+                })
+                .collect();
+            (points, pitch * 0.4)
+        }
+    }
+}
+
+/// Saves pictures as base64 PNGs.
+mod png_pictures {
+    use std::sync::Arc;
+
+    use image::RgbaImage;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        pictures: &[Option<Arc<RgbaImage>>],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            pictures
+                .iter()
+                .map(|picture| picture.as_ref().map(|image| super::png_base64(image))),
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Option<Arc<RgbaImage>>>, D::Error> {
+        Vec::<Option<String>>::deserialize(deserializer)?
+            .iter()
+            .map(|png| {
+                png.as_ref()
+                    .map(|png| {
+                        let bytes = base64::decode(png).map_err(D::Error::custom)?;
+                        let image = image::load_from_memory(&bytes).map_err(D::Error::custom)?;
+                        Ok(Arc::new(image.to_rgba8()))
+                    })
+                    .transpose()
+            })
+            .collect()
+    }
+}
+
+/// `image` as a PNG, in base64.
+pub fn png_base64(image: &RgbaImage) -> String {
+    let mut png = vec![];
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+        .unwrap();
+    base64::encode(png)
+}
+
+/// What's drawn on each marking besides its colour.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Symbols {
+    kind: SymbolKind,
+    /// Fill markings with their palette colour; otherwise they're white with a black symbol.
+    coloured: bool,
+    /// A picture for each marking, for `SymbolKind::Pictures`.
+    #[serde(with = "png_pictures")]
+    pictures: Vec<Option<Arc<RgbaImage>>>,
+    #[serde(skip)]
+    textures: Vec<Option<TextureHandle>>,
+}
+
+impl Default for Symbols {
+    fn default() -> Self {
+        Symbols {
+            kind: SymbolKind::None,
+            coloured: true,
+            pictures: vec![],
+            textures: vec![],
+        }
+    }
+}
+
+/// Plain coloured markings, for `Look::plain`.
+#[cfg(test)]
+static PLAIN: Lazy<Symbols> = Lazy::new(Symbols::default);
+
+impl Symbols {
+    /// Sets marking `index`'s picture from an image file.
+    pub fn set_picture(
+        &mut self,
+        index: usize,
+        name: &str,
+        bytes: &[u8],
+    ) -> Result<String, String> {
+        let image =
+            image::load_from_memory(bytes).map_err(|e| format!("Couldn't read {name}: {e}"))?;
+        let image = if image.width() > PICTURE_SIZE || image.height() > PICTURE_SIZE {
+            image.resize(PICTURE_SIZE, PICTURE_SIZE, FilterType::Triangle)
+        } else {
+            image
+        };
+        if self.pictures.len() <= index {
+            self.pictures.resize(index + 1, None);
+        }
+        self.pictures[index] = Some(Arc::new(image.to_rgba8()));
+        self.textures.clear();
+        self.kind = SymbolKind::Pictures;
+        Ok(format!(
+            "Using {name} for marking {}",
+            colour_letter(index as u8)
+        ))
+    }
+
+    /// Gets the pictures ready to show.
+    pub fn prepare(&mut self, ctx: &Context) {
+        if self.textures.len() == self.pictures.len() {
+            return;
+        }
+        self.textures = self
+            .pictures
+            .iter()
+            .enumerate()
+            .map(|(i, picture)| {
+                picture.as_ref().map(|image| {
+                    let size = [image.width() as usize, image.height() as usize];
+                    let image = ColorImage::from_rgba_unmultiplied(size, image.as_raw());
+                    ctx.load_texture(format!("symbol-{i}"), image, TextureFilter::Linear)
+                })
+            })
+            .collect()
+    }
+
+    /// Shows the settings for the first `n_colours` markings. Gives the marking to load a picture
+    /// for, if asked.
+    pub fn settings(&mut self, ui: &mut Ui, n_colours: usize) -> Option<usize> {
+        let mut load = None;
+        egui::ComboBox::from_label("Symbols")
+            .selected_text(self.kind.label())
+            .show_ui(ui, |ui| {
+                for kind in SymbolKind::ALL {
+                    ui.selectable_value(&mut self.kind, kind, kind.label());
+                }
+            });
+        ui.add_enabled(
+            self.kind != SymbolKind::None,
+            egui::Checkbox::new(&mut self.coloured, "Fill with palette colours"),
+        );
+        if self.kind == SymbolKind::Pictures {
+            let mut clear = None;
+            egui::Grid::new("symbol_pictures").show(ui, |ui| {
+                for i in 0..n_colours {
+                    ui.label(colour_letter(i as u8).to_string());
+                    match self.textures.get(i).and_then(Option::as_ref) {
+                        Some(texture) => {
+                            ui.image(texture, (20., 20.));
+                        }
+                        None => {
+                            ui.label("—");
+                        }
+                    }
+                    if ui.button("Load…").clicked() {
+                        load = Some(i);
+                    }
+                    if self.pictures.get(i).is_some_and(Option::is_some)
+                        && ui.small_button("✖").on_hover_text("Remove").clicked()
+                    {
+                        clear = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+            if let Some(i) = clear {
+                self.pictures[i] = None;
+                self.textures.clear();
+            }
+            ui.label("Markings without a picture show their letter.");
+        }
+        load
+    }
+}
+
+/// Everything that decides how a marking looks.
+pub struct Look<'a> {
+    pub palette: Cow<'a, Palette>,
+    pub symbols: &'a Symbols,
+}
+
+impl<'a> Look<'a> {
+    /// Just the colours of `palette`.
+    #[cfg(test)]
+    pub fn plain(palette: &'a Palette) -> Self {
+        Look {
+            palette: Cow::Borrowed(palette),
+            symbols: &PLAIN,
+        }
+    }
+
+    /// The background of marking `index`.
+    pub fn fill(&self, index: u8) -> Color32 {
+        if self.symbols.coloured || self.symbols.kind == SymbolKind::None {
+            colour(&self.palette, index)
+        } else {
+            Color32::WHITE
+        }
+    }
+
+    /// The colour to outline markings in, when they're not filled with colour and might not show
+    /// up against the background.
+    pub fn outline(&self) -> Option<Color32> {
+        (!self.symbols.coloured && self.symbols.kind != SymbolKind::None).then_some(Color32::BLACK)
+    }
+
+    /// What to draw on top of marking `index` in: black or white, whichever shows up better.
+    pub fn ink(&self, index: u8) -> Color32 {
+        let fill = self.fill(index);
+        let luminance = 0.299 * fill.r() as f32 + 0.587 * fill.g() as f32 + 0.114 * fill.b() as f32;
+        if luminance > 140. {
+            Color32::BLACK
+        } else {
+            Color32::WHITE
+        }
+    }
+
+    /// The symbol for marking `index` in a `size` square centred on (`x`, `y`).
+    pub fn symbol(&self, index: u8, x: f32, y: f32, size: f32) -> Vec<Mark> {
+        let ink = self.ink(index);
+        let size = size * SYMBOL_SCALE;
+        let path = |contours: &Contours, scale: f32| Mark::Path {
+            contours: contours
+                .iter()
+                .map(|contour| {
+                    contour
+                        .iter()
+                        .map(|&(px, py)| (x + px * scale, y + py * scale))
+                        .collect()
+                })
+                .collect(),
+            colour: ink,
+        };
+        let letter = || vec![path(&LETTERS[index as usize % LETTERS.len()], size)];
+        match self.symbols.kind {
+            SymbolKind::None => vec![],
+            SymbolKind::Shapes => match index {
+                0 => vec![Mark::Circle {
+                    x,
+                    y,
+                    radius: size / 2.,
+                    colour: ink,
+                }],
+                _ => match SHAPES.get(index as usize) {
+                    Some(shape) => vec![path(shape, size)],
+                    None => letter(),
+                },
+            },
+            SymbolKind::Letters => letter(),
+            SymbolKind::Pips => {
+                let (points, radius) = pips(index as usize + 1);
+                points
+                    .into_iter()
+                    .map(|(px, py)| Mark::Circle {
+                        x: x + px * size,
+                        y: y + py * size,
+                        radius: radius * size,
+                        colour: ink,
+                    })
+                    .collect()
+            }
+            SymbolKind::Pictures => match self.symbols.pictures.get(index as usize) {
+                Some(Some(image)) => vec![Mark::Image {
+                    x: x - size / 2.,
+                    y: y - size / 2.,
+                    size,
+                    image: image.clone(),
+                    texture: self
+                        .symbols
+                        .textures
+                        .get(index as usize)
+                        .and_then(|texture| texture.as_ref().map(TextureHandle::id)),
+                }],
+                _ => letter(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::app::palettes::A;
+    #[test]
+    fn test_symbols() {
+        let plain = Look::plain(&A);
+        assert_eq!(plain.fill(1), A[1]);
+        assert!(plain.symbol(1, 0.5, 0.5, 1.).is_empty());
+
+        let mut symbols = Symbols {
+            kind: SymbolKind::Pips,
+            coloured: false,
+            ..Default::default()
+        };
+        let look = Look {
+            palette: Cow::Borrowed(&A),
+            symbols: &symbols,
+        };
+        assert_eq!(look.fill(1), Color32::WHITE);
+        for count in 1..=MAX_COLOURS as u8 {
+            let marks = look.symbol(count - 1, 0.5, 0.5, 1.);
+            assert_eq!(marks.len(), count as usize);
+            // Black on white, and inside the marking
+            for mark in marks {
+                let Mark::Circle {
+                    x,
+                    y,
+                    radius,
+                    colour,
+                } = mark
+                else {
+                    panic!("{:?} isn't a pip", mark);
+                };
+                assert_eq!(colour, Color32::BLACK);
+                assert!(x - radius >= 0. && x + radius <= 1.);
+                assert!(y - radius >= 0. && y + radius <= 1.);
+            }
+        }
+
+        symbols.kind = SymbolKind::Letters;
+        let look = Look {
+            palette: Cow::Borrowed(&A),
+            symbols: &symbols,
+        };
+        for index in 0..MAX_COLOURS as u8 {
+            let [Mark::Path { contours, .. }] = &look.symbol(index, 0.5, 0.5, 1.)[..] else {
+                panic!("{} isn't one path", colour_letter(index));
+            };
+            assert!(!contours.is_empty());
+            assert!(contours
+                .iter()
+                .flatten()
+                .all(|&(x, y)| (0. ..=1.).contains(&x) && (0. ..=1.).contains(&y)));
+        }
+
+        // Pictures fall back to letters, and survive a save
+        let mut png = vec![];
+        RgbaImage::from_pixel(100, 50, image::Rgba([255, 0, 0, 255]))
+            .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+            .unwrap();
+        symbols.set_picture(2, "star.png", &png).unwrap();
+        assert_eq!(symbols.pictures[2].as_ref().unwrap().dimensions(), (64, 32));
+        let saved = serde_json::to_value(&symbols).unwrap();
+        let loaded: Symbols = serde_json::from_value(saved).unwrap();
+        assert_eq!(loaded.pictures, symbols.pictures);
+        let look = Look {
+            palette: Cow::Borrowed(&A),
+            symbols: &loaded,
+        };
+        assert!(matches!(
+            look.symbol(2, 0.5, 0.5, 1.)[..],
+            [Mark::Image { .. }]
+        ));
+        assert!(matches!(
+            look.symbol(1, 0.5, 0.5, 1.)[..],
+            [Mark::Path { .. }]
+        ));
+        assert!(symbols.set_picture(0, "notes.txt", b"hello").is_err());
+    }
+}
@@ -0,0 +1,213 @@
+//! Tabletop Simulator custom decks: sheets of 10×7 cards with a design on each, and the saved
+//! object that puts them together into a deck. Load the `.json` through Objects → Saved Objects
+//! once the images are somewhere TTS can reach.
+use std::io::Cursor;
+
+use eframe::egui;
+use image::{imageops, ImageOutputFormat, Rgba, RgbaImage};
+use serde_json::{json, Value};
+
+use super::sprites::{draw, Mark};
+
+const COLUMNS: usize = 10;
+const ROWS: usize = 7;
+/// TTS shows the last card on each sheet for cards hidden in someone's hand.
+const FACES_PER_SHEET: usize = COLUMNS * ROWS - 1;
+const FACE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const BACK: Rgba<u8> = Rgba([64, 64, 64, 255]);
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct DeckSettings {
+    /// The width of each card in pixels; cards are 5:7 like standard playing cards.
+    pub card_width: u32,
+    /// Where the images will be uploaded to. Left empty, the deck refers to the bare file names.
+    pub image_url: String,
+}
+
+impl Default for DeckSettings {
+    fn default() -> Self {
+        DeckSettings {
+            card_width: 250,
+            image_url: String::new(),
+        }
+    }
+}
+
+impl DeckSettings {
+    fn card_height(&self) -> u32 {
+        self.card_width * 7 / 5
+    }
+
+    /// The face sheets, the card back and the deck, as `(file name, contents)`.
+    pub fn files(&self, stem: &str, designs: &[Vec<Mark>]) -> Vec<(String, Vec<u8>)> {
+        let sheets: Vec<_> = designs.chunks(FACES_PER_SHEET.max(1)).collect();
+        let sheet_names: Vec<_> = (1..=sheets.len())
+            .map(|sheet| format!("{stem} sheet {sheet}.png"))
+            .collect();
+        let back_name = format!("{stem} back.png");
+        let deck = self.deck(stem, designs.len(), &sheet_names, &back_name);
+
+        let mut files: Vec<_> = sheet_names
+            .into_iter()
+            .zip(sheets)
+            .map(|(name, designs)| (name, self.sheet(designs)))
+            .collect();
+        files.push((back_name, png(&self.back())));
+        files.push((
+            format!("{stem} deck.json"),
+            serde_json::to_vec_pretty(&deck).unwrap(),
+        ));
+        files
+    }
+
+    fn back(&self) -> RgbaImage {
+        RgbaImage::from_pixel(self.card_width, self.card_height(), BACK)
+    }
+
+    /// Up to `FACES_PER_SHEET` designs on a full 10×7 sheet, with the hidden card in the last slot.
+    fn sheet(&self, designs: &[Vec<Mark>]) -> Vec<u8> {
+        let (width, height) = (self.card_width, self.card_height());
+        let mut image = RgbaImage::from_pixel(width * COLUMNS as u32, height * ROWS as u32, FACE);
+        // Designs take up the middle of the card, with a tenth of its width clear on each side
+        let scale = width as f32 * 0.8;
+        for (i, marks) in designs.iter().enumerate() {
+            let left = (i % COLUMNS) as u32 * width;
+            let top = (i / COLUMNS) as u32 * height;
+            let slot_left = left as f32 + width as f32 * 0.1;
+            let slot_top = top as f32 + (height as f32 - scale) / 2.;
+            for mark in marks {
+                draw(&mut image, mark, slot_left, slot_top, scale);
+            }
+        }
+        let (left, top) = ((COLUMNS - 1) as u32 * width, (ROWS - 1) as u32 * height);
+        imageops::replace(&mut image, &self.back(), left as i64, top as i64);
+        png(&image)
+    }
+
+    /// A saved object: a deck (or a single card) with one custom deck per sheet.
+    fn deck(&self, stem: &str, n_designs: usize, sheets: &[String], back: &str) -> Value {
+        let url = |name: &str| format!("{}{}", self.image_url, name.replace(' ', "%20"));
+        let custom_deck = |sheet: usize| {
+            json!({
+                (sheet + 1).to_string(): {
+                    "FaceURL": url(&sheets[sheet]),
+                    "BackURL": url(back),
+                    "NumWidth": COLUMNS,
+                    "NumHeight": ROWS,
+                    "BackIsHidden": false,
+                    "UniqueBack": false,
+                    "Type": 0,
+                }
+            })
+        };
+        let transform = json!({
+            "posX": 0, "posY": 1, "posZ": 0,
+            "rotX": 0, "rotY": 180, "rotZ": 180,
+            "scaleX": 1, "scaleY": 1, "scaleZ": 1,
+        });
+        let cards: Vec<_> = (0..n_designs)
+            .map(|i| {
+                let sheet = i / FACES_PER_SHEET;
+                json!({
+                    "Name": "Card",
+                    "Nickname": format!("#{i}"),
+                    "CardID": (sheet + 1) * 100 + i % FACES_PER_SHEET,
+                    "CustomDeck": custom_deck(sheet),
+                    "Transform": transform,
+                })
+            })
+            .collect();
+
+        let object = if let [card] = &cards[..] {
+            card.clone()
+        } else {
+            let mut all_sheets = serde_json::Map::new();
+            for sheet in 0..sheets.len() {
+                if let Value::Object(deck) = custom_deck(sheet) {
+                    all_sheets.extend(deck);
+                }
+            }
+            json!({
+                "Name": "Deck",
+                "Nickname": stem,
+                "DeckIDs": cards.iter().map(|card| card["CardID"].clone()).collect::<Vec<_>>(),
+                "CustomDeck": all_sheets,
+                "ContainedObjects": cards,
+                "Transform": transform,
+            })
+        };
+        json!({ "SaveName": stem, "ObjectStates": [object] })
+    }
+
+    pub fn settings(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.card_width, 50..=700).text("Card width (px)"));
+        ui.horizontal(|ui| {
+            ui.label("Image URL");
+            ui.text_edit_singleline(&mut self.image_url)
+                .on_hover_text("Where you'll upload the sheets, e.g. https://example.com/cards/");
+        });
+    }
+}
+
+fn png(image: &RgbaImage) -> Vec<u8> {
+    let mut bytes = vec![];
+    image
+        .write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)
+        .unwrap();
+    bytes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::app::{palettes::A, sprites::ring_marks, symbols::Look};
+    use crate::ring::Facing;
+    #[test]
+    fn test_deck() {
+        let settings = DeckSettings {
+            card_width: 50,
+            image_url: "https://example.com/".to_string(),
+        };
+        let designs = vec![ring_marks(&[0, 1, 2], Facing::Symmetric, &Look::plain(&A)); 70];
+        let files = settings.files("rings", &designs);
+        let names: Vec<_> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "rings sheet 1.png",
+                "rings sheet 2.png",
+                "rings back.png",
+                "rings deck.json"
+            ]
+        );
+
+        let sheet = image::load_from_memory(&files[0].1).unwrap().to_rgba8();
+        assert_eq!(sheet.dimensions(), (500, 490));
+        assert_eq!(*sheet.get_pixel(2, 2), FACE);
+        // The hidden card fills the bottom right slot
+        assert_eq!(*sheet.get_pixel(450, 420), BACK);
+        assert_eq!(*sheet.get_pixel(499, 489), BACK);
+
+        let deck: Value = serde_json::from_slice(&files[3].1).unwrap();
+        let deck = &deck["ObjectStates"][0];
+        assert_eq!(deck["Name"], "Deck");
+        assert_eq!(deck["DeckIDs"].as_array().unwrap().len(), 70);
+        assert_eq!(deck["DeckIDs"][68], 168);
+        assert_eq!(deck["DeckIDs"][69], 200);
+        assert_eq!(
+            deck["CustomDeck"]["2"]["FaceURL"],
+            "https://example.com/rings%20sheet%202.png"
+        );
+        assert_eq!(deck["ContainedObjects"][69]["Nickname"], "#69");
+        assert_eq!(
+            deck["ContainedObjects"][69]["CustomDeck"]["2"]["NumWidth"],
+            10
+        );
+
+        let files = settings.files("one", &designs[..1]);
+        let deck: Value = serde_json::from_slice(&files[2].1).unwrap();
+        assert_eq!(deck["ObjectStates"][0]["Name"], "Card");
+        assert_eq!(deck["ObjectStates"][0]["CardID"], 100);
+    }
+}
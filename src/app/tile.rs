@@ -1,24 +1,51 @@
-use std::collections::HashSet;
+use crate::{
+    app::downloader::make_download,
+    tile::{
+        self, AllowedTransformFamiles, Orientation, Permutation, Shape, TileSpec, GAP, MAX_CELLS,
+    },
+    Breakdown, ColourSet, SpecError,
+};
 
-use crate::app::downloader::make_download;
-
-use super::palettes::Palette;
+use super::{
+    allowed::{colours_menu, paint_allowed, within},
+    check::{check_colours, colours_used, common_shape, Canonical, Marks},
+    csv::designs_csv,
+    export::Export,
+    files::{mime, ExportFormat, Exportable},
+    palettes::PaletteChooser,
+    share::{Shared, SharedSpec},
+    sprites::{paint_at, tile_marks, two_sided_marks, Mark},
+    symbols::Look,
+    ExportSettings,
+};
 use eframe::egui;
-
-mod permutation;
-mod transforms;
-use permutation::{build_permutations, Permutation, Transforms};
-use transforms::*;
 use AllowedTransformFamiles::*;
 
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 pub struct Tile {
     n_colours: u64,
     width: u64,
     height: u64,
     allowed_xforms: AllowedTransformFamiles,
+    orientation: Orientation,
+    two_sided: bool,
+    custom_shape: bool,
+    /// The cells of the shape editor's grid that are part of the tile, one bit per cell of an
+    /// 8×8 grid, so resizing the tile doesn't lose the drawing.
+    shape_cells: u64,
+    /// The colours each cell of the shape editor's grid may take, in the same order as
+    /// `shape_cells`.
+    allowed_cells: Vec<ColourSet>,
     limit_repeats: bool,
     max_repeats: u64,
+    /// Recomputed on load: a zero count makes `settings` report a change.
+    #[serde(skip)]
     perm_count: u64,
+    /// Why the settings can't be counted, if they can't.
+    #[serde(skip)]
+    error: Option<SpecError>,
+    #[serde(skip)]
     permutations: Vec<Permutation>,
 }
 
@@ -31,7 +58,13 @@ impl Default for Tile {
             limit_repeats: false,
             max_repeats: 2,
             allowed_xforms: Rotate,
+            orientation: Orientation::Symmetric,
+            two_sided: false,
+            custom_shape: false,
+            shape_cells: u64::MAX,
+            allowed_cells: vec![ColourSet::ANY; 64],
             perm_count: 0,
+            error: None,
             permutations: vec![],
         }
     }
@@ -40,7 +73,8 @@ impl Tile {
     pub fn new() -> Self {
         Default::default()
     }
-    pub fn settings(&mut self, ui: &mut egui::Ui) -> bool {
+    /// Shows the settings, offering as many colours as the palette has; returns whether they changed.
+    pub fn settings(&mut self, ui: &mut egui::Ui, palette_len: usize) -> bool {
         let Self {
             height,
             width,
@@ -48,26 +82,50 @@ impl Tile {
             limit_repeats,
             max_repeats,
             allowed_xforms,
+            orientation,
+            two_sided,
+            custom_shape,
+            shape_cells,
+            allowed_cells,
             perm_count,
+            error,
             ..
         } = self;
-        let mut changed = *perm_count == 0;
-        let max_dimension_product = 64. / (*n_colours as f64).log2();
+        let mut changed = *perm_count == 0 && error.is_none();
+        // Every colour and orientation of every cell, on both faces, has to fit in a u64
+        let states = orientation.states();
+        let faces = if *two_sided { 2 } else { 1 };
+        let mut max_dimension_product = 64. / ((*n_colours * states) as f64).log2() / faces as f64;
+        if *custom_shape {
+            // Shapes are drawn on a grid of at most `MAX_CELLS`, less for two faces
+            max_dimension_product = max_dimension_product.min((MAX_CELLS / faces) as f64);
+        }
         let max_width = (max_dimension_product / *height as f64) as u64;
         let max_height = (max_dimension_product / *width as f64) as u64;
-        let max_colours = (64. / (*width * *height) as f64).exp2() as u64;
-        let max_max_repeats = *width * *height;
-        let min_max_repeats = (max_max_repeats + *n_colours - 1) / *n_colours;
+        let max_colours = (64. / (*width * *height * faces) as f64).exp2() as u64 / states;
+        let max_max_repeats = *width * *height * faces;
+        let min_max_repeats = max_max_repeats.div_ceil(*n_colours);
 
         ui.heading("Settings");
 
         changed |= ui
             .add(
-                egui::Slider::new(n_colours, 2..=max_colours.min(8))
-                    .text("Number of colours")
-                    .clamp_to_range(true),
+                egui::Slider::new(
+                    n_colours,
+                    2..=max_colours
+                        .min(orientation.max_colours())
+                        .min(palette_len.max(2) as u64),
+                )
+                .text("Number of colours")
+                .clamp_to_range(true),
             )
             .changed();
+        if *n_colours > palette_len as u64 {
+            ui.colored_label(
+                egui::Color32::YELLOW,
+                format!("The palette only has {palette_len} colours; the rest show grey"),
+            );
+        }
         changed |= ui
             .add(
                 egui::Slider::new(width, 1..=max_width.min(8))
@@ -82,6 +140,22 @@ impl Tile {
                     .clamp_to_range(true),
             )
             .changed();
+        changed |= ui
+            .checkbox(custom_shape, "Custom shape")
+            .on_hover_text(
+                "Cut cells out of the rectangle to make L-shapes, crosses and the like, \
+                 or right-click a cell to choose the colours it may be, like a logo",
+            )
+            .changed();
+        if *custom_shape {
+            changed |= shape_editor(ui, *width, *height, *n_colours, shape_cells, allowed_cells);
+        }
+        changed |= ui
+            .checkbox(two_sided, "Two-sided")
+            .on_hover_text(
+                "Each tile has a back, like a cardboard token; turning it over shows the other face",
+            )
+            .changed();
         changed |= ui
             .checkbox(limit_repeats, "Limit colour repetions")
             .changed();
@@ -112,177 +186,369 @@ impl Tile {
         changed |= ui
             .radio_value(allowed_xforms, RotateAndFlip, "Rotation and flip")
             .changed();
+        ui.label("Markings:").on_hover_text(
+            "Markings that aren't symmetric turn with the tile, so which way they face matters",
+        );
+        for (choice, label) in [
+            (Orientation::Symmetric, "Symmetric, like dots"),
+            (Orientation::Line, "Lines, across or down"),
+            (Orientation::Arrow, "Arrows, pointing four ways"),
+            (Orientation::Chiral, "L-shapes, four ways and mirrored"),
+        ] {
+            if ui.radio_value(orientation, choice, label).changed() {
+                changed = true;
+                *n_colours = (*n_colours).min(orientation.max_colours());
+            }
+        }
 
         ui.label(format!("There are {} distinct tiles", perm_count));
 
         changed
     }
 
-    pub fn recompute_perms(&mut self) {
-        let Self {
-            allowed_xforms,
-            width,
-            height,
-            n_colours,
-            limit_repeats,
-            max_repeats,
-            perm_count,
-            permutations,
-            ..
-        } = self;
-        let square = *width == *height;
-        let mut orbits = 0;
-        let mut fixed = 0;
-        if NoXform::applicable(*allowed_xforms, square) {
-            orbits += NoXform::ORBITS;
-            fixed += if *limit_repeats {
-                NoXform::limited_total_fixed(*width, *height, *n_colours, *max_repeats)
-            } else {
-                NoXform::total_fixed(*width, *height, *n_colours)
-            };
+    /// The cells drawn in the shape editor, in the smallest grid that holds them, if the tile
+    /// has a custom shape. Cells only allow the colours there are.
+    fn shape(&self) -> Option<Shape> {
+        if !self.custom_shape || self.width * self.height > MAX_CELLS {
+            return None;
         }
-        if Rot90::applicable(*allowed_xforms, square) {
-            orbits += Rot90::ORBITS;
-            fixed += if *limit_repeats {
-                Rot90::limited_total_fixed(*width, *height, *n_colours, *max_repeats)
-            } else {
-                Rot90::total_fixed(*width, *height, *n_colours)
-            };
-        }
-        if Rot180::applicable(*allowed_xforms, square) {
-            orbits += Rot180::ORBITS;
-            fixed += if *limit_repeats {
-                Rot180::limited_total_fixed(*width, *height, *n_colours, *max_repeats)
-            } else {
-                Rot180::total_fixed(*width, *height, *n_colours)
-            };
+        let mut shape = Shape::from_fn(self.width, self.height, |x, y| {
+            self.shape_cells & 1 << (y * 8 + x) != 0
+        });
+        for (x, y) in shape.positions().collect::<Vec<_>>() {
+            if let Some(&allowed) = self.allowed_cells.get(y * 8 + x) {
+                shape = shape.with_allowed(x, y, within(allowed, self.n_colours));
+            }
         }
-        if HFlip::applicable(*allowed_xforms, square) {
-            orbits += HFlip::ORBITS;
-            fixed += if *limit_repeats {
-                HFlip::limited_total_fixed(*width, *height, *n_colours, *max_repeats)
-            } else {
-                HFlip::total_fixed(*width, *height, *n_colours)
-            };
+        Some(shape.trimmed()).filter(|shape| !shape.is_empty())
+    }
+
+    pub fn spec(&self) -> TileSpec {
+        let shape = self.shape();
+        TileSpec {
+            width: shape.map_or(self.width, |shape| shape.width()),
+            height: shape.map_or(self.height, |shape| shape.height()),
+            n_colours: self.n_colours,
+            max_repeats: self.limit_repeats.then_some(self.max_repeats),
+            allowed_xforms: self.allowed_xforms,
+            orientation: self.orientation,
+            two_sided: self.two_sided,
+            shape,
         }
-        if VFlip::applicable(*allowed_xforms, square) {
-            orbits += VFlip::ORBITS;
-            fixed += if *limit_repeats {
-                VFlip::limited_total_fixed(*width, *height, *n_colours, *max_repeats)
-            } else {
-                VFlip::total_fixed(*width, *height, *n_colours)
-            };
+    }
+
+    /// Replaces the settings with `spec`; the results are recomputed on the next frame.
+    pub fn set_spec(&mut self, spec: TileSpec) {
+        self.width = spec.width;
+        self.height = spec.height;
+        self.n_colours = spec.n_colours;
+        self.limit_repeats = spec.max_repeats.is_some();
+        if let Some(max_repeats) = spec.max_repeats {
+            self.max_repeats = max_repeats;
         }
-        if DFlip::applicable(*allowed_xforms, square) {
-            orbits += DFlip::ORBITS;
-            fixed += if *limit_repeats {
-                DFlip::limited_total_fixed(*width, *height, *n_colours, *max_repeats)
-            } else {
-                DFlip::total_fixed(*width, *height, *n_colours)
-            };
+        self.allowed_xforms = spec.allowed_xforms;
+        self.orientation = spec.orientation;
+        self.two_sided = spec.two_sided;
+        self.custom_shape = spec.shape.is_some();
+        if let Some(shape) = spec.shape {
+            self.shape_cells = 0;
+            self.allowed_cells = vec![ColourSet::ANY; 64];
+            for (x, y) in shape.positions() {
+                self.shape_cells |= 1 << (y * 8 + x);
+                self.allowed_cells[y * 8 + x] = shape.allowed(x, y);
+            }
         }
+        self.perm_count = 0;
+        self.error = None;
+    }
 
-        *perm_count = fixed / orbits;
+    /// Takes the size and number of colours from `designs`, for lists that come without settings.
+    pub fn fit(&mut self, designs: &[Vec<Vec<u8>>]) -> Result<(), String> {
+        let mut spec = self.spec();
+        if let Some((width, rows)) = common_shape(designs) {
+            spec.width = width as u64;
+            // A two-sided tile lists its back under its front
+            spec.height = rows as u64 / spec.faces();
+            // and the gaps in the first design give the shape
+            spec.shape = None;
+            if spec.width * spec.height <= MAX_CELLS {
+                let first = &designs[0];
+                let shape = Shape::from_fn(spec.width, spec.height, |x, y| first[y][x] != GAP);
+                spec.shape = (!shape.is_rectangle()).then_some(shape);
+            }
+        }
+        spec.n_colours = spec
+            .n_colours
+            .max(colours_used(designs, spec.orientation.states()));
+        spec.validate().map_err(|e| e.to_string())?;
+        if spec != self.spec() {
+            self.set_spec(spec);
+        }
+        Ok(())
+    }
 
-        let transforms = Transforms::new(*width, *height, *allowed_xforms);
-        if *perm_count <= 10000 {
-            let mut seen = HashSet::with_capacity(*perm_count as usize);
-            let mut colour_counts = vec![0; *n_colours as usize];
-            build_permutations(
-                &transforms,
-                *width,
-                *height,
-                *n_colours,
-                0,
-                0,
-                &mut colour_counts,
-                if *limit_repeats {
-                    *max_repeats
-                } else {
-                    u64::MAX
-                },
-                Permutation::new(),
-                &mut seen,
-            );
-            permutations.clear();
-            permutations.extend(seen);
-            permutations.sort_unstable();
+    pub fn recompute_perms(&mut self) {
+        let spec = self.spec();
+        self.error = spec.validate().err();
+        if self.error.is_some() {
+            self.perm_count = 0;
+            self.permutations.clear();
+            return;
+        }
+        self.perm_count = spec.breakdown().count();
+        if self.perm_count <= 10000 && spec.validate_enumerable().is_ok() {
+            self.permutations = spec.permutations();
         } else {
-            permutations.clear();
+            self.permutations.clear();
         }
     }
 
-    pub fn render_results(&self, palette: &Palette, ui: &mut egui::Ui) {
+    pub fn render_results(
+        &self,
+        palettes: &PaletteChooser,
+        exports: &mut ExportSettings,
+        ui: &mut egui::Ui,
+    ) {
+        if let Some(error) = self.error {
+            ui.colored_label(egui::Color32::from_rgb(255, 0, 0), error.to_string());
+            return;
+        }
+        let look = palettes.preview();
         let Self {
-            width,
-            height,
+            two_sided,
             perm_count,
             permutations,
             ..
         } = self;
         if *perm_count > 10000 {
             ui.label("Too many (> 10,000) variants to display");
+        } else if self.spec().validate_enumerable().is_err() {
+            ui.label(format!(
+                "Tiles of more than {MAX_CELLS} cells can't be displayed"
+            ));
         } else {
             if *perm_count as usize != permutations.len() {
                 let message = format!("Error: Expected to find {} results, but found {} instead. Please report this as a bug to mdsherry@gmail.com", *perm_count, permutations.len());
                 ui.colored_label(egui::Color32::from_rgb(255, 0, 0), message);
             }
 
-            if cfg!(target_arch = "wasm32") && ui.button("Download JSON").clicked() {
-                let (name, bytes) = self.export_json();
-                make_download(&name, &bytes, "application/json");
+            if cfg!(target_arch = "wasm32") {
+                ui.horizontal_wrapped(|ui| {
+                    for format in ExportFormat::ALL {
+                        if ui.button(format!("Download {}", format.label())).clicked() {
+                            for (name, bytes) in self.export(format, palettes, exports) {
+                                make_download(&name, &bytes, mime(&name));
+                            }
+                        }
+                    }
+                });
             }
+            exports.settings(ui);
             ui.horizontal_wrapped(|ui| {
+                let spec = self.spec();
+                let (width, height) = (20. * spec.width as f32, 20. * spec.height as f32);
+                // Cells are 20 points, and the marks fill a square around the tile, or around
+                // both faces side by side
+                let face = width.max(height);
+                let (slot, size) = if *two_sided {
+                    (2. * face, (2. * face, height))
+                } else {
+                    (face, (width, height))
+                };
                 for permutation in permutations {
-                    let (mut rect, _response) = ui.allocate_exact_size(
-                        ((20 * *width) as f32, (20 * *height) as f32).into(),
-                        egui::Sense::hover(),
-                    );
-                    rect.set_width(20.);
-                    rect.set_height(20.);
-
-                    for x in 0..*width {
-                        for y in 0..*height {
-                            let r = rect.translate((20. * x as f32, 20. * y as f32).into());
-                            ui.painter().rect_filled(
-                                r,
-                                2.,
-                                palette[permutation.get(x as usize, y as usize, *width as usize)
-                                    as usize],
-                            );
-                        }
+                    let (rect, _response) =
+                        ui.allocate_exact_size(size.into(), egui::Sense::hover());
+                    // Only build the marks for the designs scrolled into view
+                    if ui.is_rect_visible(rect) {
+                        let origin = rect.center() - egui::vec2(slot, slot) / 2.;
+                        paint_at(
+                            ui,
+                            origin,
+                            slot,
+                            &design_marks(&spec, &spec.rows(permutation), &look),
+                        );
                     }
                 }
             });
         }
     }
+}
 
-    pub fn export_json(&self) -> (String, Vec<u8>) {
-        let Self {
-            width,
-            height,
-            n_colours,
-            allowed_xforms,
-            permutations,
-            ..
-        } = self;
-        let mut rv = vec![];
-        for permutation in permutations {
-            let mut entry = vec![];
-            for y in 0..*height {
-                let mut row = vec![];
-                for x in 0..*width {
-                    row.push(permutation.get(y as usize, x as usize, *width as usize));
+impl Canonical for Tile {
+    fn canonical(&self, rows: &[Vec<u8>]) -> Result<Vec<Vec<u8>>, String> {
+        let spec = self.spec();
+        spec.validate_enumerable().map_err(|e| e.to_string())?;
+        let n_rows = spec.height * spec.faces();
+        if common_shape(&[rows.to_vec()]) != Some((spec.width as usize, n_rows as usize)) {
+            return Err(if self.two_sided {
+                format!(
+                    "Isn't a two-sided {}×{}, with its back under its front",
+                    spec.width, spec.height
+                )
+            } else {
+                format!("Isn't {}×{}", spec.width, spec.height)
+            });
+        }
+        let gaps = |rows: &[Vec<u8>]| -> Vec<bool> {
+            rows.iter().flatten().map(|&value| value == GAP).collect()
+        };
+        if gaps(rows) != gaps(&spec.rows(&spec.permutation(rows))) {
+            return Err("Doesn't have the tile's shape".to_string());
+        }
+        check_colours(
+            rows,
+            self.n_colours,
+            spec.orientation.states(),
+            spec.max_repeats,
+        )?;
+        let states = spec.orientation.states();
+        let shape = spec.cells();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &value) in row.iter().enumerate() {
+                let allowed = shape.allowed(x, y % spec.height as usize);
+                if value != GAP && !allowed.contains((value as u64 / states) as u8) {
+                    return Err(format!("Has a colour where the tile only allows {allowed}"));
                 }
-                entry.push(row);
             }
-            rv.push(entry)
         }
-        let name = format!(
-            "{}x{} {} col {:?}.json",
-            width, height, n_colours, allowed_xforms
+        Ok(spec.rows(&spec.canonicalize(&spec.permutation(rows))))
+    }
+
+    fn distinct(&self) -> Option<Vec<Vec<Vec<u8>>>> {
+        let spec = self.spec();
+        (self.perm_count <= 10000).then(|| {
+            self.permutations
+                .iter()
+                .map(|perm| spec.rows(perm))
+                .collect()
+        })
+    }
+
+    fn n_distinct(&self) -> u64 {
+        self.perm_count
+    }
+
+    fn marks(&self) -> Marks {
+        let spec = self.spec();
+        Box::new(move |rows, look| design_marks(&spec, rows, look))
+    }
+}
+
+impl Exportable for Tile {
+    fn file_stem(&self) -> String {
+        let stem = format!(
+            "{}x{} {} col {:?}",
+            self.width, self.height, self.n_colours, self.allowed_xforms
         );
-        let bytes = serde_json::to_vec(&rv).unwrap();
-        (name, bytes)
+        let stem = match self.orientation {
+            Orientation::Symmetric => stem,
+            orientation => format!("{stem} {orientation:?}"),
+        };
+        let stem = if self.two_sided {
+            format!("{stem} two-sided")
+        } else {
+            stem
+        };
+        if self.shape().is_some() {
+            format!("{stem} shaped")
+        } else {
+            stem
+        }
+    }
+
+    fn breakdown(&self) -> Breakdown {
+        tile::breakdown(&self.spec()).unwrap_or_default()
+    }
+
+    fn export_json(&self, palettes: &PaletteChooser) -> Vec<u8> {
+        let spec = self.spec();
+        let designs = self
+            .permutations
+            .iter()
+            .map(|perm| (spec.rows(perm), spec.stabilizer(perm)));
+        let settings = Shared {
+            spec: SharedSpec::Tile(spec),
+            palette: palettes.key(),
+        };
+        Export::new(settings, palettes, self.breakdown(), designs).to_json()
+    }
+
+    fn export_csv(&self) -> String {
+        let spec = self.spec();
+        let designs = self
+            .permutations
+            .iter()
+            .map(|perm| (spec.rows(perm), spec.stabilizer(perm)));
+        designs_csv(
+            self.n_colours,
+            spec.orientation.states(),
+            self.breakdown().group_order(),
+            designs,
+        )
+    }
+
+    fn sprites(&self, look: &Look<'_>) -> Vec<Vec<Mark>> {
+        let spec = self.spec();
+        self.permutations
+            .iter()
+            .map(|perm| design_marks(&spec, &spec.rows(perm), look))
+            .collect()
+    }
+}
+
+/// Shows the `width`×`height` corner of the 8×8 shape grid as cells to click in and out of the
+/// tile, or right-click to choose which of the `n_colours` they may be; returns whether any
+/// changed. The last cell can't be taken out.
+fn shape_editor(
+    ui: &mut egui::Ui,
+    width: u64,
+    height: u64,
+    n_colours: u64,
+    shape_cells: &mut u64,
+    allowed_cells: &mut Vec<ColourSet>,
+) -> bool {
+    let mut changed = false;
+    allowed_cells.resize(64, ColourSet::ANY);
+    let visible: u64 = (0..height)
+        .flat_map(|y| (0..width).map(move |x| 1 << (y * 8 + x)))
+        .sum();
+    egui::Grid::new("shape_editor")
+        .spacing((2., 2.))
+        .show(ui, |ui| {
+            for y in 0..height {
+                for x in 0..width {
+                    let bit = 1 << (y * 8 + x);
+                    let allowed = &mut allowed_cells[(y * 8 + x) as usize];
+                    let (rect, response) =
+                        ui.allocate_exact_size((16., 16.).into(), egui::Sense::click());
+                    let inside = *shape_cells & bit != 0;
+                    if response.clicked() && (!inside || *shape_cells & visible != bit) {
+                        *shape_cells ^= bit;
+                        *allowed = ColourSet::ANY;
+                        changed = true;
+                    }
+                    let fill = if *shape_cells & bit != 0 {
+                        ui.visuals().selection.bg_fill
+                    } else {
+                        ui.visuals().widgets.inactive.bg_fill
+                    };
+                    ui.painter().rect_filled(rect, 2., fill);
+                    if *shape_cells & bit != 0 {
+                        paint_allowed(ui, rect, n_colours, *allowed);
+                        changed |= colours_menu(response, n_colours, allowed);
+                    }
+                }
+                ui.end_row();
+            }
+        });
+    changed
+}
+
+/// Draws a tile's rows: both faces side by side if it's two-sided.
+fn design_marks(spec: &TileSpec, rows: &[Vec<u8>], look: &Look<'_>) -> Vec<Mark> {
+    if spec.two_sided {
+        two_sided_marks(rows, spec.orientation, look)
+    } else {
+        tile_marks(rows, spec.orientation, look)
     }
 }
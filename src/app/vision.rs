@@ -0,0 +1,169 @@
+//! How colours look with colour vision deficiency, using the full-severity matrices of Machado,
+//! Oliveira & Fernandes (2009), so palettes can be checked for colours that can't be told apart.
+use eframe::egui::Color32;
+
+use super::{csv::colour_letter, palettes::Palette};
+
+/// Colours closer than this (CIE76 ΔE) under a simulation count as indistinguishable.
+const CONFUSABLE: f32 = 12.;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Vision {
+    #[default]
+    Normal,
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+impl Vision {
+    pub const ALL: [Vision; 4] = [
+        Vision::Normal,
+        Vision::Protanopia,
+        Vision::Deuteranopia,
+        Vision::Tritanopia,
+    ];
+    pub const DEFICIENT: [Vision; 3] =
+        [Vision::Protanopia, Vision::Deuteranopia, Vision::Tritanopia];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Vision::Normal => "Normal vision",
+            Vision::Protanopia => "Protanopia",
+            Vision::Deuteranopia => "Deuteranopia",
+            Vision::Tritanopia => "Tritanopia",
+        }
+    }
+
+    /// Applies to linear RGB.
+    fn matrix(self) -> Option<[[f32; 3]; 3]> {
+        match self {
+            Vision::Normal => None,
+            Vision::Protanopia => Some([
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ]),
+            Vision::Deuteranopia => Some([
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ]),
+            Vision::Tritanopia => Some([
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ]),
+        }
+    }
+
+    /// How `colour` looks with this kind of vision.
+    pub fn simulate(self, colour: Color32) -> Color32 {
+        let Some(matrix) = self.matrix() else {
+            return colour;
+        };
+        let linear = [colour.r(), colour.g(), colour.b()].map(to_linear);
+        let [r, g, b] = matrix.map(|row| from_linear((0..3).map(|i| row[i] * linear[i]).sum()));
+        Color32::from_rgba_unmultiplied(r, g, b, colour.a())
+    }
+}
+
+fn to_linear(channel: u8) -> f32 {
+    let c = channel as f32 / 255.;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn from_linear(c: f32) -> u8 {
+    let c = c.clamp(0., 1.);
+    let c = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    };
+    (c * 255.).round() as u8
+}
+
+/// CIELAB, with a D65 white point.
+fn lab(colour: Color32) -> [f32; 3] {
+    let [r, g, b] = [colour.r(), colour.g(), colour.b()].map(to_linear);
+    let xyz = [
+        (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047,
+        0.2126 * r + 0.7152 * g + 0.0722 * b,
+        (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883,
+    ];
+    let [x, y, z] = xyz.map(|t| {
+        if t > 216. / 24389. {
+            t.cbrt()
+        } else {
+            (24389. / 27. * t + 16.) / 116.
+        }
+    });
+    [116. * y - 16., 500. * (x - y), 200. * (y - z)]
+}
+
+fn difference(a: Color32, b: Color32) -> f32 {
+    let (a, b) = (lab(a), lab(b));
+    (0..3).map(|i| (a[i] - b[i]).powi(2)).sum::<f32>().sqrt()
+}
+
+/// Pairs of colours in `palette` that can be told apart with normal vision, but look the same
+/// with some deficiency, as `(first, second, vision)`.
+pub fn confusable(palette: &Palette) -> Vec<(usize, usize, Vision)> {
+    let mut pairs = vec![];
+    for (i, &a) in palette.iter().enumerate() {
+        for (j, &b) in palette.iter().enumerate().skip(i + 1) {
+            if difference(a, b) < CONFUSABLE {
+                continue;
+            }
+            for vision in Vision::DEFICIENT {
+                if difference(vision.simulate(a), vision.simulate(b)) < CONFUSABLE {
+                    pairs.push((i, j, vision));
+                }
+            }
+        }
+    }
+    pairs
+}
+
+/// One line for each pair from `confusable`, e.g. "B and C look alike with Deuteranopia".
+pub fn describe(pairs: &[(usize, usize, Vision)]) -> String {
+    pairs
+        .iter()
+        .map(|&(a, b, vision)| {
+            format!(
+                "{} and {} look alike with {}",
+                colour_letter(a as u8),
+                colour_letter(b as u8),
+                vision.label()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_vision() {
+        let red = Color32::from_rgb(200, 40, 40);
+        let green = Color32::from_rgb(90, 120, 40);
+        for colour in [red, green, Color32::WHITE, Color32::BLACK] {
+            assert_eq!(Vision::Normal.simulate(colour), colour);
+        }
+        // Greys stay grey
+        let grey = Vision::Deuteranopia.simulate(Color32::from_gray(128));
+        assert!(grey.r().abs_diff(128) <= 1 && grey.b().abs_diff(128) <= 1);
+
+        let pairs = confusable(&[red, green, Color32::from_rgb(30, 60, 220)]);
+        assert!(pairs.contains(&(0, 1, Vision::Deuteranopia)));
+        assert!(pairs.iter().all(|&(a, b, _)| (a, b) == (0, 1)));
+        assert!(describe(&pairs).contains("A and B look alike with Deuteranopia"));
+        // Identical colours are a different problem
+        assert!(confusable(&[red, red]).is_empty());
+    }
+}
@@ -0,0 +1,224 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use once_cell::sync::Lazy;
+type DrawCache = HashMap<(u64, u64, u64), u64>;
+static DRAW_CACHE: Lazy<Mutex<DrawCache>> = Lazy::new(Default::default);
+
+pub fn simple_count(draws: u64, n_colours: u64, each_count: u64) -> u64 {
+    *DRAW_CACHE
+        .lock()
+        .unwrap()
+        .entry((draws, n_colours, each_count))
+        .or_insert_with(|| count(draws, &mut vec![each_count as u8; n_colours as usize]))
+}
+
+fn count(draws: u64, left: &mut [u8]) -> u64 {
+    if draws == 0 {
+        return 1;
+    }
+    let mut total = 0;
+    for i in 0..left.len() {
+        if left[i] > 0 {
+            left[i] -= 1;
+            total += count(draws - 1, left);
+            left[i] += 1;
+        }
+    }
+    total
+}
+
+pub fn count2(draws: u64, left_counts: &mut [u64]) -> u64 {
+    if draws == 0 {
+        return 1;
+    }
+    let mut total = 0;
+    for i in 1..left_counts.len() {
+        if left_counts[i] > 0 {
+            let count = left_counts[i];
+            left_counts[i] -= 1;
+            left_counts[i - 1] += 1;
+            total += count * count2(draws - 1, left_counts);
+            left_counts[i - 1] -= 1;
+            left_counts[i] += 1;
+        }
+    }
+    total
+}
+
+pub fn count3(draws: &[u8], left_counts: &mut [u64]) -> u64 {
+    if let Some((next_draw, draws)) = draws.split_first() {
+        let next_draw = *next_draw as usize;
+        let mut total = 0;
+        for i in next_draw..left_counts.len() {
+            if left_counts[i] > 0 {
+                let count = left_counts[i];
+                left_counts[i] -= 1;
+                left_counts[i - next_draw] += 1;
+                total += count * count3(draws, left_counts);
+                left_counts[i - next_draw] -= 1;
+                left_counts[i] += 1;
+            }
+        }
+        total
+    } else {
+        1
+    }
+}
+
+/// How many ways there are to give each of `draws` one of the colours it allows, as its length
+/// and a bit for each of the first `n_colours` it may take, with no colour used more than
+/// `max_repeats` times in all.
+///
+/// Draws of the same length that allow the same colours are interchangeable, so this works
+/// through the colours in turn, choosing how many of each kind of draw left each one takes.
+pub fn count_allowed(draws: &[(u8, u32)], n_colours: u64, max_repeats: u64) -> u64 {
+    let mut kinds: Vec<(u8, u32)> = vec![];
+    let mut left = vec![];
+    for &draw in draws {
+        match kinds.iter().position(|&kind| kind == draw) {
+            Some(kind) => left[kind] += 1,
+            None => {
+                kinds.push(draw);
+                left.push(1);
+            }
+        }
+    }
+    let mut draw = AllowedDraw {
+        kinds,
+        n_colours,
+        max_repeats,
+        cache: HashMap::new(),
+    };
+    draw.count(0, &mut left)
+}
+
+/// The state of a `count_allowed`: the kinds of draw, and how many ways there are to finish
+/// from each colour with so many of each kind left.
+struct AllowedDraw {
+    kinds: Vec<(u8, u32)>,
+    n_colours: u64,
+    max_repeats: u64,
+    cache: HashMap<(u64, Vec<u64>), u64>,
+}
+
+impl AllowedDraw {
+    /// The ways to colour the draws `left` of each kind with `colour` and the colours after it.
+    fn count(&mut self, colour: u64, left: &mut Vec<u64>) -> u64 {
+        if left.iter().all(|&n| n == 0) {
+            return 1;
+        }
+        // Give up once some draw left can't take any of the colours left
+        let later = !((1 << colour) - 1);
+        if colour == self.n_colours
+            || self
+                .kinds
+                .iter()
+                .zip(left.iter())
+                .any(|(&(_, allowed), &n)| n > 0 && allowed & later == 0)
+        {
+            return 0;
+        }
+        if let Some(&total) = self.cache.get(&(colour, left.clone())) {
+            return total;
+        }
+        let total = self.share_out(colour, 0, self.max_repeats, left);
+        self.cache.insert((colour, left.clone()), total);
+        total
+    }
+
+    /// The ways to give `colour`, with `budget` uses of it left, to some of the draws of `kind`
+    /// and the kinds after it, then carry on with the next colour.
+    fn share_out(&mut self, colour: u64, kind: usize, budget: u64, left: &mut Vec<u64>) -> u64 {
+        let (length, allowed) = match self.kinds.get(kind) {
+            Some(&kind) => kind,
+            None => return self.count(colour + 1, left),
+        };
+        if allowed & 1 << colour == 0 {
+            return self.share_out(colour, kind + 1, budget, left);
+        }
+        let available = left[kind];
+        let mut total = 0;
+        // Which `taken` of the `available` draws of this kind get the colour
+        let mut ways = 1;
+        for taken in 0..=available.min(budget / length as u64) {
+            left[kind] = available - taken;
+            total += ways * self.share_out(colour, kind + 1, budget - taken * length as u64, left);
+            ways = ways * (available - taken) / (taken + 1);
+        }
+        left[kind] = available;
+        total
+    }
+}
+
+// fn count2(draws: u64, n_colours: u64, each_count: u64) {
+//     let mut total = 1;
+//     for i in 0..n_colours {
+
+//     }
+// }
+
+// fn fact(n: u64) -> u64 {
+//     (1..=n).product()
+// }
+
+#[cfg(test)]
+mod test {
+    use super::{count, count2, count3, count_allowed};
+    fn choose(n: u64, k: u64) -> u64 {
+        (n - k + 1..=n).product()
+    }
+    #[test]
+    fn test() {
+        assert_eq!(1, count(1, &mut [1, 0, 0]));
+        assert_eq!(2, count(1, &mut [1, 0, 1]));
+        assert_eq!(3, count(1, &mut [1, 1, 1]));
+        assert_eq!(1, count(1, &mut [2, 0, 0]));
+        assert_eq!(1, count(2, &mut [2, 0, 0]));
+        assert_eq!(0, count(3, &mut [2, 0, 0]));
+        assert_eq!(2, count(1, &mut [2, 1, 0]));
+        assert_eq!(3, count(2, &mut [2, 1, 0]));
+        assert_eq!(3, count(3, &mut [2, 1, 0]));
+        assert_eq!(90, count(5, &mut [2, 2, 2]));
+        assert_eq!(choose(4, 2), count(2, &mut [1, 1, 1, 1]));
+        assert_eq!(24, count(3, &mut [1, 1, 1, 1]));
+    }
+    #[test]
+    fn test2() {
+        assert_eq!(1, count2(1, &mut [0, 1, 0]));
+        assert_eq!(2, count2(1, &mut [0, 2, 0]));
+        assert_eq!(3, count2(1, &mut [0, 3, 0]));
+        assert_eq!(1, count2(1, &mut [0, 0, 1]));
+        assert_eq!(1, count2(2, &mut [0, 0, 1]));
+        assert_eq!(0, count2(3, &mut [0, 0, 1]));
+        assert_eq!(2, count2(1, &mut [0, 1, 1]));
+        assert_eq!(3, count2(2, &mut [0, 1, 1]));
+        assert_eq!(3, count2(3, &mut [0, 1, 1]));
+        assert_eq!(90, count2(5, &mut [0, 0, 3]));
+        assert_eq!(2, count2(1, &mut [0, 2]));
+    }
+    #[test]
+    fn test_allowed() {
+        // With every colour allowed everywhere it's the same as drawing from full bags
+        for draws in [&[1, 1, 1][..], &[2, 1, 1], &[3, 2], &[2, 2, 2, 1]] {
+            for max_repeats in 1..5 {
+                let mut counts = vec![0; max_repeats + 1];
+                counts[max_repeats] = 3;
+                let allowed: Vec<_> = draws.iter().map(|&length| (length, 0b111)).collect();
+                assert_eq!(
+                    count3(draws, &mut counts),
+                    count_allowed(&allowed, 3, max_repeats as u64)
+                );
+            }
+        }
+        // Two corners that may only be A or B, and a middle that may be anything
+        let draws = [(1, 0b011), (1, 0b011), (1, 0b111)];
+        assert_eq!(count_allowed(&draws, 3, 3), 12);
+        // With one of each colour, the corners share out A and B, leaving C for the middle
+        assert_eq!(count_allowed(&draws, 3, 1), 2);
+        // A cycle of two fixed to A uses up both of A's repeats
+        let draws = [(2, 0b01), (1, 0b11)];
+        assert_eq!(count_allowed(&draws, 2, 2), 1);
+        assert_eq!(count_allowed(&draws, 2, 1), 0);
+        assert_eq!(count_allowed(&[], 2, 1), 1);
+    }
+}
@@ -0,0 +1,318 @@
+#![forbid(unsafe_code)]
+#![cfg_attr(not(debug_assertions), deny(warnings))] // Forbid warnings in release builds
+#![warn(clippy::all, rust_2018_idioms)]
+
+use std::{fs::File, io::Write};
+
+use burnside::{ring, tile, Breakdown, SpecError};
+
+mod serve;
+
+const USAGE: &str = "\
+Usage: burnside-cli tile --width W --height H --colours N [OPTIONS]
+       burnside-cli ring --beads B --colours N [OPTIONS]
+       burnside-cli serve [--address HOST:PORT]
+
+Counts (and optionally enumerates) the distinct tiles or rings, or serves
+the same over HTTP: POST a JSON spec to /tile or /ring (default address
+127.0.0.1:8090).
+
+Options:
+  --max-repeats N       Limit how many times any one colour may appear
+  --transforms FAMILY   none, rotate (default) or rotate-flip
+  --orientation KIND    For tiles, how markings turn with the tile: symmetric
+                        (default), line, arrow or chiral
+  --two-sided           For tiles, give each tile a back, which turning it
+                        over swaps with the front
+  --shape ROWS          For tiles, which cells of the grid make up the tile, as
+                        rows of x (cell) and . (gap), e.g. .x./xxx/.x.; a
+                        capital letter fixes the cell to that colour, e.g.
+                        xxx/xAx/xxx, and letters in brackets limit it to
+                        those, e.g. [AB]x[AB]/xxx/[AB]x[AB]; the width and
+                        height come from the shape
+  --facing KIND         For rings, what turning a ring over does to its beads:
+                        symmetric (default), two-faced or pointing
+  --allowed BEADS       For rings, the colours each bead may be in turn: x for
+                        any, a capital letter for just that colour, or
+                        letters in brackets for those, e.g. A[AB]xx[AB]x;
+                        --beads defaults to how many there are
+  --breakdown           Also print the per-transform Burnside table
+  --format FORMAT       Write the enumerated designs as json or csv
+  --output FILE         Write the designs to FILE instead of stdout
+  -h, --help            Print this message";
+
+/// Refuse to enumerate more designs than this; the count is still available.
+const MAX_DESIGNS: u64 = 1_000_000;
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8090";
+
+enum Spec {
+    Tile(tile::TileSpec),
+    Ring(ring::RingSpec),
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Json,
+    Csv,
+}
+
+struct Args {
+    spec: Spec,
+    breakdown: bool,
+    format: Option<Format>,
+    output: Option<String>,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{USAGE}");
+        return;
+    }
+    if args.first().map(String::as_str) == Some("serve") {
+        let address = match args[1..] {
+            [] => DEFAULT_ADDRESS,
+            [ref flag, ref address] if flag == "--address" => address,
+            _ => {
+                eprintln!("burnside-cli: serve only takes --address\n\n{USAGE}");
+                std::process::exit(2);
+            }
+        };
+        if let Err(message) = serve::serve(address) {
+            eprintln!("burnside-cli: {message}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    let args = match parse_args(&args) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("burnside-cli: {message}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    if let Err(message) = run(&args) {
+        eprintln!("burnside-cli: {message}");
+        std::process::exit(1);
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Args, String> {
+    let (mode, mut rest) = args.split_first().ok_or("Missing mode")?;
+    let mut width = None;
+    let mut height = None;
+    let mut beads = None;
+    let mut colours = None;
+    let mut max_repeats = None;
+    let mut transforms = "rotate".to_string();
+    let mut orientation = None;
+    let mut facing = None;
+    let mut two_sided = false;
+    let mut shape: Option<tile::Shape> = None;
+    let mut allowed: Option<ring::BeadColours> = None;
+    let mut breakdown = false;
+    let mut format = None;
+    let mut output = None;
+    while let Some((flag, tail)) = rest.split_first() {
+        rest = tail;
+        if flag == "--breakdown" {
+            breakdown = true;
+            continue;
+        }
+        if flag == "--two-sided" {
+            two_sided = true;
+            continue;
+        }
+        let (value, tail) = rest
+            .split_first()
+            .ok_or_else(|| format!("Missing value for {flag}"))?;
+        rest = tail;
+        match flag.as_str() {
+            "--width" => width = Some(parse_number(flag, value)?),
+            "--height" => height = Some(parse_number(flag, value)?),
+            "--beads" => beads = Some(parse_number(flag, value)?),
+            "--colours" | "--colors" => colours = Some(parse_number(flag, value)?),
+            "--max-repeats" => max_repeats = Some(parse_number(flag, value)?),
+            "--transforms" => transforms = value.clone(),
+            "--orientation" => orientation = Some(value.parse()?),
+            "--facing" => facing = Some(value.parse()?),
+            "--shape" => shape = Some(value.parse()?),
+            "--allowed" => allowed = Some(value.parse()?),
+            "--format" => {
+                format = Some(match value.as_str() {
+                    "json" => Format::Json,
+                    "csv" => Format::Csv,
+                    _ => return Err(format!("Unknown format {value:?}")),
+                })
+            }
+            "--output" => output = Some(value.clone()),
+            _ => return Err(format!("Unknown option {flag:?}")),
+        }
+    }
+
+    let n_colours = colours.ok_or("--colours is required")?;
+    let spec = match mode.as_str() {
+        "tile" => {
+            if facing.is_some() || allowed.is_some() {
+                return Err("--facing and --allowed are only for rings".to_string());
+            }
+            let spec = tile::TileSpec {
+                width: shape
+                    .map(|shape| shape.width())
+                    .or(width)
+                    .ok_or("--width is required for tiles")?,
+                height: shape
+                    .map(|shape| shape.height())
+                    .or(height)
+                    .ok_or("--height is required for tiles")?,
+                n_colours,
+                max_repeats,
+                allowed_xforms: transforms.parse()?,
+                orientation: orientation.unwrap_or_default(),
+                two_sided,
+                shape,
+            };
+            spec.validate().map_err(|e| e.to_string())?;
+            Spec::Tile(spec)
+        }
+        "ring" => {
+            if orientation.is_some() {
+                return Err("--orientation is only for tiles".to_string());
+            }
+            if two_sided || shape.is_some() {
+                return Err("--two-sided and --shape are only for tiles".to_string());
+            }
+            let spec = ring::RingSpec {
+                n_beads: beads
+                    .or_else(|| allowed.map(|allowed| allowed.len()))
+                    .ok_or("--beads is required for rings")?,
+                n_colours,
+                max_repeats,
+                allowed_xforms: transforms.parse()?,
+                facing: facing.unwrap_or_default(),
+                allowed,
+            };
+            spec.validate().map_err(|e| e.to_string())?;
+            Spec::Ring(spec)
+        }
+        _ => return Err(format!("Unknown mode {mode:?}")),
+    };
+    if output.is_some() && format.is_none() {
+        return Err("--output needs a --format".to_string());
+    }
+
+    Ok(Args {
+        spec,
+        breakdown,
+        format,
+        output,
+    })
+}
+
+fn parse_number(flag: &str, value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{flag} expects a number, not {value:?}"))
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let breakdown = match &args.spec {
+        Spec::Tile(spec) => tile::breakdown(spec),
+        Spec::Ring(spec) => ring::breakdown(spec),
+    }
+    .map_err(|e| e.to_string())?;
+    let count = breakdown.count();
+
+    // Keep stdout clean for the designs if that's where they're going
+    let mut report: Box<dyn Write> = if args.format.is_some() && args.output.is_none() {
+        Box::new(std::io::stderr())
+    } else {
+        Box::new(std::io::stdout())
+    };
+    if args.breakdown {
+        write_breakdown(&mut report, &breakdown).map_err(|e| e.to_string())?;
+    }
+    writeln!(report, "{count}").map_err(|e| e.to_string())?;
+
+    if let Some(format) = args.format {
+        if count > MAX_DESIGNS {
+            return Err(format!(
+                "Too many designs to enumerate ({count} > {MAX_DESIGNS})"
+            ));
+        }
+        let bytes = match format {
+            Format::Json => designs_json(&args.spec),
+            Format::Csv => designs_csv(&args.spec),
+        }
+        .map_err(|e| e.to_string())?;
+        match &args.output {
+            Some(path) => File::create(path).and_then(|mut file| file.write_all(&bytes)),
+            None => std::io::stdout().write_all(&bytes),
+        }
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn write_breakdown(out: &mut dyn Write, breakdown: &Breakdown) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "{:<20} {:>8} {:>20} {:>20}",
+        "Transform", "Elements", "Fixed each", "Fixed total"
+    )?;
+    for term in &breakdown.terms {
+        writeln!(
+            out,
+            "{:<20} {:>8} {:>20} {:>20}",
+            term.transform,
+            term.elements,
+            term.fixed,
+            term.total_fixed()
+        )?;
+    }
+    writeln!(
+        out,
+        "{:<20} {:>8} {:>20} {:>20}",
+        "Total",
+        breakdown.group_order(),
+        "",
+        breakdown.total_fixed()
+    )
+}
+
+fn designs_json(spec: &Spec) -> Result<Vec<u8>, SpecError> {
+    Ok(match spec {
+        Spec::Tile(spec) => serde_json::to_vec(&tile::enumerate(spec)?.collect::<Vec<_>>()),
+        Spec::Ring(spec) => serde_json::to_vec(&ring::enumerate(spec)?.collect::<Vec<_>>()),
+    }
+    .unwrap())
+}
+
+fn designs_csv(spec: &Spec) -> Result<Vec<u8>, SpecError> {
+    let designs: Vec<String> = match spec {
+        Spec::Tile(spec) => tile::enumerate(spec)?
+            .map(|rows| {
+                let rows: Vec<String> = rows.iter().map(|row| digits(row)).collect();
+                rows.join("/")
+            })
+            .collect(),
+        Spec::Ring(spec) => ring::enumerate(spec)?.map(|beads| digits(&beads)).collect(),
+    };
+    let mut csv = "index,design\n".to_string();
+    for (index, design) in designs.iter().enumerate() {
+        csv.push_str(&format!("{index},{design}\n"));
+    }
+    Ok(csv.into_bytes())
+}
+
+/// One character per colour, or per colour and orientation: 0–9, then a–z past ten. Gaps in a
+/// tile's shape are dots.
+fn digits(colours: &[u8]) -> String {
+    colours
+        .iter()
+        .map(|&colour| match colour {
+            tile::GAP => '.',
+            colour => char::from_digit(colour as u32, 36).unwrap(),
+        })
+        .collect()
+}
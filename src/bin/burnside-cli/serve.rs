@@ -0,0 +1,276 @@
+//! A small local HTTP service, so tools in other languages can share our counting code.
+//!
+//! `POST /tile` or `POST /ring` with a spec such as
+//! `{"width": 3, "height": 3, "colours": 2, "transforms": "rotate-flip"}`, optionally with
+//! `"pageSize"` (and `"page"`, counting from 0) to also get a page of designs back.
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use burnside::{ring, tile, Breakdown, SpecError};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+use crate::MAX_DESIGNS;
+
+/// Requests are a few hundred bytes of JSON; anything much bigger is a mistake.
+const MAX_BODY: usize = 64 * 1024;
+/// The request line and headers together, which browsers keep to a few kilobytes.
+const MAX_HEADERS: u64 = 16 * 1024;
+const MAX_PAGE_SIZE: u64 = 10_000;
+/// How many requests are handled at once; any page can reach the service, so more are turned
+/// away rather than each getting a thread.
+const MAX_CONNECTIONS: usize = 16;
+/// How long a slow client may take to send its request or read the response.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Request<S> {
+    #[serde(flatten)]
+    spec: S,
+    #[serde(default)]
+    page: u64,
+    page_size: Option<u64>,
+}
+
+pub fn serve(address: &str) -> Result<(), String> {
+    let listener = TcpListener::bind(address).map_err(|e| format!("{address}: {e}"))?;
+    eprintln!("Listening on http://{address}");
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                    eprintln!("burnside-cli: Too many connections; dropping one");
+                    continue;
+                }
+                let connections = Arc::clone(&connections);
+                std::thread::spawn(move || {
+                    if let Err(e) = handle(stream) {
+                        eprintln!("burnside-cli: {e}");
+                    }
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+            Err(e) => eprintln!("burnside-cli: {e}"),
+        }
+    }
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?.take(MAX_HEADERS));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut content_length = 0;
+    let mut complete = request_line.ends_with('\n');
+    while complete {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        // Running out of headers before the blank line means they went past `MAX_HEADERS`
+        complete = header.ends_with('\n');
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    let (status, body) = if !complete {
+        (
+            "431 Request Header Fields Too Large",
+            error("Request headers too large"),
+        )
+    } else if content_length > MAX_BODY {
+        ("413 Payload Too Large", error("Request body too large"))
+    } else {
+        reader.get_mut().set_limit(MAX_BODY as u64);
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+        route(&method, &path, &body)
+    };
+    let rejected = !status.starts_with('2');
+    let body = if body.is_null() {
+        String::new()
+    } else {
+        body.to_string()
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Content-Type\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    if rejected {
+        // Closing with the rest of the request unread resets the connection, which can lose the
+        // response before the client reads it, so finish reading (up to a limit) first
+        stream.shutdown(Shutdown::Write)?;
+        let _ = std::io::copy(&mut (&stream).take(MAX_BODY as u64), &mut std::io::sink());
+    }
+    Ok(())
+}
+
+fn route(method: &str, path: &str, body: &[u8]) -> (&'static str, Value) {
+    let result = match (method, path) {
+        ("OPTIONS", _) => return ("204 No Content", Value::Null),
+        ("POST", "/tile") => parse(body).and_then(|request: Request<tile::TileSpec>| {
+            let breakdown = tile::breakdown(&request.spec).map_err(|e| e.to_string())?;
+            let designs = page(&request, &breakdown, |spec, start, len| {
+                let designs = tile::enumerate(spec)?.skip(start).take(len);
+                Ok(json!(designs.collect::<Vec<_>>()))
+            })?;
+            Ok(response(&breakdown, designs))
+        }),
+        ("POST", "/ring") => parse(body).and_then(|request: Request<ring::RingSpec>| {
+            let breakdown = ring::breakdown(&request.spec).map_err(|e| e.to_string())?;
+            let designs = page(&request, &breakdown, |spec, start, len| {
+                let designs = ring::enumerate(spec)?.skip(start).take(len);
+                Ok(json!(designs.collect::<Vec<_>>()))
+            })?;
+            Ok(response(&breakdown, designs))
+        }),
+        (_, "/tile" | "/ring") => return ("405 Method Not Allowed", error("Use POST")),
+        _ => return ("404 Not Found", error("Try POST /tile or POST /ring")),
+    };
+    match result {
+        Ok(value) => ("200 OK", value),
+        Err(message) => ("400 Bad Request", error(&message)),
+    }
+}
+
+fn parse<T: DeserializeOwned>(body: &[u8]) -> Result<T, String> {
+    serde_json::from_slice(body).map_err(|e| e.to_string())
+}
+
+/// The requested page of designs, if any were asked for. `enumerate` gets the spec, how many
+/// designs to skip and how many to take after them.
+fn page<S>(
+    request: &Request<S>,
+    breakdown: &Breakdown,
+    enumerate: impl FnOnce(&S, usize, usize) -> Result<Value, SpecError>,
+) -> Result<Option<Value>, String> {
+    let page_size = match request.page_size {
+        Some(page_size) => page_size.min(MAX_PAGE_SIZE),
+        None => return Ok(None),
+    };
+    let count = breakdown.count();
+    if count > MAX_DESIGNS {
+        return Err(format!(
+            "Too many designs to enumerate ({count} > {MAX_DESIGNS})"
+        ));
+    }
+    let start = request.page.saturating_mul(page_size).min(count) as usize;
+    let designs = enumerate(&request.spec, start, page_size as usize).map_err(|e| e.to_string())?;
+    Ok(Some(json!({
+        "page": request.page,
+        "pageSize": page_size,
+        "designs": designs,
+    })))
+}
+
+fn response(breakdown: &Breakdown, designs: Option<Value>) -> Value {
+    let mut value = json!({
+        "count": breakdown.count(),
+        "groupOrder": breakdown.group_order(),
+        "breakdown": breakdown,
+    });
+    if let Some(Value::Object(page)) = designs {
+        value.as_object_mut().unwrap().extend(page);
+    }
+    value
+}
+
+fn error(message: &str) -> Value {
+    json!({ "error": message })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_route() {
+        let body = br#"{"width": 2, "height": 2, "colours": 3, "page": 1, "pageSize": 10}"#;
+        let (status, value) = route("POST", "/tile", body);
+        assert_eq!(status, "200 OK");
+        assert_eq!(value["count"], 24);
+        assert_eq!(value["groupOrder"], 4);
+        assert_eq!(value["breakdown"][0]["fixed"], 81);
+        assert_eq!(value["page"], 1);
+        assert_eq!(value["designs"].as_array().unwrap().len(), 10);
+        let spec: tile::TileSpec = serde_json::from_slice(body).unwrap();
+        assert_eq!(
+            value["designs"][0],
+            json!(tile::enumerate(&spec).unwrap().nth(10))
+        );
+
+        let (status, value) = route("POST", "/ring", br#"{"beads": 6, "colours": 2}"#);
+        assert_eq!(status, "200 OK");
+        assert_eq!(value["count"], 14);
+        assert!(value.get("designs").is_none());
+
+        let (status, _) = route("POST", "/ring", br#"{"beads": 60, "colours": 2}"#);
+        assert_eq!(status, "400 Bad Request");
+        let (status, _) = route("GET", "/tile", b"");
+        assert_eq!(status, "405 Method Not Allowed");
+    }
+
+    /// Sends `request` to `handle` over a real socket and returns the response.
+    fn exchange(request: &[u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let handler = std::thread::spawn(move || handle(server));
+        // The server may answer and hang up before reading everything
+        let _ = client.write_all(request);
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        drop(client);
+        handler.join().unwrap().unwrap();
+        response
+    }
+
+    #[test]
+    fn test_limits() {
+        let body = r#"{"beads": 6, "colours": 2}"#;
+        let request = format!(
+            "POST /ring HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        let response = exchange(request.as_bytes());
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.contains(r#""count":14"#));
+
+        let huge = format!(
+            "POST /ring HTTP/1.1\r\nX-Padding: {}\r\n\r\n",
+            "a".repeat(MAX_HEADERS as usize)
+        );
+        let response = exchange(huge.as_bytes());
+        assert!(
+            response.starts_with("HTTP/1.1 431 Request Header Fields Too Large"),
+            "{}",
+            response
+        );
+    }
+}
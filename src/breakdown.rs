@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+/// One line of a Burnside table: a kind of transform, how many elements of the symmetry group
+/// are of that kind, and how many colourings each of them leaves unchanged.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Term {
+    /// A human-readable name for the transform, e.g. `Rotate 180°`.
+    pub transform: String,
+    /// How many elements of the group this term stands for.
+    pub elements: u64,
+    /// How many colourings each of those elements leaves unchanged.
+    pub fixed: u64,
+}
+
+impl Term {
+    pub fn new(transform: impl Into<String>, elements: u64, fixed: u64) -> Self {
+        Term {
+            transform: transform.into(),
+            elements,
+            fixed,
+        }
+    }
+
+    /// The colourings left unchanged, summed over every element of this term.
+    pub fn total_fixed(&self) -> u64 {
+        self.elements * self.fixed
+    }
+}
+
+/// The per-transform fixed-point counts that Burnside's lemma averages over.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Breakdown {
+    pub terms: Vec<Term>,
+}
+
+impl Breakdown {
+    pub fn push(&mut self, term: Term) {
+        self.terms.push(term);
+    }
+
+    /// The size of the symmetry group.
+    pub fn group_order(&self) -> u64 {
+        self.terms.iter().map(|term| term.elements).sum()
+    }
+
+    /// The colourings left unchanged, summed over the whole group.
+    pub fn total_fixed(&self) -> u64 {
+        self.terms.iter().map(Term::total_fixed).sum()
+    }
+
+    /// The number of distinct designs: the average number of colourings fixed per transform.
+    pub fn count(&self) -> u64 {
+        self.total_fixed()
+            .checked_div(self.group_order())
+            .unwrap_or(0)
+    }
+}
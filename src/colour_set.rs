@@ -0,0 +1,171 @@
+use std::{convert::TryFrom, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::tile::MAX_COLOURS;
+
+/// The colours a cell or bead may take, like a corner that may only be red or blue.
+///
+/// These serialise as `x` for any colour, a capital letter for just that colour (`A` for the
+/// first), or letters in brackets for a choice of them, like `[AC]`. [`FromStr`] reads the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ColourSet(u32);
+
+impl ColourSet {
+    /// Every colour there is.
+    pub const ANY: ColourSet = ColourSet((1 << MAX_COLOURS) - 1);
+
+    /// Just `colour`.
+    pub fn only(colour: u8) -> Self {
+        ColourSet(1 << colour)
+    }
+
+    pub fn from_colours(colours: impl IntoIterator<Item = u8>) -> Self {
+        ColourSet(
+            colours
+                .into_iter()
+                .fold(0, |bits, colour| bits | 1 << colour),
+        )
+    }
+
+    pub fn contains(self, colour: u8) -> bool {
+        self.0 & 1u32.checked_shl(colour as u32).unwrap_or(0) != 0
+    }
+
+    pub fn is_any(self) -> bool {
+        self == Self::ANY
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The colours in both sets.
+    pub fn intersection(self, other: Self) -> Self {
+        ColourSet(self.0 & other.0)
+    }
+
+    /// The set with `colour` added if it's missing, or taken out if it's there.
+    pub fn toggled(self, colour: u8) -> Self {
+        ColourSet(self.0 ^ 1 << colour)
+    }
+
+    /// The one colour in the set, if there's only one.
+    pub fn single(self) -> Option<u8> {
+        (self.0.count_ones() == 1).then(|| self.0.trailing_zeros() as u8)
+    }
+
+    /// The colours in the set, in order.
+    pub fn colours(self) -> impl Iterator<Item = u8> {
+        (0..MAX_COLOURS as u8).filter(move |&colour| self.contains(colour))
+    }
+
+    /// Whether the set is empty or names a colour past the first `n_colours`, which is how a
+    /// spec's sets can go wrong. `ANY` is always fine.
+    pub(crate) fn is_invalid(self, n_colours: u64) -> bool {
+        !self.is_any() && (self.is_empty() || self.0 >> n_colours != 0)
+    }
+
+    /// The set's colours among the first `n_colours`, one bit each.
+    pub(crate) fn bits_below(self, n_colours: u64) -> u32 {
+        self.0 & ((1 << n_colours) - 1)
+    }
+
+    /// Splits `s` into cells of `x`, `.` (a gap, `None`), capital letters and bracketed choices
+    /// of letters, for shapes and rings to build on.
+    pub(crate) fn parse_cells(s: &str) -> Result<Vec<Option<ColourSet>>, String> {
+        let mut cells = vec![];
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            cells.push(match c {
+                'x' => Some(Self::ANY),
+                '.' => None,
+                'A'..='Z' => Some(Self::only(c as u8 - b'A')),
+                '[' => {
+                    let rest = chars.as_str();
+                    let (letters, after) = rest
+                        .split_once(']')
+                        .ok_or_else(|| format!("Unclosed bracket in {s:?}"))?;
+                    if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_uppercase()) {
+                        return Err(format!("Expected letters in brackets in {s:?}"));
+                    }
+                    chars = after.chars();
+                    Some(Self::from_colours(letters.bytes().map(|c| c - b'A')))
+                }
+                c => return Err(format!("Unexpected {c:?} in {s:?}")),
+            });
+        }
+        Ok(cells)
+    }
+}
+
+impl fmt::Display for ColourSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let letter = |colour: u8| (b'A' + colour) as char;
+        if self.is_any() {
+            f.write_str("x")
+        } else if let Some(colour) = self.single() {
+            write!(f, "{}", letter(colour))
+        } else {
+            let letters: String = self.colours().map(letter).collect();
+            write!(f, "[{letters}]")
+        }
+    }
+}
+
+impl FromStr for ColourSet {
+    type Err = String;
+
+    /// Parses `x`, a capital letter or bracketed letters.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Self::parse_cells(s)?[..] {
+            [Some(colours)] => Ok(colours),
+            _ => Err(format!("{s:?} isn't one set of colours")),
+        }
+    }
+}
+
+impl TryFrom<String> for ColourSet {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<ColourSet> for String {
+    fn from(colours: ColourSet) -> Self {
+        colours.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_parse() {
+        for s in ["x", "A", "[AC]", "Z"] {
+            assert_eq!(s.parse::<ColourSet>().unwrap().to_string(), s);
+        }
+        assert_eq!("[B]".parse(), Ok(ColourSet::only(1)));
+        assert_eq!(
+            ColourSet::parse_cells("x.[AB]C"),
+            Ok(vec![
+                Some(ColourSet::ANY),
+                None,
+                Some(ColourSet::from_colours([0, 1])),
+                Some(ColourSet::only(2)),
+            ])
+        );
+        assert!("[]".parse::<ColourSet>().is_err());
+        assert!("[Ab]".parse::<ColourSet>().is_err());
+        assert!("AB".parse::<ColourSet>().is_err());
+        assert!("[AB".parse::<ColourSet>().is_err());
+        let corners = ColourSet::from_colours([0, 2]);
+        assert!(corners.is_invalid(2));
+        assert!(!corners.is_invalid(3));
+        assert!(!ColourSet::ANY.is_invalid(1));
+        assert_eq!(corners.bits_below(2), 1);
+    }
+}
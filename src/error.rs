@@ -0,0 +1,50 @@
+use std::fmt;
+
+/// Why a [`TileSpec`](crate::tile::TileSpec) or [`RingSpec`](crate::ring::RingSpec) can't be
+/// counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecError {
+    /// The design has fewer than `min` or more than `max` cells or beads. `max` is `u64::MAX`
+    /// when there's no upper limit.
+    Size { min: u64, max: u64 },
+    /// The number of colours isn't between 1 and `max`.
+    Colours { max: u64 },
+    /// There are too many colourings to count in a `u64`.
+    Overflow,
+    /// The repeat limit was zero, which no design can meet.
+    NoRepeats,
+    /// A tile's shape isn't the size of the tile, or doesn't reach every edge of it.
+    Shape,
+    /// A cell or bead allows no colours, or a colour past the number of colours, or a ring
+    /// doesn't give the colours for every bead.
+    Allowed,
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpecError::Size { min, max: u64::MAX } => {
+                write!(f, "Designs must have at least {min} positions")
+            }
+            SpecError::Size { min, max } => {
+                write!(f, "Designs must have between {min} and {max} positions")
+            }
+            SpecError::Colours { max } => {
+                write!(f, "Number of colours must be between 1 and {max}")
+            }
+            SpecError::Overflow => write!(f, "Too many colourings to count"),
+            SpecError::NoRepeats => write!(f, "Maximum repeats must be at least 1"),
+            SpecError::Shape => write!(
+                f,
+                "The shape must be the tile's width and height, with a cell against every edge"
+            ),
+            SpecError::Allowed => write!(
+                f,
+                "Each cell or bead must allow some of the colours and no others, and a ring needs \
+                 the colours for every bead"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SpecError {}
@@ -0,0 +1,77 @@
+//! Counting and enumeration for JavaScript callers, for pages that don't embed the egui canvas.
+//!
+//! ```js
+//! burnside.countTiles({ width: 2, height: 2, colours: 3, transforms: "rotate" }); // 24
+//! burnside.enumerateRings({ beads: 4, colours: 2, maxRepeats: 2 });
+//! ```
+//!
+//! Counts above `Number.MAX_SAFE_INTEGER` come back as a `BigInt`.
+use wasm_bindgen::prelude::*;
+
+use crate::{ring, tile};
+
+/// Don't hand back more designs than this in one go; the count is still available.
+const MAX_DESIGNS: u64 = 100_000;
+
+fn tile_spec(spec: JsValue) -> Result<tile::TileSpec, JsError> {
+    let spec: tile::TileSpec = serde_wasm_bindgen::from_value(spec)?;
+    spec.validate()?;
+    Ok(spec)
+}
+
+fn ring_spec(spec: JsValue) -> Result<ring::RingSpec, JsError> {
+    let spec: ring::RingSpec = serde_wasm_bindgen::from_value(spec)?;
+    spec.validate()?;
+    Ok(spec)
+}
+
+fn count_to_js(count: u64) -> JsValue {
+    const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+    if count <= MAX_SAFE_INTEGER {
+        JsValue::from_f64(count as f64)
+    } else {
+        JsValue::from(count)
+    }
+}
+
+fn check_enumerable(count: u64) -> Result<(), JsError> {
+    if count > MAX_DESIGNS {
+        Err(JsError::new(&format!(
+            "Too many designs to enumerate ({count} > {MAX_DESIGNS})"
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// The number of distinct tiles for `{width, height, colours, maxRepeats?, transforms?,
+/// orientation?, twoSided?, shape?}`.
+#[wasm_bindgen(js_name = countTiles)]
+pub fn count_tiles(spec: JsValue) -> Result<JsValue, JsError> {
+    Ok(count_to_js(tile::count(&tile_spec(spec)?)?))
+}
+
+/// Every distinct tile, as an array of rows of colour indices.
+#[wasm_bindgen(js_name = enumerateTiles)]
+pub fn enumerate_tiles(spec: JsValue) -> Result<JsValue, JsError> {
+    let spec = tile_spec(spec)?;
+    check_enumerable(tile::count(&spec)?)?;
+    let designs: Vec<_> = tile::enumerate(&spec)?.collect();
+    Ok(serde_wasm_bindgen::to_value(&designs)?)
+}
+
+/// The number of distinct rings for `{beads, colours, maxRepeats?, transforms?, facing?,
+/// allowed?}`.
+#[wasm_bindgen(js_name = countRings)]
+pub fn count_rings(spec: JsValue) -> Result<JsValue, JsError> {
+    Ok(count_to_js(ring::count(&ring_spec(spec)?)?))
+}
+
+/// Every distinct ring, as an array of colour indices.
+#[wasm_bindgen(js_name = enumerateRings)]
+pub fn enumerate_rings(spec: JsValue) -> Result<JsValue, JsError> {
+    let spec = ring_spec(spec)?;
+    check_enumerable(ring::count(&spec)?)?;
+    let designs: Vec<_> = ring::enumerate(&spec)?.collect();
+    Ok(serde_wasm_bindgen::to_value(&designs)?)
+}
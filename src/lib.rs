@@ -1,5 +1,27 @@
+//! Counting and enumerating distinct designs with Burnside's lemma.
+//!
+//! Given a rectangular game tile or a necklace of beads, N colours to mark each position with, and
+//! the ways the piece may be turned or flipped, the [`tile`] and [`ring`] modules count how many
+//! genuinely different pieces there are, break that count down by transform, and list them.
+//!
+//! The egui front end lives behind the `gui` feature, which is on by default. Turn off default
+//! features to depend on the counting code alone.
 #![cfg_attr(not(debug_assertions), deny(warnings))] // Forbid warnings in release builds
 #![warn(clippy::all, rust_2018_idioms)]
 
+#[cfg(feature = "gui")]
 mod app;
+mod bag_draw;
+mod breakdown;
+mod colour_set;
+mod error;
+#[cfg(target_arch = "wasm32")]
+mod js_api;
+mod parallel;
+pub mod ring;
+pub mod tile;
+#[cfg(feature = "gui")]
 pub use app::BurnsideApp;
+pub use breakdown::{Breakdown, Term};
+pub use colour_set::ColourSet;
+pub use error::SpecError;
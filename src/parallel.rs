@@ -0,0 +1,136 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+
+/// Runs `search` once for every prefix of fixed colours, and merges the canonical sets found.
+///
+/// On native builds the prefixes are handed out to one worker per core; on the web everything
+/// runs on the calling thread with an empty prefix. `search` is responsible for rejecting
+/// prefixes that already break any colour limits.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn search_by_prefix<T, F>(n_colours: u64, n_positions: u64, search: F) -> HashSet<T>
+where
+    T: Eq + Hash + Send,
+    F: Fn(&[u8], &mut HashSet<T>) + Sync,
+{
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    search_on_threads(threads, n_colours, n_positions, search)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn search_on_threads<T, F>(
+    threads: usize,
+    n_colours: u64,
+    n_positions: u64,
+    search: F,
+) -> HashSet<T>
+where
+    T: Eq + Hash + Send,
+    F: Fn(&[u8], &mut HashSet<T>) + Sync,
+{
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    if threads == 1 {
+        let mut seen = HashSet::new();
+        search(&[], &mut seen);
+        return seen;
+    }
+
+    let prefixes = prefixes(n_colours, prefix_len(n_colours, n_positions, threads));
+    let next = AtomicUsize::new(0);
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut seen = HashSet::new();
+                    while let Some(prefix) = prefixes.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        search(prefix, &mut seen);
+                    }
+                    seen
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("Enumeration worker panicked"))
+            .reduce(|mut merged, mut seen| {
+                if seen.len() > merged.len() {
+                    std::mem::swap(&mut merged, &mut seen);
+                }
+                merged.extend(seen);
+                merged
+            })
+            .unwrap_or_default()
+    })
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn search_by_prefix<T, F>(_n_colours: u64, _n_positions: u64, search: F) -> HashSet<T>
+where
+    T: Eq + Hash + Send,
+    F: Fn(&[u8], &mut HashSet<T>) + Sync,
+{
+    let mut seen = HashSet::new();
+    search(&[], &mut seen);
+    seen
+}
+
+/// The number of positions to fix so that every worker has a few prefixes to chew on.
+/// At least one position is always left for the search itself.
+#[cfg(not(target_arch = "wasm32"))]
+fn prefix_len(n_colours: u64, n_positions: u64, threads: usize) -> u64 {
+    let wanted = threads as u64 * 4;
+    let mut len = 0;
+    let mut n_prefixes = 1;
+    while n_prefixes < wanted && len + 1 < n_positions {
+        n_prefixes *= n_colours;
+        len += 1;
+    }
+    len
+}
+
+/// Every colouring of the first `len` positions, in lexicographic order.
+#[cfg(not(target_arch = "wasm32"))]
+fn prefixes(n_colours: u64, len: u64) -> Vec<Vec<u8>> {
+    let mut rv = vec![vec![]];
+    for _ in 0..len {
+        rv = rv
+            .into_iter()
+            .flat_map(|prefix| {
+                (0..n_colours).map(move |colour| {
+                    let mut prefix = prefix.clone();
+                    prefix.push(colour as u8);
+                    prefix
+                })
+            })
+            .collect();
+    }
+    rv
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_prefixes() {
+        assert_eq!(prefixes(3, 0), vec![Vec::<u8>::new()]);
+        assert_eq!(
+            prefixes(2, 2),
+            vec![vec![0, 0], vec![0, 1], vec![1, 0], vec![1, 1]]
+        );
+        assert_eq!(prefix_len(2, 10, 4), 4);
+        assert_eq!(prefix_len(8, 10, 4), 2);
+        assert_eq!(prefix_len(2, 3, 64), 2);
+        assert_eq!(prefix_len(2, 1, 64), 0);
+    }
+
+    #[test]
+    fn test_search_on_threads() {
+        // Collect every 5-digit base-3 string; each prefix must be searched exactly once
+        let all = search_on_threads(4, 3, 5, |prefix, seen: &mut HashSet<Vec<u8>>| {
+            for rest in prefixes(3, 5 - prefix.len() as u64) {
+                assert!(seen.insert([prefix, &rest].concat()));
+            }
+        });
+        assert_eq!(all.len(), 243);
+    }
+}
@@ -0,0 +1,834 @@
+//! Necklaces of beads on a loop of string.
+//!
+//! ```
+//! use burnside::ring::{self, AllowedTransformFamiles, Facing, RingSpec};
+//!
+//! let spec = RingSpec {
+//!     n_beads: 6,
+//!     n_colours: 2,
+//!     max_repeats: None,
+//!     allowed_xforms: AllowedTransformFamiles::RotateAndFlip,
+//!     facing: Facing::Symmetric,
+//!     allowed: None,
+//! };
+//! assert_eq!(ring::count(&spec)?, 13);
+//! assert_eq!(ring::enumerate(&spec)?.nth(1), Some(vec![0, 0, 0, 0, 0, 1]));
+//! # Ok::<(), burnside::SpecError>(())
+//! ```
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bag_draw::*,
+    breakdown::{Breakdown, Term},
+    SpecError,
+};
+
+mod bead_colours;
+mod permutation;
+pub use bead_colours::BeadColours;
+pub(crate) use permutation::Permutation;
+use permutation::{find_permutations, Transforms};
+
+/// The most beads a ring may have: permutations pack five bits per bead into 50 bits.
+pub const MAX_BEADS: u64 = 10;
+/// The most colours a bead may take: one for each letter, as for tiles.
+pub const MAX_COLOURS: u64 = 26;
+/// The most colours and facings together a bead may take, in its five bits.
+pub const MAX_STATES: u64 = 32;
+
+/// Which ways a ring may be moved before two rings count as the same.
+///
+/// These serialise as `none`, `rotate` and `rotate-flip`, the same names [`FromStr`] accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AllowedTransformFamiles {
+    /// Rings are fixed in place, so every colouring is distinct.
+    #[serde(rename = "none")]
+    NoTransforms,
+    /// Beads may slide around the string.
+    #[default]
+    #[serde(rename = "rotate")]
+    Rotate,
+    /// Rings may be turned over as well as slid around.
+    #[serde(rename = "rotate-flip")]
+    RotateAndFlip,
+}
+use AllowedTransformFamiles::*;
+
+impl FromStr for AllowedTransformFamiles {
+    type Err = String;
+
+    /// Parses `none`, `rotate` or `rotate-flip`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(NoTransforms),
+            "rotate" => Ok(Rotate),
+            "rotate-flip" => Ok(RotateAndFlip),
+            _ => Err(format!("Unknown transform family {s:?}")),
+        }
+    }
+}
+
+/// What turning a ring over does to its beads.
+///
+/// Sliding beads around the string leaves them as they were, but turning the ring over shows the
+/// other side of every bead and reverses which way along the string each one points.
+///
+/// These serialise as `symmetric`, `two-faced` and `pointing`, the same names [`FromStr`] accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Facing {
+    /// Beads look the same from either side and either way along the string.
+    #[default]
+    #[serde(rename = "symmetric")]
+    Symmetric,
+    /// Beads have a front and a back, like charms, and turning the ring over swaps them.
+    #[serde(rename = "two-faced")]
+    TwoFaced,
+    /// Beads point one way along the string, like half-coloured beads, and turning the ring over
+    /// reverses them.
+    #[serde(rename = "pointing")]
+    Pointing,
+}
+
+impl FromStr for Facing {
+    type Err = String;
+
+    /// Parses `symmetric`, `two-faced` or `pointing`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "symmetric" => Ok(Facing::Symmetric),
+            "two-faced" => Ok(Facing::TwoFaced),
+            "pointing" => Ok(Facing::Pointing),
+            _ => Err(format!("Unknown facing {s:?}")),
+        }
+    }
+}
+
+impl Facing {
+    pub const ALL: [Facing; 3] = [Facing::Symmetric, Facing::TwoFaced, Facing::Pointing];
+
+    pub fn is_symmetric(&self) -> bool {
+        *self == Facing::Symmetric
+    }
+
+    /// How many ways up a bead can be: front or back, or pointing clockwise or anticlockwise.
+    pub fn states(self) -> u64 {
+        match self {
+            Facing::Symmetric => 1,
+            Facing::TwoFaced | Facing::Pointing => 2,
+        }
+    }
+
+    /// The most colours a ring can have with these beads.
+    pub fn max_colours(self) -> u64 {
+        MAX_COLOURS.min(MAX_STATES / self.states())
+    }
+
+    /// The colour of a bead and which way up it is (0 for the front or clockwise, 1 for the back
+    /// or anticlockwise), from the value `enumerate` gives for it.
+    pub fn split(self, value: u8) -> (u8, u8) {
+        let states = self.states() as u8;
+        (value / states, value % states)
+    }
+
+    /// The value of a bead after the ring is turned over.
+    pub(crate) fn turn_over(self, value: u8) -> u8 {
+        match self {
+            Facing::Symmetric => value,
+            Facing::TwoFaced | Facing::Pointing => value ^ 1,
+        }
+    }
+
+    /// How many of a bead's states turning it over leaves as they are: for a bead on the axis of
+    /// a flip, which stays where it is but still gets turned over.
+    fn fixed_by_flip(self) -> u64 {
+        match self {
+            Facing::Symmetric => 1,
+            Facing::TwoFaced | Facing::Pointing => 0,
+        }
+    }
+}
+
+/// Everything needed to count or enumerate a family of rings.
+///
+/// In JSON this reads `{"beads": 6, "colours": 3, "maxRepeats": 2, "transforms": "rotate",
+/// "facing": "two-faced", "allowed": "A[AB]xxxx"}`, where `maxRepeats`, `transforms`, `facing`
+/// and `allowed` may be left out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RingSpec {
+    #[serde(rename = "beads")]
+    pub n_beads: u64,
+    #[serde(rename = "colours")]
+    pub n_colours: u64,
+    /// The most times any one colour may appear on a ring, if limited.
+    #[serde(default)]
+    pub max_repeats: Option<u64>,
+    /// Which ways a ring may be moved before two rings count as the same.
+    #[serde(rename = "transforms", default)]
+    pub allowed_xforms: AllowedTransformFamiles,
+    /// What turning the ring over does to its beads.
+    #[serde(default, skip_serializing_if = "Facing::is_symmetric")]
+    pub facing: Facing,
+    /// The colours each bead may take, if some are limited. Only rotations and flips that put
+    /// every bead where one allowed the same colours was count as symmetries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed: Option<BeadColours>,
+}
+
+impl RingSpec {
+    /// Checks that the ring fits in a permutation.
+    pub fn validate(&self) -> Result<(), SpecError> {
+        if self.n_beads < 2 || self.n_beads > MAX_BEADS {
+            return Err(SpecError::Size {
+                min: 2,
+                max: MAX_BEADS,
+            });
+        }
+        let max_colours = self.facing.max_colours();
+        if self.n_colours == 0 || self.n_colours > max_colours {
+            return Err(SpecError::Colours { max: max_colours });
+        }
+        if self.max_repeats == Some(0) {
+            return Err(SpecError::NoRepeats);
+        }
+        if let Some(allowed) = self.allowed {
+            if allowed.len() != self.n_beads
+                || allowed
+                    .sets()
+                    .any(|colours| colours.is_invalid(self.n_colours))
+            {
+                return Err(SpecError::Allowed);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn breakdown(&self) -> Breakdown {
+        if let Some(allowed) = self.allowed {
+            return self.allowed_breakdown(&allowed);
+        }
+        match self.max_repeats {
+            Some(max_repeats) => limited_breakdown(
+                self.n_colours,
+                self.n_beads,
+                max_repeats,
+                self.allowed_xforms,
+                self.facing,
+            ),
+            None => unlimited_breakdown(
+                self.n_colours,
+                self.n_beads,
+                self.allowed_xforms,
+                self.facing,
+            ),
+        }
+    }
+
+    /// The breakdown for beads limited to some colours, a rotation or flip at a time: each
+    /// counts if it keeps the limits where they were, and fixes the colourings that are the same
+    /// all the way round its cycles. Flips of the same kind that fix as many share a term.
+    fn allowed_breakdown(&self, allowed: &BeadColours) -> Breakdown {
+        let n_beads = self.n_beads;
+        let mut breakdown = Breakdown::default();
+        breakdown.push(Term::new("Identity", 1, self.fixed_by(allowed, 0, false)));
+        if matches!(self.allowed_xforms, Rotate | RotateAndFlip) {
+            for step in 1..n_beads {
+                if allowed.symmetric_under(place(n_beads, step, false)) {
+                    let fixed = self.fixed_by(allowed, step, false);
+                    breakdown.push(Term::new(rotate_name(step, n_beads), 1, fixed));
+                }
+            }
+        }
+        if matches!(self.allowed_xforms, RotateAndFlip) {
+            let mut flips: Vec<Term> = vec![];
+            for step in 0..n_beads {
+                if !allowed.symmetric_under(place(n_beads, step, true)) {
+                    continue;
+                }
+                // Turning over swaps beads n and n_beads - 1 - n + step, so a bead stays put
+                // when that's an even number of beads round
+                let name = if n_beads % 2 == 1 {
+                    ODD_FLIP
+                } else if step % 2 == 1 {
+                    CORNER_FLIP
+                } else {
+                    EDGE_FLIP
+                };
+                let fixed = self.fixed_by(allowed, step, true);
+                match flips
+                    .iter_mut()
+                    .find(|term| term.transform == name && term.fixed == fixed)
+                {
+                    Some(term) => term.elements += 1,
+                    None => flips.push(Term::new(name, 1, fixed)),
+                }
+            }
+            breakdown.terms.extend(flips);
+        }
+        breakdown
+    }
+
+    /// How many colourings turning the ring over, if `flipped`, then sliding it `step` beads
+    /// along leaves unchanged: each cycle has to be one colour all its beads allow, and turning
+    /// over has to leave the beads the same way up.
+    fn fixed_by(&self, allowed: &BeadColours, step: u64, flipped: bool) -> u64 {
+        let cycles = allowed.cycles(place(self.n_beads, step, flipped));
+        let facings: u64 = if flipped {
+            let unmoved = cycles.iter().filter(|&&(length, _)| length == 1).count() as u64;
+            flip_facings(self.facing, cycles.len() as u64 - unmoved, unmoved)
+        } else {
+            self.facing.states().pow(cycles.len() as u32)
+        };
+        let draws: Vec<_> = cycles
+            .iter()
+            .map(|&(length, colours)| (length as u8, colours.bits_below(self.n_colours)))
+            .collect();
+        let colourings = match self.max_repeats {
+            Some(max_repeats) => count_allowed(&draws, self.n_colours, max_repeats),
+            None => draws
+                .iter()
+                .map(|&(_, colours)| colours.count_ones() as u64)
+                .product(),
+        };
+        colourings * facings
+    }
+
+    /// The colours each bead may take: as limited, or else any.
+    pub fn bead_colours(&self) -> BeadColours {
+        self.allowed
+            .unwrap_or_else(|| BeadColours::any(self.n_beads))
+    }
+
+    fn transforms(&self) -> Transforms {
+        Transforms::new(
+            self.n_beads,
+            self.allowed_xforms,
+            self.facing,
+            self.bead_colours(),
+        )
+    }
+
+    /// Every distinct ring, as the smallest member of its orbit, in sorted order.
+    pub(crate) fn permutations(&self) -> Vec<Permutation> {
+        let mut permutations: Vec<_> = find_permutations(
+            &self.transforms(),
+            self.n_beads,
+            self.n_colours,
+            self.facing.states(),
+            self.max_repeats.unwrap_or(u64::MAX),
+        )
+        .into_iter()
+        .collect();
+        permutations.sort_unstable();
+        permutations
+    }
+
+    /// How many elements of the symmetry group leave `permutation` unchanged. The ring's orbit
+    /// has `group_order / stabilizer` members.
+    #[cfg_attr(not(feature = "gui"), allow(dead_code))]
+    pub(crate) fn stabilizer(&self, permutation: &Permutation) -> u64 {
+        self.transforms().stabilizer(permutation)
+    }
+
+    /// The smallest of the rings equivalent to `permutation`, which is how `permutations` lists it.
+    #[cfg_attr(not(feature = "gui"), allow(dead_code))]
+    pub(crate) fn canonicalize(&self, permutation: &Permutation) -> Permutation {
+        self.transforms().canonicalize(permutation)
+    }
+
+    /// The ring with these beads; the inverse of `beads`.
+    #[cfg_attr(not(feature = "gui"), allow(dead_code))]
+    pub(crate) fn permutation(&self, beads: &[u8]) -> Permutation {
+        let mut permutation = Permutation::new();
+        for (n, &colour) in beads.iter().enumerate() {
+            permutation.set(n as u64, colour);
+        }
+        permutation
+    }
+
+    /// The value of each bead of `permutation`, in order around the ring.
+    pub(crate) fn beads(&self, permutation: &Permutation) -> Vec<u8> {
+        (0..self.n_beads).map(|n| permutation.get(n)).collect()
+    }
+}
+
+/// The number of distinct rings.
+pub fn count(spec: &RingSpec) -> Result<u64, SpecError> {
+    Ok(breakdown(spec)?.count())
+}
+
+/// How many rings each transform in the symmetry group leaves unchanged.
+pub fn breakdown(spec: &RingSpec) -> Result<Breakdown, SpecError> {
+    spec.validate()?;
+    Ok(spec.breakdown())
+}
+
+/// Every distinct ring, as the colour index of each bead in turn.
+///
+/// With beads that can be either way up, each bead holds its colour and which way up it is
+/// together, which [`Facing::split`] takes apart.
+///
+/// Each ring is the lexicographically smallest of its equivalent rotations and flips, and the
+/// rings come out in sorted order. The whole set is found before the first is returned, so check
+/// [`count`] before enumerating anything large.
+pub fn enumerate(spec: &RingSpec) -> Result<impl Iterator<Item = Vec<u8>>, SpecError> {
+    spec.validate()?;
+    let spec = *spec;
+    Ok(spec
+        .permutations()
+        .into_iter()
+        .map(move |permutation| spec.beads(&permutation)))
+}
+
+pub(crate) fn gcd(a: u64, b: u64) -> u64 {
+    // a and b should be small enough that trial division is feasible
+    // Heck, we could even just have a look-up table
+    assert!(b <= a);
+    for i in (1..=b).rev() {
+        if a.is_multiple_of(i) && b.is_multiple_of(i) {
+            return i;
+        }
+    }
+    1
+}
+
+fn limited_breakdown(
+    n_colours: u64,
+    n_beads: u64,
+    max_repeats: u64,
+    allowed_xforms: AllowedTransformFamiles,
+    facing: Facing,
+) -> Breakdown {
+    let mut breakdown = Breakdown::default();
+    breakdown.push(Term::new(
+        "Identity",
+        1,
+        simple_count(n_beads, n_colours, max_repeats) * facing.states().pow(n_beads as u32),
+    ));
+    if matches!(allowed_xforms, Rotate | RotateAndFlip) {
+        limited_rotate_terms(&mut breakdown, n_colours, n_beads, max_repeats, facing);
+    }
+    if matches!(allowed_xforms, RotateAndFlip) {
+        limited_flip_terms(&mut breakdown, n_colours, n_beads, max_repeats, facing);
+    }
+    breakdown
+}
+
+fn limited_rotate_terms(
+    breakdown: &mut Breakdown,
+    n_colours: u64,
+    n_beads: u64,
+    max_repeats: u64,
+    facing: Facing,
+) {
+    for i in 1..n_beads {
+        // This is the number of 'free' points. If the GCD == 1, then we require that all points the be same colour. If it's 2, then we have two points that can be independently coloured, and so on.
+        // E.g. if i == 2, and n_beads == 6, then bead 0 maps to bead 2 maps to bead 4, while bead 1 maps to bead 3 maps to bead 5. If i = 4, then bead 0 maps to bead 4 maps to bead 2, etc.
+        let rotations = gcd(n_beads, i);
+
+        let orbit_size = n_beads / rotations;
+        // We basically have a bag of max_repeats / orbit_size stones of each colour, from which we will be making `rotations` draws.
+        // How many distinct outcomes are there?
+        let n_combis = simple_count(rotations, n_colours, max_repeats / orbit_size);
+        // Sliding beads along doesn't turn them over, so each orbit can be either way up
+        let n_facings = facing.states().pow(rotations as u32);
+        breakdown.push(Term::new(rotate_name(i, n_beads), 1, n_combis * n_facings));
+    }
+}
+
+/// How many ways up the beads of a ring a flip fixes can be, when `pairs` pairs of beads swap
+/// places and `unmoved` beads sit on the axis. A pair can be either way up, as long as the two
+/// are opposite ways, but beads on the axis are turned over where they are.
+fn flip_facings(facing: Facing, pairs: u64, unmoved: u64) -> u64 {
+    facing.states().pow(pairs as u32) * facing.fixed_by_flip().pow(unmoved as u32)
+}
+
+fn limited_flip_terms(
+    breakdown: &mut Breakdown,
+    n_colours: u64,
+    n_beads: u64,
+    max_repeats: u64,
+    facing: Facing,
+) {
+    let reduced_max = max_repeats / 2;
+    if n_beads.is_multiple_of(2) {
+        // We can flip on edges, or corners
+        // Edges will fix n_beads/2, and we have n_beads/2 flip axes
+        // While corners will fix n_beads/2 + 1, and again we have n_beads/2 flip axes
+
+        // For edge flips, it's straight-forward
+        let n_combis = simple_count(n_beads / 2, n_colours, reduced_max);
+        let n_facings = flip_facings(facing, n_beads / 2, 0);
+        breakdown.push(Term::new(EDGE_FLIP, n_beads / 2, n_combis * n_facings));
+        // For corner flips, we don't have the same matching constraints.
+        // If both axes of the corner match in colour, that one colour will be less available for the other points
+        // Otherwise, it will depend on whether max_repeats is even or odd.
+        let mut counts = vec![0; max_repeats as usize + 1];
+        counts[max_repeats as usize] = n_colours;
+        let mut draws = vec![2; (n_beads - 2) as usize / 2];
+        draws.push(1);
+        draws.push(1);
+        let n_combis = count3(&draws, &mut counts);
+        let n_facings = flip_facings(facing, (n_beads - 2) / 2, 2);
+        breakdown.push(Term::new(CORNER_FLIP, n_beads / 2, n_combis * n_facings));
+    } else {
+        // Each corner flip is an edge flip and vice versa.
+        // Each will fix (n - 1) / 2 + 1
+        let mut counts = vec![0; max_repeats as usize / 2 + 1];
+        counts[reduced_max as usize] = n_colours - 1;
+        counts[(max_repeats - 1) as usize / 2] += 1;
+        let n_combis = n_colours * count2((n_beads - 1) / 2, &mut counts);
+        let n_facings = flip_facings(facing, (n_beads - 1) / 2, 1);
+        breakdown.push(Term::new(ODD_FLIP, n_beads, n_combis * n_facings));
+    }
+}
+
+fn unlimited_breakdown(
+    n_colours: u64,
+    n_beads: u64,
+    allowed_xforms: AllowedTransformFamiles,
+    facing: Facing,
+) -> Breakdown {
+    // Every bead can be any colour, either way up
+    let n_states = n_colours * facing.states();
+    let mut breakdown = Breakdown::default();
+    breakdown.push(Term::new("Identity", 1, n_states.pow(n_beads as u32)));
+    if matches!(allowed_xforms, Rotate | RotateAndFlip) {
+        for i in 1..n_beads {
+            let rotations = gcd(n_beads, i);
+            breakdown.push(Term::new(
+                rotate_name(i, n_beads),
+                1,
+                n_states.pow(rotations as u32),
+            ));
+        }
+    }
+    if matches!(allowed_xforms, RotateAndFlip) {
+        if n_beads.is_multiple_of(2) {
+            // We can flip on edges, or corners
+            // Edges will fix n_beads/2, and we have n_beads/2 flip axes
+            // While corners will fix n_beads/2 + 1, and again we have n_beads/2 flip axes
+            breakdown.push(Term::new(
+                EDGE_FLIP,
+                n_beads / 2,
+                n_colours.pow((n_beads / 2) as u32) * flip_facings(facing, n_beads / 2, 0),
+            ));
+            breakdown.push(Term::new(
+                CORNER_FLIP,
+                n_beads / 2,
+                n_colours.pow((n_beads / 2 + 1) as u32)
+                    * flip_facings(facing, (n_beads - 2) / 2, 2),
+            ));
+        } else {
+            // Each corner flip is an edge flip and vice versa.
+            // Each will fix (n - 1) / 2 + 1
+            // Rely on rounding down so we don't need to subtract 1 first
+            breakdown.push(Term::new(
+                ODD_FLIP,
+                n_beads,
+                n_colours.pow((n_beads / 2 + 1) as u32) * flip_facings(facing, n_beads / 2, 1),
+            ));
+        }
+    }
+    breakdown
+}
+
+/// Where bead `n` of `n_beads` ends up when the ring is turned over, if `flipped`, then slid
+/// `step` beads along, the same as `Permutation::flip` then `Permutation::rotate_n` move it.
+pub(crate) fn place(n_beads: u64, step: u64, flipped: bool) -> impl Fn(u64) -> u64 {
+    move |n| {
+        let n = if flipped { n_beads - 1 - n } else { n };
+        (n + step) % n_beads
+    }
+}
+
+const EDGE_FLIP: &str = "Flip between beads";
+const CORNER_FLIP: &str = "Flip through beads";
+const ODD_FLIP: &str = "Flip";
+
+fn rotate_name(step: u64, n_beads: u64) -> String {
+    format!("Rotate {step}/{n_beads}")
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::{
+        count, enumerate, limited_breakdown,
+        permutation::{build_permutations, Permutation, Transforms},
+        unlimited_breakdown, AllowedTransformFamiles, BeadColours, Facing, RingSpec,
+    };
+    use crate::SpecError;
+
+    // #[test]
+    // fn test_ring_limited2() {
+    //     let xform = AllowedTransformFamiles::RotateAndFlip;
+    //     let beads = 3;
+    //     let transforms = Transforms::new(beads, xform);
+    //     let colours = 2;
+    //     let max_repeats = 3;
+    //     let breakdown = limited_breakdown(colours, beads, max_repeats, xform);
+    //     let (orbits, fixed) = (breakdown.group_order(), breakdown.total_fixed());
+    //     assert_eq!(0, fixed % orbits, "Fixed count indivisible by orbits on {colours} colours, {beads} beads, {max_repeats} max repeats and {xform:?} transform");
+    //     let perm_count = if orbits == 0 {
+    //         0
+    //     } else {
+    //         fixed / orbits
+    //     };
+    //     if perm_count <= 10000 {
+    //         let mut seen = HashSet::with_capacity(perm_count as usize);
+    //         let mut colour_counts = vec![0; colours as usize];
+    //         build_permutations(
+    //             &transforms,
+    //             beads,
+    //             colours,
+    //             0,
+    //             Permutation::new(),
+    //             &mut colour_counts,
+    //             max_repeats,
+    //             &mut seen,
+    //         );
+    //         assert_eq!(seen.len(), perm_count as usize, "Mismatch on {colours} colours, {beads} beads, {max_repeats} max repeats and {xform:?} transform");
+    //     }
+    // }
+
+    /// Asserts that counting the rings for `spec` agrees with listing them one by one, which
+    /// only works for fixtures with a modest number of rings.
+    fn assert_count_matches_enumeration(spec: &RingSpec) {
+        let rings: u64 = spec
+            .bead_colours()
+            .sets()
+            .map(|allowed| allowed.bits_below(spec.n_colours).count_ones() as u64)
+            .product();
+        assert!(
+            rings * spec.facing.states().pow(spec.n_beads as u32) <= 1 << 16,
+            "{:?} has too many rings to list",
+            spec
+        );
+        assert_eq!(
+            count(spec).unwrap(),
+            spec.permutations().len() as u64,
+            "{:?}",
+            spec
+        );
+    }
+
+    #[test]
+    #[cfg_attr(not(feature = "slow_tests"), ignore = "Skipping slow tests")]
+    fn test_ring_limited() {
+        for xform in [
+            AllowedTransformFamiles::NoTransforms,
+            AllowedTransformFamiles::Rotate,
+            AllowedTransformFamiles::RotateAndFlip,
+        ] {
+            for beads in 2..10 {
+                let transforms =
+                    Transforms::new(beads, xform, Facing::Symmetric, BeadColours::any(beads));
+                for colours in 1..8 {
+                    let min_max_repeats = beads.div_ceil(colours);
+                    let max_max_repeats = beads;
+                    for max_repeats in min_max_repeats..=max_max_repeats {
+                        let breakdown = limited_breakdown(
+                            colours,
+                            beads,
+                            max_repeats,
+                            xform,
+                            Facing::Symmetric,
+                        );
+                        let (orbits, fixed) = (breakdown.group_order(), breakdown.total_fixed());
+                        assert_eq!(0, fixed % orbits, "Fixed count indivisible by orbits on {colours} colours, {beads} beads, {max_repeats} max repeats and {xform:?} transform");
+                        let perm_count = fixed.checked_div(orbits).unwrap_or(0);
+                        if perm_count <= 10000 {
+                            let mut seen = HashSet::with_capacity(perm_count as usize);
+                            let mut colour_counts = vec![0; colours as usize];
+                            build_permutations(
+                                &transforms,
+                                beads,
+                                colours,
+                                1,
+                                0,
+                                Permutation::new(),
+                                &mut colour_counts,
+                                max_repeats,
+                                &mut seen,
+                            );
+                            assert_eq!(seen.len(), perm_count as usize, "Mismatch on {colours} colours, {beads} beads, {max_repeats} max repeats and {xform:?} transform");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg_attr(not(feature = "slow_tests"), ignore = "Skipping slow tests")]
+    fn test_ring_unlimited() {
+        for xform in [
+            AllowedTransformFamiles::NoTransforms,
+            AllowedTransformFamiles::Rotate,
+            AllowedTransformFamiles::RotateAndFlip,
+        ] {
+            for beads in 2..10 {
+                let transforms =
+                    Transforms::new(beads, xform, Facing::Symmetric, BeadColours::any(beads));
+                for colours in 1..8 {
+                    let breakdown = unlimited_breakdown(colours, beads, xform, Facing::Symmetric);
+                    let (orbits, fixed) = (breakdown.group_order(), breakdown.total_fixed());
+                    let perm_count = fixed.checked_div(orbits).unwrap_or(0);
+                    if perm_count <= 10000 {
+                        let mut seen = HashSet::with_capacity(perm_count as usize);
+                        let mut colour_counts = vec![0; colours as usize];
+                        build_permutations(
+                            &transforms,
+                            beads,
+                            colours,
+                            1,
+                            0,
+                            Permutation::new(),
+                            &mut colour_counts,
+                            u64::MAX,
+                            &mut seen,
+                        );
+                        assert_eq!(
+                            seen.len(),
+                            perm_count as usize,
+                            "Mismatch on {colours} colours, {beads} beads and {xform:?} transform"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_many_colours() {
+        // Sixteen colours need more than the three bits per bead permutations used to have
+        let spec = RingSpec {
+            n_beads: 3,
+            n_colours: 16,
+            max_repeats: None,
+            allowed_xforms: AllowedTransformFamiles::Rotate,
+            facing: Facing::Symmetric,
+            allowed: None,
+        };
+        assert_eq!(count(&spec), Ok(1376));
+        let rings: Vec<_> = enumerate(&spec).unwrap().collect();
+        assert_eq!(rings.len(), 1376);
+        assert_eq!(rings[1], [0, 0, 1]);
+        assert_eq!(rings.last().unwrap(), &[15; 3]);
+    }
+
+    #[test]
+    fn test_allowed_colours() {
+        // Beads that may be any colour count the same either way
+        for facing in Facing::ALL {
+            for n_beads in 2..8 {
+                for max_repeats in [None, Some(2), Some(3)] {
+                    let spec = RingSpec {
+                        n_beads,
+                        n_colours: 3,
+                        max_repeats,
+                        allowed_xforms: AllowedTransformFamiles::RotateAndFlip,
+                        facing,
+                        allowed: None,
+                    };
+                    let limited = RingSpec {
+                        allowed: Some(BeadColours::any(n_beads)),
+                        ..spec
+                    };
+                    assert_eq!(spec.breakdown().count(), limited.breakdown().count());
+                    assert_eq!(
+                        spec.breakdown().group_order(),
+                        limited.breakdown().group_order()
+                    );
+                }
+            }
+        }
+
+        // and limited beads are counted the same as the rings actually found
+        for allowed in ["A[AB]x[AB]", "[AB]x[AB]x", "[AB][BC][AC]xx", "Ax[BC]x[BC]x"] {
+            let allowed: BeadColours = allowed.parse().unwrap();
+            for facing in Facing::ALL {
+                for allowed_xforms in [
+                    AllowedTransformFamiles::NoTransforms,
+                    AllowedTransformFamiles::Rotate,
+                    AllowedTransformFamiles::RotateAndFlip,
+                ] {
+                    for max_repeats in [None, Some(2), Some(3)] {
+                        let spec = RingSpec {
+                            n_beads: allowed.len(),
+                            n_colours: 3,
+                            max_repeats,
+                            allowed_xforms,
+                            facing,
+                            allowed: Some(allowed),
+                        };
+                        assert_count_matches_enumeration(&spec);
+                    }
+                }
+            }
+        }
+
+        // Beads that may only be the first two colours make the bracelets of two colours
+        let spec = RingSpec {
+            n_beads: 6,
+            n_colours: 3,
+            max_repeats: None,
+            allowed_xforms: AllowedTransformFamiles::RotateAndFlip,
+            facing: Facing::Symmetric,
+            allowed: Some("[AB][AB][AB][AB][AB][AB]".parse().unwrap()),
+        };
+        assert_eq!(count(&spec), Ok(13));
+        assert!(enumerate(&spec).unwrap().flatten().all(|colour| colour < 2));
+        let spec = RingSpec {
+            allowed: Some("[AB][AB]".parse().unwrap()),
+            ..spec
+        };
+        assert_eq!(spec.validate(), Err(SpecError::Allowed));
+    }
+
+    #[test]
+    fn test_facing() {
+        // Burnside's count has to match the orbits actually found, now beads turn over too
+        for facing in Facing::ALL {
+            for allowed_xforms in [
+                AllowedTransformFamiles::NoTransforms,
+                AllowedTransformFamiles::Rotate,
+                AllowedTransformFamiles::RotateAndFlip,
+            ] {
+                for (n_beads, n_colours, max_repeats) in [
+                    (4, 2, None),
+                    (5, 2, None),
+                    (6, 1, None),
+                    (6, 3, Some(2)),
+                    (5, 2, Some(3)),
+                ] {
+                    let spec = RingSpec {
+                        n_beads,
+                        n_colours,
+                        max_repeats,
+                        allowed_xforms,
+                        facing,
+                        allowed: None,
+                    };
+                    assert_count_matches_enumeration(&spec);
+                }
+            }
+        }
+        // A charm turned over shows its back, so a single colour makes more than one bracelet
+        let spec = RingSpec {
+            n_beads: 3,
+            n_colours: 1,
+            max_repeats: None,
+            allowed_xforms: AllowedTransformFamiles::RotateAndFlip,
+            facing: Facing::TwoFaced,
+            allowed: None,
+        };
+        // Front, front, front (the same as back, back, back) and front, front, back
+        assert_eq!(count(&spec), Ok(2));
+        assert_eq!(enumerate(&spec).unwrap().last(), Some(vec![0, 0, 1]));
+    }
+}
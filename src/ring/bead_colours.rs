@@ -0,0 +1,132 @@
+use std::{convert::TryFrom, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use super::MAX_BEADS;
+use crate::ColourSet;
+
+/// The colours each bead of a ring may take, like a clasp that's always the first colour or
+/// spacers that may only be the first or second.
+///
+/// These serialise as a [`ColourSet`] for each bead in turn around the ring: `A[AB]x[AB]` fixes
+/// the first bead to the first colour, limits the second and fourth to the first two colours,
+/// and leaves the third free. [`FromStr`] reads the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct BeadColours {
+    len: u8,
+    allowed: [ColourSet; MAX_BEADS as usize],
+}
+
+impl BeadColours {
+    /// `n_beads` beads that may each be any colour.
+    pub fn any(n_beads: u64) -> Self {
+        Self::from_sets(vec![ColourSet::ANY; n_beads as usize])
+    }
+
+    /// The beads allowed each of `sets` in turn.
+    pub fn from_sets(sets: impl IntoIterator<Item = ColourSet>) -> Self {
+        let mut allowed = [ColourSet::ANY; MAX_BEADS as usize];
+        let mut len = 0;
+        for colours in sets {
+            assert!((len as u64) < MAX_BEADS, "Too many beads");
+            allowed[len] = colours;
+            len += 1;
+        }
+        BeadColours {
+            len: len as u8,
+            allowed,
+        }
+    }
+
+    /// How many beads there are.
+    pub fn len(&self) -> u64 {
+        self.len as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The colours bead `n` may take.
+    pub fn allowed(&self, n: u64) -> ColourSet {
+        self.allowed[n as usize]
+    }
+
+    /// The colours each bead may take, in turn.
+    pub fn sets(&self) -> impl Iterator<Item = ColourSet> + '_ {
+        self.allowed[..self.len as usize].iter().copied()
+    }
+
+    /// Whether any beads are limited to some colours.
+    pub fn is_limited(&self) -> bool {
+        self.sets().any(|colours| !colours.is_any())
+    }
+
+    /// Whether moving each bead `n` to `place(n)` puts every bead where one allowed the same
+    /// colours was, so it's one of the ring's symmetries.
+    pub(crate) fn symmetric_under(&self, place: impl Fn(u64) -> u64) -> bool {
+        (0..self.len()).all(|n| self.allowed(place(n)) == self.allowed(n))
+    }
+
+    /// The cycles moving each bead `n` to `place(n)` moves the beads round, as their lengths and
+    /// the colours all their beads allow.
+    pub(crate) fn cycles(&self, place: impl Fn(u64) -> u64) -> Vec<(u64, ColourSet)> {
+        let mut seen = vec![false; self.len as usize];
+        let mut cycles = vec![];
+        for start in 0..self.len() {
+            let mut length = 0;
+            let mut allowed = ColourSet::ANY;
+            let mut n = start;
+            while !seen[n as usize] {
+                seen[n as usize] = true;
+                length += 1;
+                allowed = allowed.intersection(self.allowed(n));
+                n = place(n);
+            }
+            if length > 0 {
+                cycles.push((length, allowed));
+            }
+        }
+        cycles
+    }
+}
+
+impl fmt::Display for BeadColours {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for colours in self.sets() {
+            write!(f, "{colours}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for BeadColours {
+    type Err = String;
+
+    /// Parses an `x`, capital letter or bracketed letters for each bead.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let sets = ColourSet::parse_cells(s)?
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| format!("A ring can't have gaps, as in {s:?}"))?;
+        if sets.len() as u64 > MAX_BEADS {
+            return Err(format!("{s:?} has more than {MAX_BEADS} beads"));
+        }
+        Ok(Self::from_sets(sets))
+    }
+}
+
+impl TryFrom<String> for BeadColours {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<BeadColours> for String {
+    fn from(beads: BeadColours) -> Self {
+        beads.to_string()
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::HashSet;
+
+use bitvec::prelude::*;
+
+use super::{place, AllowedTransformFamiles, BeadColours, Facing};
+use crate::{parallel::search_by_prefix, ColourSet};
+
+/// Bits per bead: enough for `MAX_STATES`.
+const BITS: u64 = 5;
+const _: () = assert!(super::MAX_STATES <= 1 << BITS);
+
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+pub struct Permutation {
+    value: BitArr!(for 50, in u32, Msb0),
+}
+
+impl Default for Permutation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Permutation {
+    pub fn new() -> Self {
+        Self {
+            value: bitarr![u32, Msb0; 0; 50],
+        }
+    }
+    pub fn get(&self, n: u64) -> u8 {
+        self.value[(n * BITS) as usize..((n + 1) * BITS) as usize].load()
+    }
+    pub fn set(&mut self, n: u64, value: u8) {
+        self.value[(n * BITS) as usize..((n + 1) * BITS) as usize].store(value);
+    }
+    pub fn rotate_n(&self, n: u64, max: u64) -> Self {
+        let mut new = Self::new();
+        for i in 0..max {
+            new.set((i + n) % max, self.get(i));
+        }
+        new
+    }
+
+    /// Turns the ring over, which reverses the order of the beads and turns each one over too.
+    pub fn flip(&self, max: u64, facing: Facing) -> Self {
+        let mut new = Self::new();
+        for i in 0..max {
+            new.set(max - 1 - i, facing.turn_over(self.get(i)));
+        }
+        new
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn build_permutations(
+    transforms: &Transforms,
+    n_beads: u64,
+    n_colours: u64,
+    states: u64,
+    n: u64,
+    mut permutation: Permutation,
+    colour_counts: &mut [u64],
+    colour_limit: u64,
+    seen: &mut HashSet<Permutation>,
+) {
+    if n == n_beads {
+        // We're done; canonicalize and add to `seen`
+        seen.insert(transforms.canonicalize(&permutation));
+        return;
+    }
+
+    let allowed = transforms.allowed(n);
+    for value in 0..n_colours * states {
+        let colour = value / states;
+        // Only try the colours the bead allows
+        if !allowed.contains(colour as u8) {
+            continue;
+        }
+        permutation.set(n, value as u8);
+        colour_counts[colour as usize] += 1;
+        if colour_counts[colour as usize] <= colour_limit {
+            build_permutations(
+                transforms,
+                n_beads,
+                n_colours,
+                states,
+                n + 1,
+                permutation.clone(),
+                colour_counts,
+                colour_limit,
+                seen,
+            );
+        }
+        colour_counts[colour as usize] -= 1;
+    }
+}
+
+/// Finds every canonical ring, splitting the search on the colours (and facings) of the first few
+/// beads.
+pub fn find_permutations(
+    transforms: &Transforms,
+    n_beads: u64,
+    n_colours: u64,
+    states: u64,
+    colour_limit: u64,
+) -> HashSet<Permutation> {
+    search_by_prefix(n_colours * states, n_beads, |prefix, seen| {
+        let mut permutation = Permutation::new();
+        let mut colour_counts = vec![0; n_colours as usize];
+        for (n, &value) in prefix.iter().enumerate() {
+            let colour = value as usize / states as usize;
+            if !transforms.allowed(n as u64).contains(colour as u8) {
+                return;
+            }
+            colour_counts[colour] += 1;
+            if colour_counts[colour] > colour_limit {
+                return;
+            }
+            permutation.set(n as u64, value);
+        }
+        build_permutations(
+            transforms,
+            n_beads,
+            n_colours,
+            states,
+            prefix.len() as u64,
+            permutation,
+            &mut colour_counts,
+            colour_limit,
+            seen,
+        );
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transforms {
+    n_beads: u64,
+    allowed_families: AllowedTransformFamiles,
+    facing: Facing,
+    allowed: BeadColours,
+}
+
+impl Transforms {
+    pub fn new(
+        n_beads: u64,
+        allowed_families: AllowedTransformFamiles,
+        facing: Facing,
+        allowed: BeadColours,
+    ) -> Self {
+        Transforms {
+            n_beads,
+            allowed_families,
+            facing,
+            allowed,
+        }
+    }
+
+    /// `perm` transformed by every element of the group, starting with the identity. The group
+    /// leaves out any rotation or flip that would move a bead to where one allowed different
+    /// colours was.
+    pub fn images(self, perm: &Permutation) -> Vec<Permutation> {
+        let n_beads = self.n_beads;
+        let symmetric = |step, flipped| self.allowed.symmetric_under(place(n_beads, step, flipped));
+        let mut images = vec![perm.clone()];
+        if matches!(
+            self.allowed_families,
+            AllowedTransformFamiles::Rotate | AllowedTransformFamiles::RotateAndFlip
+        ) {
+            for n in 1..n_beads {
+                if symmetric(n, false) {
+                    images.push(perm.rotate_n(n, n_beads));
+                }
+            }
+        }
+        if matches!(
+            self.allowed_families,
+            AllowedTransformFamiles::RotateAndFlip
+        ) {
+            let flipped = perm.flip(n_beads, self.facing);
+            for n in 1..n_beads {
+                if symmetric(n, true) {
+                    images.push(flipped.rotate_n(n, n_beads));
+                }
+            }
+            if symmetric(0, true) {
+                images.push(flipped);
+            }
+        }
+        images
+    }
+
+    /// The colours bead `n` may take.
+    pub fn allowed(&self, n: u64) -> ColourSet {
+        self.allowed.allowed(n)
+    }
+
+    pub fn canonicalize(self, perm: &Permutation) -> Permutation {
+        self.images(perm).into_iter().min().unwrap()
+    }
+
+    /// How many elements of the group leave `perm` unchanged.
+    #[cfg_attr(not(feature = "gui"), allow(dead_code))]
+    pub fn stabilizer(self, perm: &Permutation) -> u64 {
+        self.images(perm)
+            .iter()
+            .filter(|image| *image == perm)
+            .count() as u64
+    }
+}
@@ -0,0 +1,810 @@
+//! Game tiles, with a marking in each cell: rectangles, or any shape drawn on a grid.
+//!
+//! ```
+//! use burnside::tile::{self, AllowedTransformFamiles, Orientation, TileSpec};
+//!
+//! let spec = TileSpec {
+//!     width: 2,
+//!     height: 2,
+//!     n_colours: 3,
+//!     max_repeats: None,
+//!     allowed_xforms: AllowedTransformFamiles::Rotate,
+//!     orientation: Orientation::Symmetric,
+//!     two_sided: false,
+//!     shape: None,
+//! };
+//! assert_eq!(tile::count(&spec)?, 24);
+//! let first = tile::enumerate(&spec)?.next().unwrap();
+//! assert_eq!(first, vec![vec![0, 0], vec![0, 0]]);
+//! # Ok::<(), burnside::SpecError>(())
+//! ```
+use std::convert::TryFrom;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bag_draw::{count3, count_allowed},
+    breakdown::{Breakdown, Term},
+    SpecError,
+};
+
+mod orientation;
+mod permutation;
+mod shape;
+mod transforms;
+use orientation::Motion;
+pub use orientation::Orientation;
+pub(crate) use permutation::Permutation;
+use permutation::{find_permutations, Transforms};
+pub use shape::Shape;
+pub use transforms::AllowedTransformFamiles;
+use transforms::*;
+
+/// The most cells a tile may have to be listed, both faces included: permutations pack five bits
+/// per cell into 125 bits. Bigger tiles can still be counted.
+pub const MAX_CELLS: u64 = 25;
+/// The most colours a cell may take: one for each letter, so designs can be written as `ABBA/BAAB`.
+pub const MAX_COLOURS: u64 = 26;
+/// The most colours and orientations together a cell may take, in its five bits.
+pub const MAX_STATES: u64 = 32;
+/// What `enumerate` gives for the gaps in a tile's shape, which take no colour.
+pub const GAP: u8 = u8::MAX;
+
+/// Everything needed to count or enumerate a family of tiles.
+///
+/// In JSON this reads `{"width": 2, "height": 2, "colours": 3, "maxRepeats": 2, "transforms":
+/// "rotate", "orientation": "arrow", "twoSided": true}`, where everything after `colours` may be
+/// left out.
+///
+/// A two-sided tile's back is listed under its front, as rows `height..2 * height`. Each back cell
+/// sits directly behind the front cell with the same position, so the back reads as if seen
+/// through the front.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TileSpec {
+    pub width: u64,
+    pub height: u64,
+    #[serde(rename = "colours")]
+    pub n_colours: u64,
+    /// The most times any one colour may appear on a tile, if limited.
+    #[serde(default)]
+    pub max_repeats: Option<u64>,
+    /// Which ways a tile may be moved before two tiles count as the same.
+    #[serde(rename = "transforms", default)]
+    pub allowed_xforms: AllowedTransformFamiles,
+    /// Which way round the markings in the cells can sit.
+    #[serde(default, skip_serializing_if = "Orientation::is_symmetric")]
+    pub orientation: Orientation,
+    /// Whether the tile has a back as well as a front, like a cardboard token. Turning it over
+    /// swaps the faces.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub two_sided: bool,
+    /// Which cells of the grid make up the tile, if it isn't the whole rectangle.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shape: Option<Shape>,
+}
+
+impl TileSpec {
+    /// Checks that the tile can be counted, with its count fitting in a `u64`.
+    pub fn validate(&self) -> Result<(), SpecError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(SpecError::Size {
+                min: 1,
+                max: u64::MAX,
+            });
+        }
+        let max_colours = self.orientation.max_colours();
+        if self.n_colours == 0 || self.n_colours > max_colours {
+            return Err(SpecError::Colours { max: max_colours });
+        }
+        if let Some(shape) = self.shape {
+            let size = (shape.width(), shape.height());
+            if size != (self.width, self.height) || shape.trimmed() != shape {
+                return Err(SpecError::Shape);
+            }
+            if shape
+                .allowed_sets()
+                .any(|allowed| allowed.is_invalid(self.n_colours))
+            {
+                return Err(SpecError::Allowed);
+            }
+        }
+        let cells = match self.shape {
+            Some(shape) => shape.len(),
+            None => self.width.saturating_mul(self.height),
+        };
+        // A single colour is held to the size two would allow, so a tile can't be endlessly big
+        let states = (self.n_colours * self.orientation.states()).max(2);
+        let colourings = u32::try_from(cells.saturating_mul(self.faces()))
+            .ok()
+            .and_then(|cells| states.checked_pow(cells));
+        if colourings.is_none() {
+            return Err(SpecError::Overflow);
+        }
+        if self.max_repeats == Some(0) {
+            return Err(SpecError::NoRepeats);
+        }
+        Ok(())
+    }
+
+    /// Checks that the tiles can be listed as well as counted: each is held in a permutation of
+    /// at most `MAX_CELLS` cells.
+    pub fn validate_enumerable(&self) -> Result<(), SpecError> {
+        self.validate()?;
+        if self.width * self.height * self.faces() > MAX_CELLS {
+            return Err(SpecError::Size {
+                min: 1,
+                max: MAX_CELLS,
+            });
+        }
+        Ok(())
+    }
+
+    pub(crate) fn breakdown(&self) -> Breakdown {
+        match self.shape {
+            Some(shape) if !shape.is_rectangle() || shape.is_limited() => self.shaped_breakdown(),
+            _ => self.rectangle_breakdown(),
+        }
+    }
+
+    fn rectangle_breakdown(&self) -> Breakdown {
+        let square = self.width == self.height;
+        let terms = vec![
+            self.term::<NoXform>("Identity", square),
+            self.term::<Rot90>("Rotate 90°/270°", square),
+            self.term::<Rot180>("Rotate 180°", square),
+            self.term::<HFlip>("Horizontal flip", square),
+            self.term::<VFlip>("Vertical flip", square),
+            self.term::<DFlip>("Diagonal flip", square),
+        ];
+        Breakdown {
+            terms: terms.into_iter().flatten().collect(),
+        }
+    }
+
+    fn term<T: Transform>(&self, name: &str, square: bool) -> Option<Term> {
+        if !T::applicable(self.allowed_xforms, square) {
+            return None;
+        }
+        let Self {
+            width,
+            height,
+            n_colours,
+            orientation,
+            two_sided,
+            ..
+        } = *self;
+        let fixed = match self.max_repeats {
+            Some(max_repeats) => T::limited_n_fixed(
+                width,
+                height,
+                two_sided,
+                n_colours,
+                max_repeats,
+                orientation,
+            ),
+            None => T::n_fixed(width, height, two_sided, n_colours, orientation),
+        };
+        Some(Term::new(name, T::ORBITS, fixed))
+    }
+
+    /// The breakdown for any shape of tile: its symmetries are whichever allowed motions land it
+    /// on itself, fixed cells included, and each fixes the colourings that are the same all the
+    /// way round its cycles.
+    fn shaped_breakdown(&self) -> Breakdown {
+        let shape = self.cells();
+        let terms = [
+            ("Identity", &[Motion::IDENTITY][..]),
+            ("Rotate 90°/270°", &[Motion::ROTATE_90, Motion::ROTATE_270]),
+            ("Rotate 180°", &[Motion::ROTATE_180]),
+            ("Horizontal flip", &[Motion::HFLIP]),
+            ("Vertical flip", &[Motion::VFLIP]),
+            ("Diagonal flip", &[Motion::DFLIP1, Motion::DFLIP2]),
+        ];
+        let mut breakdown = Breakdown::default();
+        for &(name, motions) in &terms {
+            let mut group: Vec<Term> = vec![];
+            for &motion in motions {
+                if !self.allowed_xforms.allows(motion) || !shape.symmetric_under(motion) {
+                    continue;
+                }
+                // The two diagonals can fix different numbers of colourings when the cells
+                // along them allow different colours
+                let fixed = self.fixed_by(&shape, motion);
+                match group.iter_mut().find(|term| term.fixed == fixed) {
+                    Some(term) => term.elements += 1,
+                    None => group.push(Term::new(name, 1, fixed)),
+                }
+            }
+            breakdown.terms.extend(group);
+        }
+        breakdown
+    }
+
+    /// How many colourings of `shape` `motion` leaves unchanged: each of its cycles has to be
+    /// one colour that all its cells allow, and each marking has to come back round the way it
+    /// started.
+    fn fixed_by(&self, shape: &Shape, motion: Motion) -> u64 {
+        let cycles = shape.cycles(motion, self.two_sided);
+        let orientations: u64 = cycles
+            .iter()
+            .map(|&(length, _)| self.orientation.fixed_by_repeated(motion, length))
+            .product();
+        let draws: Vec<_> = cycles
+            .iter()
+            .map(|&(length, allowed)| (length as u8, allowed.bits_below(self.n_colours)))
+            .collect();
+        let colourings = match self.max_repeats {
+            Some(max_repeats) if shape.is_limited() => {
+                count_allowed(&draws, self.n_colours, max_repeats)
+            }
+            Some(max_repeats) => {
+                let mut counts = vec![0; max_repeats as usize + 1];
+                counts[max_repeats as usize] = self.n_colours;
+                let lengths: Vec<_> = draws.iter().map(|&(length, _)| length).collect();
+                count3(&lengths, &mut counts)
+            }
+            None => draws
+                .iter()
+                .map(|&(_, allowed)| allowed.count_ones() as u64)
+                .product(),
+        };
+        colourings * orientations
+    }
+
+    /// The cells that make up the tile: its shape, or else the whole rectangle.
+    pub fn cells(&self) -> Shape {
+        self.shape
+            .unwrap_or_else(|| Shape::rectangle(self.width, self.height))
+    }
+
+    /// How many faces the tile has: 1, or 2 if it's two-sided.
+    pub fn faces(&self) -> u64 {
+        if self.two_sided {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn transforms(&self) -> Transforms {
+        Transforms::new(
+            self.cells(),
+            self.two_sided,
+            self.allowed_xforms,
+            self.orientation,
+        )
+    }
+
+    /// Every distinct tile, as the smallest member of its orbit, in sorted order.
+    pub(crate) fn permutations(&self) -> Vec<Permutation> {
+        let mut permutations: Vec<_> = find_permutations(
+            &self.transforms(),
+            self.width,
+            self.height * self.faces(),
+            self.n_colours,
+            self.orientation.states(),
+            self.max_repeats.unwrap_or(u64::MAX),
+        )
+        .into_iter()
+        .collect();
+        permutations.sort_unstable();
+        permutations
+    }
+
+    /// How many elements of the symmetry group leave `permutation` unchanged. The tile's orbit
+    /// has `group_order / stabilizer` members.
+    #[cfg_attr(not(feature = "gui"), allow(dead_code))]
+    pub(crate) fn stabilizer(&self, permutation: &Permutation) -> u64 {
+        self.transforms().stabilizer(permutation)
+    }
+
+    /// The smallest of the tiles equivalent to `permutation`, which is how `permutations` lists it.
+    #[cfg_attr(not(feature = "gui"), allow(dead_code))]
+    pub(crate) fn canonicalize(&self, permutation: &Permutation) -> Permutation {
+        self.transforms().canonicalize(permutation)
+    }
+
+    /// The tile with these rows of cell values; the inverse of `rows`.
+    #[cfg_attr(not(feature = "gui"), allow(dead_code))]
+    pub(crate) fn permutation(&self, rows: &[Vec<u8>]) -> Permutation {
+        let w = self.width as usize;
+        let transforms = self.transforms();
+        let mut permutation = Permutation::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &colour) in row.iter().enumerate() {
+                if transforms.is_cell(x, y) {
+                    permutation.set(x, y, w, colour);
+                }
+            }
+        }
+        permutation
+    }
+
+    /// The value of each cell of `permutation`, row by row, with the back (if any) after the front
+    /// and `GAP` for the gaps in the shape.
+    pub(crate) fn rows(&self, permutation: &Permutation) -> Vec<Vec<u8>> {
+        let w = self.width as usize;
+        let transforms = self.transforms();
+        (0..(self.height * self.faces()) as usize)
+            .map(|y| {
+                (0..w)
+                    .map(|x| {
+                        if transforms.is_cell(x, y) {
+                            permutation.get(x, y, w)
+                        } else {
+                            GAP
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// The number of distinct tiles.
+pub fn count(spec: &TileSpec) -> Result<u64, SpecError> {
+    Ok(breakdown(spec)?.count())
+}
+
+/// How many tiles each transform in the symmetry group leaves unchanged.
+pub fn breakdown(spec: &TileSpec) -> Result<Breakdown, SpecError> {
+    spec.validate()?;
+    Ok(spec.breakdown())
+}
+
+/// Every distinct tile, as rows of colour indices.
+///
+/// With oriented markings, each cell holds its colour and orientation together, which
+/// [`Orientation::split`] takes apart. A two-sided tile has twice as many rows: its front, then its
+/// back. A shaped tile has [`GAP`] in the cells that aren't part of it.
+///
+/// Each tile is the lexicographically smallest of its equivalent rotations and flips, and the
+/// tiles come out in sorted order. The whole set is found before the first is returned, so check
+/// [`count`] before enumerating anything large.
+pub fn enumerate(spec: &TileSpec) -> Result<impl Iterator<Item = Vec<Vec<u8>>>, SpecError> {
+    spec.validate_enumerable()?;
+    let spec = *spec;
+    Ok(spec
+        .permutations()
+        .into_iter()
+        .map(move |permutation| spec.rows(&permutation)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Checks Burnside's count for `spec` against a brute-force search of every tile. Cases
+    /// must be small enough for that search to stay quick.
+    fn assert_count_matches_enumeration(spec: &TileSpec) {
+        let face: u64 = spec
+            .cells()
+            .allowed_sets()
+            .map(|allowed| {
+                allowed.bits_below(spec.n_colours).count_ones() as u64 * spec.orientation.states()
+            })
+            .product();
+        assert!(
+            face.pow(spec.faces() as u32) <= 1 << 13,
+            "{:?} is too big to check by enumeration",
+            spec
+        );
+        assert_eq!(
+            count(spec).unwrap(),
+            spec.permutations().len() as u64,
+            "{:?}",
+            spec
+        );
+    }
+
+    #[test]
+    fn test_readme_example() {
+        // 2×2 tiles in three colours under rotation, as worked through in the README
+        let spec = TileSpec {
+            width: 2,
+            height: 2,
+            n_colours: 3,
+            max_repeats: None,
+            allowed_xforms: AllowedTransformFamiles::Rotate,
+            orientation: Orientation::Symmetric,
+            two_sided: false,
+            shape: None,
+        };
+        let breakdown = breakdown(&spec).unwrap();
+        let fixed: Vec<_> = breakdown
+            .terms
+            .iter()
+            .map(|term| (term.elements, term.fixed))
+            .collect();
+        assert_eq!(fixed, vec![(1, 81), (2, 3), (1, 9)]);
+        assert_eq!(breakdown.total_fixed(), 96);
+        assert_eq!(breakdown.group_order(), 4);
+        assert_eq!(count(&spec), Ok(24));
+        assert_eq!(enumerate(&spec).unwrap().count(), 24);
+    }
+
+    #[test]
+    fn test_orbits_cover_every_tile() {
+        // Orbit–stabilizer: the orbits of the distinct tiles partition all the colourings
+        let spec = TileSpec {
+            width: 3,
+            height: 3,
+            n_colours: 2,
+            max_repeats: None,
+            allowed_xforms: AllowedTransformFamiles::RotateAndFlip,
+            orientation: Orientation::Symmetric,
+            two_sided: false,
+            shape: None,
+        };
+        let group_order = spec.breakdown().group_order();
+        let covered: u64 = spec
+            .permutations()
+            .iter()
+            .map(|perm| group_order / spec.stabilizer(perm))
+            .sum();
+        assert_eq!(covered, 1 << 9);
+    }
+
+    #[test]
+    fn test_oriented_markings() {
+        // Burnside's count has to match the orbits actually found, now markings turn too
+        for (orientation, width, height, n_colours, max_repeats) in [
+            (Orientation::Symmetric, 2, 2, 2, None),
+            (Orientation::Symmetric, 3, 3, 1, None),
+            (Orientation::Symmetric, 3, 2, 2, Some(3)),
+            (Orientation::Symmetric, 1, 1, 3, None),
+            (Orientation::Line, 2, 2, 2, None),
+            (Orientation::Line, 3, 3, 1, None),
+            (Orientation::Line, 3, 2, 2, Some(3)),
+            (Orientation::Line, 1, 1, 3, None),
+            (Orientation::Arrow, 2, 2, 2, None),
+            (Orientation::Arrow, 2, 2, 1, None),
+            (Orientation::Arrow, 3, 1, 2, Some(2)),
+            (Orientation::Arrow, 1, 1, 3, None),
+            (Orientation::Chiral, 2, 1, 2, None),
+            (Orientation::Chiral, 2, 2, 1, None),
+            (Orientation::Chiral, 2, 1, 2, Some(1)),
+            (Orientation::Chiral, 1, 1, 3, None),
+        ] {
+            for allowed_xforms in [
+                AllowedTransformFamiles::NoTransforms,
+                AllowedTransformFamiles::Rotate,
+                AllowedTransformFamiles::RotateAndFlip,
+            ] {
+                let spec = TileSpec {
+                    width,
+                    height,
+                    n_colours,
+                    max_repeats,
+                    allowed_xforms,
+                    orientation,
+                    two_sided: false,
+                    shape: None,
+                };
+                assert_count_matches_enumeration(&spec);
+            }
+        }
+        // A single arrow can be turned to point any way, but not an L-shape's mirror image
+        let mut spec = TileSpec {
+            width: 1,
+            height: 1,
+            n_colours: 1,
+            max_repeats: None,
+            allowed_xforms: AllowedTransformFamiles::Rotate,
+            orientation: Orientation::Arrow,
+            two_sided: false,
+            shape: None,
+        };
+        assert_eq!(count(&spec), Ok(1));
+        spec.orientation = Orientation::Chiral;
+        assert_eq!(count(&spec), Ok(2));
+        spec.n_colours = 5;
+        assert_eq!(spec.validate(), Err(SpecError::Colours { max: 4 }));
+    }
+
+    #[test]
+    fn test_two_sided() {
+        // Turning a two-sided tile over swaps its faces, so the count has to follow the orbits
+        for (orientation, width, height, n_colours, max_repeats) in [
+            (Orientation::Symmetric, 1, 1, 3, None),
+            (Orientation::Symmetric, 2, 1, 2, None),
+            (Orientation::Symmetric, 2, 2, 2, None),
+            (Orientation::Symmetric, 2, 2, 1, None),
+            (Orientation::Symmetric, 3, 1, 2, Some(3)),
+            (Orientation::Arrow, 1, 1, 3, None),
+            (Orientation::Arrow, 2, 1, 2, None),
+            (Orientation::Arrow, 2, 1, 1, None),
+            (Orientation::Arrow, 3, 1, 1, None),
+            (Orientation::Arrow, 2, 1, 2, Some(2)),
+        ] {
+            for allowed_xforms in [
+                AllowedTransformFamiles::NoTransforms,
+                AllowedTransformFamiles::Rotate,
+                AllowedTransformFamiles::RotateAndFlip,
+            ] {
+                let spec = TileSpec {
+                    width,
+                    height,
+                    n_colours,
+                    max_repeats,
+                    allowed_xforms,
+                    orientation,
+                    two_sided: true,
+                    shape: None,
+                };
+                assert_count_matches_enumeration(&spec);
+            }
+        }
+        // A one-cell token in two colours: which colour is on which side only matters if it
+        // can't be turned over
+        let mut spec = TileSpec {
+            width: 1,
+            height: 1,
+            n_colours: 2,
+            max_repeats: None,
+            allowed_xforms: AllowedTransformFamiles::Rotate,
+            orientation: Orientation::Symmetric,
+            two_sided: true,
+            shape: None,
+        };
+        assert_eq!(count(&spec), Ok(4));
+        spec.allowed_xforms = AllowedTransformFamiles::RotateAndFlip;
+        assert_eq!(count(&spec), Ok(3));
+        let tiles: Vec<_> = enumerate(&spec).unwrap().collect();
+        assert_eq!(tiles[1], [[0], [1]]);
+        // Both faces have to fit to be listed, though they can still be counted
+        spec.width = 5;
+        spec.height = 3;
+        assert!(count(&spec).is_ok());
+        assert_eq!(
+            enumerate(&spec).err(),
+            Some(SpecError::Size {
+                min: 1,
+                max: MAX_CELLS
+            })
+        );
+    }
+
+    #[test]
+    fn test_shapes() {
+        // Counting round the cycles gives the same terms as the rectangle formulas
+        for (width, height) in [(1, 1), (2, 2), (3, 2), (3, 3), (4, 4), (5, 3)] {
+            for orientation in Orientation::ALL {
+                for max_repeats in [None, Some(4)] {
+                    for two_sided in [false, true] {
+                        let spec = TileSpec {
+                            width,
+                            height,
+                            n_colours: 2,
+                            max_repeats,
+                            allowed_xforms: AllowedTransformFamiles::RotateAndFlip,
+                            orientation,
+                            two_sided,
+                            shape: None,
+                        };
+                        if spec.validate().is_err() {
+                            continue;
+                        }
+                        let rectangle = spec.rectangle_breakdown();
+                        let shaped = spec.shaped_breakdown();
+                        assert_eq!(rectangle.terms, shaped.terms, "{:?}", spec);
+                    }
+                }
+            }
+        }
+
+        // and the same as the tiles actually found. Arrows on five cells make too many tiles to
+        // search, so those shapes are marked with lines instead
+        for (shape, marking) in [
+            ("x./xx", Orientation::Arrow),
+            (".x./xxx/.x.", Orientation::Line),
+            ("xxx/.x.", Orientation::Arrow),
+            ("xx./.xx", Orientation::Arrow),
+            ("x.x/xxx", Orientation::Line),
+        ] {
+            let shape: Shape = shape.parse().unwrap();
+            for (orientation, two_sided, max_repeats) in [
+                (Orientation::Symmetric, false, None),
+                (Orientation::Symmetric, false, Some(2)),
+                (Orientation::Symmetric, true, None),
+                (marking, false, None),
+            ] {
+                for allowed_xforms in [
+                    AllowedTransformFamiles::NoTransforms,
+                    AllowedTransformFamiles::Rotate,
+                    AllowedTransformFamiles::RotateAndFlip,
+                ] {
+                    let spec = TileSpec {
+                        width: shape.width(),
+                        height: shape.height(),
+                        n_colours: 2,
+                        max_repeats,
+                        allowed_xforms,
+                        orientation,
+                        two_sided,
+                        shape: Some(shape),
+                    };
+                    assert_count_matches_enumeration(&spec);
+                }
+            }
+        }
+
+        // A plus sign has all eight symmetries of the square
+        let plus = ".x./xxx/.x.".parse().unwrap();
+        let spec = TileSpec {
+            width: 3,
+            height: 3,
+            n_colours: 2,
+            max_repeats: None,
+            allowed_xforms: AllowedTransformFamiles::RotateAndFlip,
+            orientation: Orientation::Symmetric,
+            two_sided: false,
+            shape: Some(plus),
+        };
+        let breakdown = breakdown(&spec).unwrap();
+        assert_eq!(breakdown.group_order(), 8);
+        assert_eq!(breakdown.count(), 12);
+        let first = enumerate(&spec).unwrap().next().unwrap();
+        assert_eq!(first, [[GAP, 0, GAP], [0, 0, 0], [GAP, 0, GAP]]);
+        // Shapes have to fill their grid
+        let spec = TileSpec {
+            shape: Some("..x/.xx".parse().unwrap()),
+            ..spec
+        };
+        assert_eq!(spec.validate(), Err(SpecError::Shape));
+    }
+
+    #[test]
+    fn test_fixed_cells() {
+        // Fixed cells are counted the same as the tiles actually found. Shapes with four free
+        // cells are marked with lines, as arrows would make too many tiles to search
+        for (shape, marking) in [
+            (".x./xAx/.x.", Orientation::Line),
+            ("Ax/xA", Orientation::Arrow),
+            ("Ax/xx", Orientation::Arrow),
+            (".B./xxx/.x.", Orientation::Line),
+            ("AxB", Orientation::Arrow),
+        ] {
+            let shape: Shape = shape.parse().unwrap();
+            for (orientation, two_sided, max_repeats) in [
+                (Orientation::Symmetric, false, None),
+                (Orientation::Symmetric, false, Some(3)),
+                (Orientation::Symmetric, true, Some(4)),
+                (marking, false, None),
+            ] {
+                let spec = TileSpec {
+                    width: shape.width(),
+                    height: shape.height(),
+                    n_colours: 3,
+                    max_repeats,
+                    allowed_xforms: AllowedTransformFamiles::RotateAndFlip,
+                    orientation,
+                    two_sided,
+                    shape: Some(shape),
+                };
+                assert_count_matches_enumeration(&spec);
+            }
+        }
+
+        // A logo in the middle leaves the other eight cells free to colour: half of the 102
+        // two-colour 3×3 tiles
+        let spec = TileSpec {
+            width: 3,
+            height: 3,
+            n_colours: 2,
+            max_repeats: None,
+            allowed_xforms: AllowedTransformFamiles::RotateAndFlip,
+            orientation: Orientation::Symmetric,
+            two_sided: false,
+            shape: Some("xxx/xBx/xxx".parse().unwrap()),
+        };
+        assert_eq!(count(&spec), Ok(51));
+        assert!(enumerate(&spec).unwrap().all(|design| design[1][1] == 1));
+        // Fixing a cell to only one use of its colour rules the colour out elsewhere
+        let spec = TileSpec {
+            max_repeats: Some(1),
+            n_colours: 3,
+            width: 2,
+            height: 1,
+            shape: Some("Ax".parse().unwrap()),
+            ..spec
+        };
+        assert_eq!(count(&spec), Ok(2));
+        let spec = TileSpec {
+            shape: Some("Dx".parse().unwrap()),
+            ..spec
+        };
+        assert_eq!(spec.validate(), Err(SpecError::Allowed));
+    }
+
+    #[test]
+    fn test_allowed_colours() {
+        // Cells limited to some colours are counted the same as the tiles actually found
+        for shape in [
+            ".[AB]./[AB]x[AB]/.[AB].",
+            "[AB]x/x[AC]",
+            "[BC]x/x[BC]",
+            ".[AC]./x[AB]x",
+            "[AB][BC][AC]",
+            "A[AB]/[AB]A",
+        ] {
+            let shape: Shape = shape.parse().unwrap();
+            for (orientation, two_sided, max_repeats) in [
+                (Orientation::Symmetric, false, None),
+                (Orientation::Symmetric, false, Some(2)),
+                (Orientation::Symmetric, false, Some(4)),
+                (Orientation::Symmetric, true, Some(3)),
+                (Orientation::Line, false, Some(3)),
+            ] {
+                let spec = TileSpec {
+                    width: shape.width(),
+                    height: shape.height(),
+                    n_colours: 3,
+                    max_repeats,
+                    allowed_xforms: AllowedTransformFamiles::RotateAndFlip,
+                    orientation,
+                    two_sided,
+                    shape: Some(shape),
+                };
+                assert_count_matches_enumeration(&spec);
+            }
+        }
+
+        // A square whose cells may only be the first two of three colours has the six tiles of
+        // two colours
+        let spec = TileSpec {
+            width: 2,
+            height: 2,
+            n_colours: 3,
+            max_repeats: None,
+            allowed_xforms: AllowedTransformFamiles::RotateAndFlip,
+            orientation: Orientation::Symmetric,
+            two_sided: false,
+            shape: Some("[AB][AB]/[AB][AB]".parse().unwrap()),
+        };
+        assert_eq!(count(&spec), Ok(6));
+        assert!(enumerate(&spec)
+            .unwrap()
+            .flatten()
+            .flatten()
+            .all(|colour| colour < 2));
+        // The diagonals of this one allow different colours, so they fix different numbers
+        let diagonal = TileSpec {
+            shape: Some("A[AB]/[AB]A".parse().unwrap()),
+            ..spec
+        };
+        let flips: Vec<_> = breakdown(&diagonal)
+            .unwrap()
+            .terms
+            .into_iter()
+            .filter(|term| term.transform == "Diagonal flip")
+            .map(|term| (term.elements, term.fixed))
+            .collect();
+        assert_eq!(flips, [(1, 2), (1, 4)]);
+        let spec = TileSpec {
+            shape: Some("[AD]x/xx".parse().unwrap()),
+            ..spec
+        };
+        assert_eq!(spec.validate(), Err(SpecError::Allowed));
+    }
+
+    #[test]
+    fn test_many_colours() {
+        let spec = TileSpec {
+            width: 2,
+            height: 1,
+            n_colours: MAX_COLOURS,
+            max_repeats: Some(1),
+            allowed_xforms: AllowedTransformFamiles::RotateAndFlip,
+            orientation: Orientation::Symmetric,
+            two_sided: false,
+            shape: None,
+        };
+        // Each pair of different colours, either way round
+        assert_eq!(count(&spec), Ok(26 * 25 / 2));
+        let tiles: Vec<_> = enumerate(&spec).unwrap().collect();
+        assert_eq!(tiles.len(), 26 * 25 / 2);
+        assert_eq!(tiles.last().unwrap(), &[[24, 25]]);
+    }
+}
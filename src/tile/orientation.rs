@@ -0,0 +1,224 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::{MAX_COLOURS, MAX_STATES};
+
+/// How much of its own symmetry a cell's marking lacks, and so how many ways round it can sit.
+///
+/// A marking's orientation is how far it's turned clockwise from pointing up, and for chiral
+/// markings whether it's mirrored. Turning or flipping the tile turns or flips every marking on
+/// it as well as moving it to another cell.
+///
+/// These serialise as `symmetric`, `line`, `arrow` and `chiral`, the same names [`FromStr`]
+/// accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    /// Markings look the same however they're turned, like dots.
+    #[default]
+    Symmetric,
+    /// Markings that run either across or down the cell, like bars.
+    Line,
+    /// Markings that point one of four ways and are their own mirror image, like arrows or
+    /// half-circles.
+    Arrow,
+    /// Markings that point one of four ways and come in a mirrored form too, like L-shapes.
+    Chiral,
+}
+use Orientation::*;
+
+impl FromStr for Orientation {
+    type Err = String;
+
+    /// Parses `symmetric`, `line`, `arrow` or `chiral`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "symmetric" => Ok(Symmetric),
+            "line" => Ok(Line),
+            "arrow" => Ok(Arrow),
+            "chiral" => Ok(Chiral),
+            _ => Err(format!("Unknown orientation {s:?}")),
+        }
+    }
+}
+
+/// A rotation or reflection of the tile, as it acts on a marking: a turn of `quarter_turns`
+/// clockwise, after a mirror left to right if `flip`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Motion {
+    quarter_turns: u8,
+    flip: bool,
+}
+
+impl Motion {
+    pub const IDENTITY: Motion = Motion::new(0, false);
+    /// The motions of `Permutation::rotate_90` and friends, which turn the tile anticlockwise.
+    pub const ROTATE_90: Motion = Motion::new(3, false);
+    pub const ROTATE_180: Motion = Motion::new(2, false);
+    pub const ROTATE_270: Motion = Motion::new(1, false);
+    pub const HFLIP: Motion = Motion::new(0, true);
+    pub const VFLIP: Motion = Motion::new(2, true);
+    pub const DFLIP1: Motion = Motion::new(3, true);
+    pub const DFLIP2: Motion = Motion::new(1, true);
+
+    /// Every rotation and reflection of a square, in the order `Transforms::images` lists them.
+    pub const ALL: [Motion; 8] = [
+        Motion::IDENTITY,
+        Motion::ROTATE_90,
+        Motion::ROTATE_270,
+        Motion::ROTATE_180,
+        Motion::DFLIP1,
+        Motion::DFLIP2,
+        Motion::HFLIP,
+        Motion::VFLIP,
+    ];
+
+    /// Whether the motion swaps the width and height, and so only works on a square.
+    pub(crate) fn needs_square(self) -> bool {
+        self.quarter_turns % 2 == 1
+    }
+
+    /// Where the cell at `(x, y)` of a `w`×`h` grid ends up: mirrored left to right if
+    /// `flip`, then turned clockwise.
+    pub(crate) fn place(self, x: usize, y: usize, w: usize, h: usize) -> (usize, usize) {
+        let (mut x, mut y, mut w, mut h) = (x, y, w, h);
+        if self.flip {
+            x = w - 1 - x;
+        }
+        for _ in 0..self.quarter_turns {
+            (x, y, w, h) = (h - 1 - y, x, h, w);
+        }
+        (x, y)
+    }
+
+    /// Whether this is one of the flips, which turn the tile over.
+    pub(crate) fn turns_over(self) -> bool {
+        self.flip
+    }
+
+    const fn new(quarter_turns: u8, flip: bool) -> Self {
+        Motion {
+            quarter_turns,
+            flip,
+        }
+    }
+}
+
+impl Orientation {
+    pub const ALL: [Orientation; 4] = [Symmetric, Line, Arrow, Chiral];
+
+    pub fn is_symmetric(&self) -> bool {
+        *self == Symmetric
+    }
+
+    /// How many ways round a marking can sit.
+    pub fn states(self) -> u64 {
+        match self {
+            Symmetric => 1,
+            Line => 2,
+            Arrow => 4,
+            Chiral => 8,
+        }
+    }
+
+    /// The most colours a tile can have with these markings, as every colour and orientation
+    /// must fit in a cell.
+    pub fn max_colours(self) -> u64 {
+        MAX_COLOURS.min(MAX_STATES / self.states())
+    }
+
+    /// The colour and orientation of a cell, from the value `enumerate` gives for it.
+    pub fn split(self, value: u8) -> (u8, u8) {
+        let states = self.states() as u8;
+        (value / states, value % states)
+    }
+
+    /// The value `enumerate` gives for a cell with this colour and orientation.
+    pub fn join(self, colour: u8, state: u8) -> u8 {
+        colour * self.states() as u8 + state
+    }
+
+    /// How far orientation `state` is turned clockwise from pointing up, in quarter turns, and
+    /// whether it's mirrored.
+    pub fn pose(self, state: u8) -> (u8, bool) {
+        (state % 4, state >= 4)
+    }
+
+    /// The orientation a marking in `state` ends up in after `motion`.
+    fn turn_state(self, state: u8, motion: Motion) -> u8 {
+        let (turns, mirrored) = self.pose(state);
+        let turns = match motion.flip {
+            true => (motion.quarter_turns + 4 - turns) % 4,
+            false => (motion.quarter_turns + turns) % 4,
+        };
+        match self {
+            Symmetric => 0,
+            Line => turns % 2,
+            Arrow => turns,
+            Chiral => turns + 4 * (mirrored != motion.flip) as u8,
+        }
+    }
+
+    /// The value of a cell after `motion` turns its marking.
+    pub(crate) fn turn(self, value: u8, motion: Motion) -> u8 {
+        let (colour, state) = self.split(value);
+        self.join(colour, self.turn_state(state, motion))
+    }
+
+    /// How many orientations `motion` leaves as they are.
+    pub(crate) fn fixed_by(self, motion: Motion) -> u64 {
+        self.fixed_by_repeated(motion, 1)
+    }
+
+    /// How many orientations come back to where they started after `motion` is done `times`
+    /// over, as a marking must to go round a cycle of that length.
+    pub(crate) fn fixed_by_repeated(self, motion: Motion, times: u64) -> u64 {
+        (0..self.states() as u8)
+            .filter(|&state| {
+                let turned = (0..times).fold(state, |state, _| self.turn_state(state, motion));
+                turned == state
+            })
+            .count() as u64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_fixed_orientations() {
+        let motions = [
+            Motion::IDENTITY,
+            Motion::ROTATE_90,
+            Motion::ROTATE_180,
+            Motion::ROTATE_270,
+            Motion::HFLIP,
+            Motion::VFLIP,
+            Motion::DFLIP1,
+            Motion::DFLIP2,
+        ];
+        let fixed = |orientation: Orientation| motions.map(|motion| orientation.fixed_by(motion));
+        assert_eq!(fixed(Symmetric), [1; 8]);
+        assert_eq!(fixed(Line), [2, 0, 2, 0, 2, 2, 0, 0]);
+        assert_eq!(fixed(Arrow), [4, 0, 0, 0, 2, 2, 0, 0]);
+        assert_eq!(fixed(Chiral), [8, 0, 0, 0, 0, 0, 0, 0]);
+
+        // An arrow pointing right points up once the tile turns anticlockwise, and left once it's
+        // mirrored
+        assert_eq!(
+            Arrow.turn(Arrow.join(2, 1), Motion::ROTATE_90),
+            Arrow.join(2, 0)
+        );
+        assert_eq!(
+            Arrow.turn(Arrow.join(2, 1), Motion::HFLIP),
+            Arrow.join(2, 3)
+        );
+        // Mirroring twice gets back to where it started
+        for state in 0..8 {
+            let flipped = Chiral.turn_state(state, Motion::VFLIP);
+            assert_ne!(flipped, state);
+            assert_eq!(Chiral.turn_state(flipped, Motion::VFLIP), state);
+        }
+    }
+}
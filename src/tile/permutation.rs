@@ -0,0 +1,496 @@
+use std::collections::HashSet;
+
+use bitvec::prelude::*;
+
+use super::{orientation::Motion, transforms::AllowedTransformFamiles, Orientation, Shape};
+use crate::{parallel::search_by_prefix, ColourSet};
+
+/// Bits per cell: enough for `MAX_STATES`.
+const BITS: usize = 5;
+const _: () = assert!(super::MAX_STATES <= 1 << BITS);
+
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+pub struct Permutation {
+    value: BitArr!(for 125, in u32, Msb0),
+}
+
+impl Default for Permutation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Permutation {
+    pub fn new() -> Self {
+        Self {
+            value: bitarr![u32, Msb0; 0; 125],
+        }
+    }
+    pub fn get(&self, x: usize, y: usize, w: usize) -> u8 {
+        let offset = y * w + x;
+        self.value[offset * BITS..(offset + 1) * BITS].load()
+    }
+    pub fn set(&mut self, x: usize, y: usize, w: usize, value: u8) {
+        let offset = y * w + x;
+        self.value[offset * BITS..(offset + 1) * BITS].store(value);
+    }
+    pub fn rotate_90(&self, w: usize, h: usize) -> Self {
+        assert_eq!(w, h);
+        let mut new = Self::new();
+        for x in 0..w {
+            for y in 0..h {
+                new.set(y, w - 1 - x, w, self.get(x, y, w));
+            }
+        }
+        new
+    }
+
+    pub fn rotate_180(&self, w: usize, h: usize) -> Self {
+        let mut new = Self::new();
+        for x in 0..w {
+            for y in 0..h {
+                new.set(w - 1 - x, h - 1 - y, w, self.get(x, y, w));
+            }
+        }
+        new
+    }
+
+    pub fn rotate_270(&self, w: usize, h: usize) -> Self {
+        assert_eq!(w, h);
+        let mut new = Self::new();
+        for x in 0..w {
+            for y in 0..h {
+                new.set(h - 1 - y, x, w, self.get(x, y, w));
+            }
+        }
+        new
+    }
+
+    pub fn hflip(&self, w: usize, h: usize) -> Self {
+        let mut new = Self::new();
+        for x in 0..w {
+            for y in 0..h {
+                new.set(w - 1 - x, y, w, self.get(x, y, w));
+            }
+        }
+        new
+    }
+
+    pub fn vflip(&self, w: usize, h: usize) -> Self {
+        let mut new = Self::new();
+        for x in 0..w {
+            for y in 0..h {
+                new.set(x, h - 1 - y, w, self.get(x, y, w));
+            }
+        }
+        new
+    }
+
+    pub fn dflip1(&self, w: usize, h: usize) -> Self {
+        assert_eq!(w, h);
+        let mut new = Self::new();
+        for x in 0..w {
+            for y in 0..h {
+                new.set(y, x, w, self.get(x, y, w));
+            }
+        }
+        new
+    }
+
+    pub fn dflip2(&self, w: usize, h: usize) -> Self {
+        assert_eq!(w, h);
+        let mut new = Self::new();
+        for x in 0..w {
+            for y in 0..h {
+                new.set(h - 1 - y, w - 1 - x, w, self.get(x, y, w));
+            }
+        }
+        new
+    }
+
+    /// The cells of a `w`×`h` grid moved by `motion`.
+    pub fn moved(&self, motion: Motion, w: usize, h: usize) -> Self {
+        match motion {
+            Motion::ROTATE_90 => self.rotate_90(w, h),
+            Motion::ROTATE_180 => self.rotate_180(w, h),
+            Motion::ROTATE_270 => self.rotate_270(w, h),
+            Motion::HFLIP => self.hflip(w, h),
+            Motion::VFLIP => self.vflip(w, h),
+            Motion::DFLIP1 => self.dflip1(w, h),
+            Motion::DFLIP2 => self.dflip2(w, h),
+            _ => self.clone(),
+        }
+    }
+
+    /// Turns the marking in each of `shape`'s cells by `motion`, after the cells themselves have
+    /// been moved.
+    pub fn reorient(mut self, shape: &Shape, orientation: Orientation, motion: Motion) -> Self {
+        if orientation != Orientation::Symmetric {
+            let w = shape.width() as usize;
+            for (x, y) in shape.positions() {
+                let value = self.get(x, y, w);
+                self.set(x, y, w, orientation.turn(value, motion));
+            }
+        }
+        self
+    }
+
+    /// The back of a two-sided tile with `cells` cells a face, moved to where the front goes.
+    pub fn back(&self, cells: usize) -> Self {
+        let mut new = Self::new();
+        for offset in 0..cells {
+            new.set(offset, 0, cells, self.get(cells + offset, 0, cells));
+        }
+        new
+    }
+
+    /// This tile's front, with the front of `back` behind it.
+    pub fn with_back(mut self, back: &Self, cells: usize) -> Self {
+        for offset in 0..cells {
+            self.set(cells + offset, 0, cells, back.get(offset, 0, cells));
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_square_rotation() {
+        let mut perm = Permutation::new();
+        perm.set(0, 0, 2, 1);
+        perm.set(1, 0, 2, 2);
+        perm.set(0, 1, 2, 3);
+        perm.set(1, 1, 2, 4);
+        let rot_90 = perm.rotate_90(2, 2);
+        let rot_270 = perm.rotate_270(2, 2);
+        let identity = rot_90.rotate_270(2, 2);
+        assert_eq!(perm, identity);
+        let identity = rot_270.rotate_90(2, 2);
+        assert_eq!(perm, identity);
+        let rot_90_90 = rot_90.rotate_90(2, 2);
+        let rot_180 = perm.rotate_180(2, 2);
+        assert_eq!(rot_90_90, rot_180);
+        let transform = Transforms::new(
+            Shape::rectangle(2, 2),
+            false,
+            AllowedTransformFamiles::Rotate,
+            Orientation::Symmetric,
+        );
+        assert_eq!(
+            transform.canonicalize(&perm),
+            transform.canonicalize(&rot_180)
+        );
+        assert_eq!(1, perm.get(0, 0, 2));
+        assert_eq!(2, perm.get(1, 0, 2));
+        assert_eq!(3, perm.get(0, 1, 2));
+        assert_eq!(4, perm.get(1, 1, 2));
+    }
+
+    #[test]
+    fn test_rect_rotation() {
+        let mut perm = Permutation::new();
+        perm.set(0, 0, 3, 1);
+        perm.set(1, 0, 3, 2);
+        perm.set(2, 0, 3, 3);
+        perm.set(0, 1, 3, 4);
+        perm.set(1, 1, 3, 5);
+        perm.set(2, 1, 3, 6);
+        let rot_180 = perm.rotate_180(3, 2);
+        let identity = rot_180.rotate_180(3, 2);
+        assert_eq!(perm, identity);
+        assert_eq!(perm.get(0, 0, 3), 1);
+        assert_eq!(perm.get(1, 0, 3), 2);
+        assert_eq!(perm.get(2, 0, 3), 3);
+        assert_eq!(perm.get(0, 1, 3), 4);
+        assert_eq!(perm.get(1, 1, 3), 5);
+        assert_eq!(perm.get(2, 1, 3), 6);
+        let transform = Transforms::new(
+            Shape::rectangle(3, 2),
+            false,
+            AllowedTransformFamiles::Rotate,
+            Orientation::Symmetric,
+        );
+        assert_eq!(
+            transform.canonicalize(&perm),
+            transform.canonicalize(&rot_180)
+        );
+    }
+
+    #[test]
+    fn test_markings_turn_with_the_tile() {
+        // An arrow pointing right in the top left corner
+        let arrow = Orientation::Arrow;
+        let mut perm = Permutation::new();
+        perm.set(0, 0, 2, arrow.join(1, 1));
+        let transforms = Transforms::new(
+            Shape::rectangle(2, 2),
+            false,
+            AllowedTransformFamiles::RotateAndFlip,
+            arrow,
+        );
+        let images = transforms.images(&perm);
+        // Turned anticlockwise it's in the bottom left, pointing up
+        assert_eq!(images[1].get(0, 1, 2), arrow.join(1, 0));
+        // Turned clockwise it's in the top right, pointing down
+        assert_eq!(images[2].get(1, 0, 2), arrow.join(1, 2));
+        // Mirrored left to right it's in the top right, pointing left
+        assert_eq!(images[6].get(1, 0, 2), arrow.join(1, 3));
+        // Every image is different, as nothing about the tile is symmetric
+        assert_eq!(transforms.stabilizer(&perm), 1);
+    }
+
+    #[test]
+    fn test_find_permutations_matches_serial() {
+        for (shape, n_colours, orientation, colour_limit) in [
+            ("xx/xx", 3, Orientation::Symmetric, u64::MAX),
+            ("xxx/xxx/xxx", 2, Orientation::Symmetric, 5),
+            ("xxx/xxx", 4, Orientation::Symmetric, 2),
+            ("xx/xx", 2, Orientation::Arrow, 3),
+            (".x./xxx/.x.", 3, Orientation::Symmetric, u64::MAX),
+        ] {
+            let shape: Shape = shape.parse().unwrap();
+            let (w, h) = (shape.width(), shape.height());
+            let transforms = Transforms::new(
+                shape,
+                false,
+                AllowedTransformFamiles::RotateAndFlip,
+                orientation,
+            );
+            let states = orientation.states();
+            let mut serial = HashSet::new();
+            build_permutations(
+                &transforms,
+                w,
+                h,
+                n_colours,
+                states,
+                0,
+                0,
+                &mut vec![0; n_colours as usize],
+                colour_limit,
+                Permutation::new(),
+                &mut serial,
+            );
+            assert_eq!(
+                serial,
+                find_permutations(&transforms, w, h, n_colours, states, colour_limit)
+            );
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn build_permutations(
+    transforms: &Transforms,
+    w: u64,
+    h: u64,
+    n_colours: u64,
+    states: u64,
+    mut x: u64,
+    mut y: u64,
+    colour_counts: &mut [u64],
+    colour_limit: u64,
+    mut permutation: Permutation,
+    seen: &mut HashSet<Permutation>,
+) {
+    if x == w {
+        x = 0;
+        y += 1;
+        if y == h {
+            // We're done; canonicalize and add to `seen`
+            seen.insert(transforms.canonicalize(&permutation));
+            return;
+        }
+    }
+
+    if x >= w || y >= h {
+        panic!("Out of bounds! {}/{} {}/{}", x, w, y, h);
+    }
+    if !transforms.is_cell(x as usize, y as usize) {
+        // A gap in the shape, which stays empty
+        build_permutations(
+            transforms,
+            w,
+            h,
+            n_colours,
+            states,
+            x + 1,
+            y,
+            colour_counts,
+            colour_limit,
+            permutation,
+            seen,
+        );
+        return;
+    }
+    let allowed = transforms.allowed(x as usize, y as usize);
+    for value in 0..n_colours * states {
+        let colour = value / states;
+        // Only try the colours the cell allows
+        if !allowed.contains(colour as u8) {
+            continue;
+        }
+        colour_counts[colour as usize] += 1;
+        if colour_counts[colour as usize] <= colour_limit {
+            permutation.set(x as usize, y as usize, w as usize, value as u8);
+            build_permutations(
+                transforms,
+                w,
+                h,
+                n_colours,
+                states,
+                x + 1,
+                y,
+                colour_counts,
+                colour_limit,
+                permutation.clone(),
+                seen,
+            );
+        }
+        colour_counts[colour as usize] -= 1;
+    }
+}
+
+/// Finds every canonical tile, splitting the search on the colours (and orientations) of the
+/// first few cells.
+pub fn find_permutations(
+    transforms: &Transforms,
+    w: u64,
+    h: u64,
+    n_colours: u64,
+    states: u64,
+    colour_limit: u64,
+) -> HashSet<Permutation> {
+    search_by_prefix(n_colours * states, w * h, |prefix, seen| {
+        let mut permutation = Permutation::new();
+        let mut colour_counts = vec![0; n_colours as usize];
+        for (offset, &value) in prefix.iter().enumerate() {
+            if !transforms.is_cell(offset % w as usize, offset / w as usize) {
+                // Gaps only ever hold zero
+                if value != 0 {
+                    return;
+                }
+                continue;
+            }
+            let colour = value as usize / states as usize;
+            if !transforms
+                .allowed(offset % w as usize, offset / w as usize)
+                .contains(colour as u8)
+            {
+                return;
+            }
+            colour_counts[colour] += 1;
+            if colour_counts[colour] > colour_limit {
+                return;
+            }
+            permutation.set(offset % w as usize, offset / w as usize, w as usize, value);
+        }
+        let offset = prefix.len() as u64;
+        build_permutations(
+            transforms,
+            w,
+            h,
+            n_colours,
+            states,
+            offset % w,
+            offset / w,
+            &mut colour_counts,
+            colour_limit,
+            permutation,
+            seen,
+        );
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transforms {
+    shape: Shape,
+    two_sided: bool,
+    allowed_families: AllowedTransformFamiles,
+    orientation: Orientation,
+}
+
+impl Transforms {
+    pub fn new(
+        shape: Shape,
+        two_sided: bool,
+        allowed_families: AllowedTransformFamiles,
+        orientation: Orientation,
+    ) -> Self {
+        Transforms {
+            shape,
+            two_sided,
+            allowed_families,
+            orientation,
+        }
+    }
+
+    /// `perm` transformed by every element of the group, starting with the identity.
+    ///
+    /// A two-sided tile has each face moved the same way, then swapped if the tile was turned over.
+    pub fn images(self, perm: &Permutation) -> Vec<Permutation> {
+        let front = self.face_images(perm);
+        if !self.two_sided {
+            return front.into_iter().map(|(image, _)| image).collect();
+        }
+        let cells = (self.shape.width() * self.shape.height()) as usize;
+        let back = self.face_images(&perm.back(cells));
+        front
+            .into_iter()
+            .zip(back)
+            .map(|((front, turned_over), (back, _))| {
+                if turned_over {
+                    back.with_back(&front, cells)
+                } else {
+                    front.with_back(&back, cells)
+                }
+            })
+            .collect()
+    }
+
+    /// The front face of `perm` transformed by every element of the group, and whether each
+    /// element turns the tile over. The group is whichever allowed motions land the shape on
+    /// itself.
+    fn face_images(self, perm: &Permutation) -> Vec<(Permutation, bool)> {
+        let w = self.shape.width() as usize;
+        let h = self.shape.height() as usize;
+        let mut images = vec![(perm.clone(), false)];
+        for motion in &Motion::ALL[1..] {
+            if self.allowed_families.allows(*motion) && self.shape.symmetric_under(*motion) {
+                let image = perm.moved(*motion, w, h);
+                images.push((
+                    image.reorient(&self.shape, self.orientation, *motion),
+                    motion.turns_over(),
+                ));
+            }
+        }
+        images
+    }
+
+    /// Whether `(x, y)` is a cell of the tile, rather than a gap in its shape. The back of a
+    /// two-sided tile has rows `h..2 * h`.
+    pub fn is_cell(&self, x: usize, y: usize) -> bool {
+        self.shape.contains(x, y % self.shape.height() as usize)
+    }
+
+    /// The colours the cell at `(x, y)` may take. The back of a two-sided tile is limited the
+    /// same as the front.
+    pub fn allowed(&self, x: usize, y: usize) -> ColourSet {
+        self.shape.allowed(x, y % self.shape.height() as usize)
+    }
+
+    pub fn canonicalize(self, perm: &Permutation) -> Permutation {
+        self.images(perm).into_iter().min().unwrap()
+    }
+
+    /// How many elements of the group leave `perm` unchanged.
+    #[cfg_attr(not(feature = "gui"), allow(dead_code))]
+    pub fn stabilizer(self, perm: &Permutation) -> u64 {
+        self.images(perm)
+            .iter()
+            .filter(|image| *image == perm)
+            .count() as u64
+    }
+}
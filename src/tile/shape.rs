@@ -0,0 +1,338 @@
+use std::{convert::TryFrom, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use super::{orientation::Motion, MAX_CELLS};
+use crate::ColourSet;
+
+/// Which cells of a tile's grid are part of it, for tiles that aren't rectangles: L-shapes,
+/// crosses, and other polyominoes. Cells can also be limited to some colours before counting,
+/// like a logo that's always in the middle or corners that may only be red or blue.
+///
+/// These serialise as rows of `x` for a cell, `.` for a gap, a capital letter for a cell fixed
+/// to that colour, or letters in brackets for a cell limited to those, separated by `/`:
+/// `.x./xAx/.x.` is a plus sign with the first colour in the middle. [`FromStr`] reads the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Shape {
+    width: u8,
+    height: u8,
+    /// One bit per cell of the grid, row by row.
+    cells: u32,
+    /// The colours each cell of the grid may take, row by row.
+    allowed: [ColourSet; MAX_CELLS as usize],
+}
+
+impl Shape {
+    /// The whole of a `width`×`height` grid.
+    pub fn rectangle(width: u64, height: u64) -> Self {
+        Self::from_fn(width, height, |_, _| true)
+    }
+
+    /// The cells of a `width`×`height` grid for which `contains` is true.
+    pub fn from_fn(width: u64, height: u64, contains: impl Fn(usize, usize) -> bool) -> Self {
+        assert!(
+            width * height <= MAX_CELLS,
+            "{}×{} is too big",
+            width,
+            height
+        );
+        let mut cells = 0;
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                if contains(x, y) {
+                    cells |= 1 << (y * width as usize + x);
+                }
+            }
+        }
+        Shape {
+            width: width as u8,
+            height: height as u8,
+            cells,
+            allowed: [ColourSet::ANY; MAX_CELLS as usize],
+        }
+    }
+
+    /// The same shape with the cell at `(x, y)` part of it and limited to `colours`.
+    pub fn with_allowed(mut self, x: usize, y: usize, colours: ColourSet) -> Self {
+        let offset = y * self.width as usize + x;
+        self.cells |= 1 << offset;
+        self.allowed[offset] = colours;
+        self
+    }
+
+    /// The same shape with the cell at `(x, y)` part of it and fixed to `colour`.
+    pub fn with_fixed(self, x: usize, y: usize, colour: u8) -> Self {
+        self.with_allowed(x, y, ColourSet::only(colour))
+    }
+
+    pub fn width(&self) -> u64 {
+        self.width as u64
+    }
+
+    pub fn height(&self) -> u64 {
+        self.height as u64
+    }
+
+    /// Whether the cell at `(x, y)` is part of the tile.
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x < self.width as usize
+            && y < self.height as usize
+            && self.cells & 1 << (y * self.width as usize + x) != 0
+    }
+
+    /// The colours the cell at `(x, y)` may take: any, unless it's part of the tile and limited.
+    pub fn allowed(&self, x: usize, y: usize) -> ColourSet {
+        if self.contains(x, y) {
+            self.allowed[y * self.width as usize + x]
+        } else {
+            ColourSet::ANY
+        }
+    }
+
+    /// The colour the cell at `(x, y)` is fixed to, if it may only take one.
+    pub fn fixed(&self, x: usize, y: usize) -> Option<u8> {
+        self.allowed(x, y).single()
+    }
+
+    /// Whether any cells are limited to some colours.
+    pub fn is_limited(&self) -> bool {
+        self.positions().any(|(x, y)| !self.allowed(x, y).is_any())
+    }
+
+    /// The colours each cell may take, row by row.
+    pub fn allowed_sets(&self) -> impl Iterator<Item = ColourSet> + '_ {
+        self.positions().map(move |(x, y)| self.allowed(x, y))
+    }
+
+    /// How many cells the tile has.
+    pub fn len(&self) -> u64 {
+        self.cells.count_ones() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells == 0
+    }
+
+    pub fn is_rectangle(&self) -> bool {
+        self.len() == self.width() * self.height()
+    }
+
+    /// The same cells in the smallest grid that holds them.
+    pub fn trimmed(&self) -> Self {
+        let cells: Vec<_> = self.positions().collect();
+        let left = cells.iter().map(|&(x, _)| x).min().unwrap_or(0);
+        let top = cells.iter().map(|&(_, y)| y).min().unwrap_or(0);
+        let right = cells.iter().map(|&(x, _)| x + 1).max().unwrap_or(0);
+        let bottom = cells.iter().map(|&(_, y)| y + 1).max().unwrap_or(0);
+        let mut trimmed = Self::from_fn((right - left) as u64, (bottom - top) as u64, |x, y| {
+            self.contains(left + x, top + y)
+        });
+        for (x, y) in trimmed.positions().collect::<Vec<_>>() {
+            trimmed = trimmed.with_allowed(x, y, self.allowed(left + x, top + y));
+        }
+        trimmed
+    }
+
+    /// Each cell of the tile, as `(x, y)`, row by row.
+    pub fn positions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let (width, height) = (self.width as usize, self.height as usize);
+        (0..height)
+            .flat_map(move |y| (0..width).map(move |x| (x, y)))
+            .filter(move |&(x, y)| self.contains(x, y))
+    }
+
+    /// Whether `motion` lands the shape on itself, with every cell on one allowed the same
+    /// colours, so it's one of the tile's symmetries.
+    pub(crate) fn symmetric_under(&self, motion: Motion) -> bool {
+        let (w, h) = (self.width as usize, self.height as usize);
+        if motion.needs_square() && w != h {
+            return false;
+        }
+        self.positions().all(|(x, y)| {
+            let (to_x, to_y) = motion.place(x, y, w, h);
+            self.contains(to_x, to_y) && self.allowed(to_x, to_y) == self.allowed(x, y)
+        })
+    }
+
+    /// The cycles `motion` moves the cells round, as their lengths and the colours all their
+    /// cells allow, which are the only ones the cycle can be if `motion` leaves it unchanged. A
+    /// two-sided tile has every cell on the back too, behind the one at the same place on the
+    /// front and limited the same way, and swaps its faces when `motion` turns it over.
+    pub(crate) fn cycles(&self, motion: Motion, two_sided: bool) -> Vec<(u64, ColourSet)> {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let faces = if two_sided { 2 } else { 1 };
+        let index = |face: usize, x: usize, y: usize| (face * h + y) * w + x;
+        let mut seen = vec![false; faces * w * h];
+        let mut cycles = vec![];
+        for face in 0..faces {
+            for (x, y) in self.positions() {
+                let mut length = 0;
+                let mut allowed = ColourSet::ANY;
+                let (mut face, mut x, mut y) = (face, x, y);
+                while !seen[index(face, x, y)] {
+                    seen[index(face, x, y)] = true;
+                    length += 1;
+                    allowed = allowed.intersection(self.allowed(x, y));
+                    (x, y) = motion.place(x, y, w, h);
+                    if two_sided && motion.turns_over() {
+                        face = 1 - face;
+                    }
+                }
+                if length > 0 {
+                    cycles.push((length, allowed));
+                }
+            }
+        }
+        cycles
+    }
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..self.height as usize {
+            if y > 0 {
+                f.write_str("/")?;
+            }
+            for x in 0..self.width as usize {
+                if self.contains(x, y) {
+                    write!(f, "{}", self.allowed(x, y))?;
+                } else {
+                    f.write_str(".")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Shape {
+    type Err = String;
+
+    /// Parses rows of `x`, `.`, capital letters and bracketed letters separated by `/`, all the
+    /// same length.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = s
+            .split('/')
+            .map(ColourSet::parse_cells)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("{e} in shape {s:?}"))?;
+        let width = rows[0].len();
+        if rows.iter().any(|row| row.len() != width) {
+            return Err(format!(
+                "The rows of shape {s:?} aren't all the same length"
+            ));
+        }
+        if (width * rows.len()) as u64 > MAX_CELLS {
+            return Err(format!("Shape {s:?} has more than {MAX_CELLS} cells"));
+        }
+        let mut shape = Self::from_fn(width as u64, rows.len() as u64, |_, _| false);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                if let Some(colours) = *cell {
+                    shape = shape.with_allowed(x, y, colours);
+                }
+            }
+        }
+        Ok(shape)
+    }
+}
+
+impl TryFrom<String> for Shape {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Shape> for String {
+    fn from(shape: Shape) -> Self {
+        shape.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_symmetries() {
+        let symmetries = |shape: &str| -> Vec<bool> {
+            let shape: Shape = shape.parse().unwrap();
+            Motion::ALL
+                .map(|motion| shape.symmetric_under(motion))
+                .to_vec()
+        };
+        // Identity, 90°, 270°, 180°, both diagonals, then horizontal and vertical flips
+        let all = vec![true; 8];
+        assert_eq!(symmetries(".x./xxx/.x."), all);
+        assert_eq!(
+            symmetries("x./xx"),
+            [true, false, false, false, false, true, false, false]
+        );
+        assert_eq!(
+            symmetries("xxx/.x."),
+            [true, false, false, false, false, false, true, false]
+        );
+        assert_eq!(
+            symmetries("xx./.xx"),
+            [true, false, false, true, false, false, false, false]
+        );
+
+        // A plus sign's centre stays put, and the arms go round together
+        let plus: Shape = ".x./xxx/.x.".parse().unwrap();
+        let lengths = |motion, two_sided| -> Vec<u64> {
+            plus.cycles(motion, two_sided)
+                .into_iter()
+                .map(|(length, _)| length)
+                .collect()
+        };
+        assert_eq!(lengths(Motion::ROTATE_90, false), [4, 1]);
+        assert_eq!(lengths(Motion::HFLIP, false), [1, 2, 1, 1]);
+        assert_eq!(lengths(Motion::HFLIP, true), [2; 5]);
+        assert_eq!(plus.to_string(), ".x./xxx/.x.");
+        assert_eq!(plus.len(), 5);
+
+        // Fixed cells only go to cells fixed the same way
+        assert_eq!(symmetries(".x./xBx/.x."), all);
+        assert_eq!(
+            symmetries("Ax/xA"),
+            [true, false, false, true, true, true, false, false]
+        );
+        let logo: Shape = "Ax/xA".parse().unwrap();
+        assert_eq!(
+            logo.cycles(Motion::ROTATE_180, false),
+            [(2, ColourSet::only(0)), (2, ColourSet::ANY)]
+        );
+        // and cells limited to some colours only go to cells limited to the same ones
+        assert_eq!(
+            symmetries("[AB]x[AB]/xxx/[AB]x[AB]"),
+            symmetries(".x./xxx/.x.")
+        );
+        assert_eq!(
+            symmetries("[AB]x/x[AC]"),
+            [true, false, false, false, true, false, false, false]
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "xx/x".parse::<Shape>().map(|_| ()),
+            Err("The rows of shape \"xx/x\" aren't all the same length".to_string())
+        );
+        assert!("x#".parse::<Shape>().is_err());
+        let shape: Shape = "..../.xx./..C.".parse().unwrap();
+        assert_eq!(shape.trimmed().to_string(), "xx/.C");
+        assert_eq!(shape.trimmed().fixed(1, 1), Some(2));
+        let shape: Shape = "[AB]x/.[BA]".parse().unwrap();
+        assert_eq!(shape.to_string(), "[AB]x/.[AB]");
+        assert!(shape.is_limited());
+        assert!("x[A/x".parse::<Shape>().is_err());
+        assert!(Shape::rectangle(3, 2).is_rectangle());
+        assert_eq!(
+            serde_json::to_string(&Shape::rectangle(2, 1)).unwrap(),
+            "\"xx\""
+        );
+    }
+}
@@ -1,16 +1,60 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Which ways a tile may be moved before two tiles count as the same.
+///
+/// These serialise as `none`, `rotate` and `rotate-flip`, the same names [`FromStr`] accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum AllowedTransformFamiles {
+    /// Tiles are fixed in place, so every colouring is distinct.
+    #[serde(rename = "none")]
     NoTransforms,
+    /// Tiles may be turned: by 180°, or by 90° if they're square.
+    #[default]
+    #[serde(rename = "rotate")]
     Rotate,
+    /// Tiles may be turned over as well as turned.
+    #[serde(rename = "rotate-flip")]
     RotateAndFlip,
 }
 use AllowedTransformFamiles::*;
 
-use crate::app::bag_draw::count3;
+impl AllowedTransformFamiles {
+    /// Whether `motion` is one of the moves these families allow.
+    pub(crate) fn allows(self, motion: Motion) -> bool {
+        match self {
+            NoTransforms => motion == Motion::IDENTITY,
+            Rotate => !motion.turns_over(),
+            RotateAndFlip => true,
+        }
+    }
+}
+
+impl FromStr for AllowedTransformFamiles {
+    type Err = String;
+
+    /// Parses `none`, `rotate` or `rotate-flip`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(NoTransforms),
+            "rotate" => Ok(Rotate),
+            "rotate-flip" => Ok(RotateAndFlip),
+            _ => Err(format!("Unknown transform family {s:?}")),
+        }
+    }
+}
+
+use super::{orientation::Motion, Orientation};
+use crate::bag_draw::count3;
 
 pub trait Transform {
     const ORBITS: u64;
     const ORBIT_SIZE: u64 = 2;
+    /// What the transform (or one of them, when there are two) does to each marking.
+    const MOTION: Motion;
+    /// Whether the transform turns the tile over, which swaps the faces of a two-sided tile.
+    const TURNS_OVER: bool = false;
     fn applicable(allowed_families: AllowedTransformFamiles, square: bool) -> bool;
     fn base_fixed(w: u64, h: u64) -> u64;
     fn free(w: u64, h: u64) -> u64 {
@@ -18,22 +62,45 @@ pub trait Transform {
         let unfixed = w * h - fixed * Self::ORBIT_SIZE;
         fixed + unfixed
     }
-    fn n_fixed(w: u64, h: u64, n_colours: u64) -> u64 {
-        n_colours.pow(Self::free(w, h) as u32)
+    /// How the transform moves the cells, as (orbits, orbit size, cells left where they are).
+    ///
+    /// A second face has the same cycles again, unless the transform turns the tile over: then
+    /// every cell swaps places with the one that lands behind it.
+    fn cycles(w: u64, h: u64, two_sided: bool) -> (u64, u64, u64) {
+        let fixed = Self::base_fixed(w, h);
+        let unmoved = Self::free(w, h) - fixed;
+        match (two_sided, Self::TURNS_OVER) {
+            (false, _) => (fixed, Self::ORBIT_SIZE, unmoved),
+            (true, false) => (2 * fixed, Self::ORBIT_SIZE, 2 * unmoved),
+            (true, true) => (w * h, 2, 0),
+        }
+    }
+    /// Every transform undoes itself after `ORBIT_SIZE` goes, so a marking can go round an
+    /// orbit in any orientation; one that stays put must be in an orientation the transform
+    /// leaves alone.
+    fn orientations(w: u64, h: u64, two_sided: bool, orientation: Orientation) -> u64 {
+        let (orbits, _, unmoved) = Self::cycles(w, h, two_sided);
+        orientation.states().pow(orbits as u32)
+            * orientation.fixed_by(Self::MOTION).pow(unmoved as u32)
     }
-    fn total_fixed(w: u64, h: u64, n_colours: u64) -> u64 {
-        Self::n_fixed(w, h, n_colours) * Self::ORBITS
+    fn n_fixed(w: u64, h: u64, two_sided: bool, n_colours: u64, orientation: Orientation) -> u64 {
+        let (orbits, _, unmoved) = Self::cycles(w, h, two_sided);
+        n_colours.pow((orbits + unmoved) as u32) * Self::orientations(w, h, two_sided, orientation)
     }
-    fn limited_n_fixed(w: u64, h: u64, n_colours: u64, max_repeats: u64) -> u64 {
+    fn limited_n_fixed(
+        w: u64,
+        h: u64,
+        two_sided: bool,
+        n_colours: u64,
+        max_repeats: u64,
+        orientation: Orientation,
+    ) -> u64 {
         let mut counts = vec![0; max_repeats as usize + 1];
         counts[max_repeats as usize] = n_colours;
-        let fixed = Self::base_fixed(w, h);
-        let mut draws = vec![Self::ORBIT_SIZE as u8; fixed as usize];
-        draws.resize(Self::free(w, h) as usize, 1);
-        count3(&draws, &mut counts)
-    }
-    fn limited_total_fixed(w: u64, h: u64, n_colours: u64, max_repeats: u64) -> u64 {
-        Self::limited_n_fixed(w, h, n_colours, max_repeats) * Self::ORBITS
+        let (orbits, orbit_size, unmoved) = Self::cycles(w, h, two_sided);
+        let mut draws = vec![orbit_size as u8; orbits as usize];
+        draws.resize((orbits + unmoved) as usize, 1);
+        count3(&draws, &mut counts) * Self::orientations(w, h, two_sided, orientation)
     }
 }
 
@@ -41,6 +108,7 @@ pub struct NoXform;
 impl Transform for NoXform {
     const ORBITS: u64 = 1;
     const ORBIT_SIZE: u64 = 1;
+    const MOTION: Motion = Motion::IDENTITY;
     fn applicable(_allowed_families: AllowedTransformFamiles, _square: bool) -> bool {
         true
     }
@@ -54,6 +122,7 @@ pub struct Rot90;
 impl Transform for Rot90 {
     const ORBITS: u64 = 2;
     const ORBIT_SIZE: u64 = 4;
+    const MOTION: Motion = Motion::ROTATE_90;
 
     fn applicable(allowed_families: AllowedTransformFamiles, square: bool) -> bool {
         matches!(allowed_families, Rotate | RotateAndFlip) && square
@@ -73,6 +142,7 @@ impl Transform for Rot90 {
 pub struct Rot180;
 impl Transform for Rot180 {
     const ORBITS: u64 = 1;
+    const MOTION: Motion = Motion::ROTATE_180;
 
     fn applicable(allowed_families: AllowedTransformFamiles, _square: bool) -> bool {
         matches!(allowed_families, Rotate | RotateAndFlip)
@@ -91,6 +161,8 @@ impl Transform for Rot180 {
 pub struct HFlip;
 impl Transform for HFlip {
     const ORBITS: u64 = 1;
+    const MOTION: Motion = Motion::HFLIP;
+    const TURNS_OVER: bool = true;
 
     fn applicable(allowed_families: AllowedTransformFamiles, _square: bool) -> bool {
         allowed_families == RotateAndFlip
@@ -104,6 +176,8 @@ impl Transform for HFlip {
 pub struct VFlip;
 impl Transform for VFlip {
     const ORBITS: u64 = 1;
+    const MOTION: Motion = Motion::VFLIP;
+    const TURNS_OVER: bool = true;
 
     fn applicable(allowed_families: AllowedTransformFamiles, _square: bool) -> bool {
         allowed_families == RotateAndFlip
@@ -117,6 +191,8 @@ impl Transform for VFlip {
 pub struct DFlip;
 impl Transform for DFlip {
     const ORBITS: u64 = 2;
+    const MOTION: Motion = Motion::DFLIP1;
+    const TURNS_OVER: bool = true;
 
     fn applicable(allowed_families: AllowedTransformFamiles, square: bool) -> bool {
         allowed_families == RotateAndFlip && square